@@ -79,6 +79,50 @@ impl From<Infallible> for Error {
     }
 }
 
+/// Error returned as [`Error::Custom`] by
+/// [`Template::render_into_limited()`](crate::Template::render_into_limited) when the output
+/// exceeds the limit, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded(pub usize);
+
+impl std::error::Error for LimitExceeded {}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "output exceeds the limit of {} bytes", self.0)
+    }
+}
+
+/// Error returned by [`Template::render_into_fallible()`](crate::Template::render_into_fallible)
+///
+/// It keeps the error of the writer separate from the other errors, so it is not lost.
+#[derive(Debug)]
+pub enum RenderError<E> {
+    /// the writer returned an error
+    Write(E),
+
+    /// any other error while rendering the template
+    Render(Error),
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for RenderError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Write(err) => Some(err),
+            RenderError::Render(err) => Some(err),
+        }
+    }
+}
+
+impl<E: Display> Display for RenderError<E> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Write(err) => write!(formatter, "write error: {err}"),
+            RenderError::Render(err) => write!(formatter, "{err}"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Error;
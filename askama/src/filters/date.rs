@@ -0,0 +1,114 @@
+use time::format_description::parse_strftime_borrowed;
+use time::format_description::BorrowedFormatItem;
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+use crate::error::Error::Custom;
+use crate::Result;
+
+/// Dates and times that can be formatted with the [`date`] filter (requires `time` feature)
+pub trait FormatDate {
+    /// Formats the value with the parsed `format`
+    fn format_date(&self, format: &[BorrowedFormatItem<'_>])
+        -> Result<String, time::error::Format>;
+}
+
+macro_rules! impl_format_date {
+    ($($ty:ty),*) => {
+        $(
+            impl FormatDate for $ty {
+                #[inline]
+                fn format_date(
+                    &self,
+                    format: &[BorrowedFormatItem<'_>],
+                ) -> Result<String, time::error::Format> {
+                    self.format(format)
+                }
+            }
+        )*
+    };
+}
+
+impl_format_date!(OffsetDateTime, PrimitiveDateTime, Date, Time);
+
+impl<T: FormatDate + ?Sized> FormatDate for &T {
+    #[inline]
+    fn format_date(
+        &self,
+        format: &[BorrowedFormatItem<'_>],
+    ) -> Result<String, time::error::Format> {
+        T::format_date(*self, format)
+    }
+}
+
+/// Formats a date or time with a `strftime`-style `format` (requires `time` feature)
+///
+/// An [`OffsetDateTime`] is formatted in its own offset, e.g. `%H:%M %z` of a time in UTC+2
+/// renders `14:30 +0200`. The format must not use components the value does not have, e.g.
+/// `%z` of a [`PrimitiveDateTime`] or `%H` of a [`Date`] is an error. Like the output of other
+/// filters, the output is escaped.
+///
+/// ```
+/// # use askama::Template;
+/// # use time::{Date, Month, PrimitiveDateTime, Time};
+/// #[derive(Template)]
+/// #[template(source = r#"{{ created_at|date("%Y-%m-%d %H:%M") }}"#, ext = "txt")]
+/// struct Post {
+///     created_at: PrimitiveDateTime,
+/// }
+///
+/// let created_at = PrimitiveDateTime::new(
+///     Date::from_calendar_date(2024, Month::March, 9).unwrap(),
+///     Time::from_hms(18, 5, 0).unwrap(),
+/// );
+/// assert_eq!(Post { created_at }.to_string(), "2024-03-09 18:05");
+/// ```
+#[inline]
+pub fn date(value: impl FormatDate, format: &str) -> Result<String> {
+    let format = parse_strftime_borrowed(format).map_err(|err| Custom(Box::new(err)))?;
+    value
+        .format_date(&format)
+        .map_err(|err| Custom(Box::new(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Month, UtcOffset};
+
+    use super::*;
+
+    fn date_time() -> PrimitiveDateTime {
+        PrimitiveDateTime::new(
+            Date::from_calendar_date(2024, Month::March, 9).unwrap(),
+            Time::from_hms(18, 5, 7).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(date_time(), "%Y-%m-%d").unwrap(), "2024-03-09");
+        assert_eq!(date(date_time(), "%H:%M:%S").unwrap(), "18:05:07");
+        assert_eq!(date(date_time(), "%d %B %Y").unwrap(), "09 March 2024");
+        assert_eq!(date(date_time(), "100%%").unwrap(), "100%");
+        assert_eq!(date(date_time().date(), "%a %b %e").unwrap(), "Sat Mar  9");
+        let time: &Time = &date_time().time();
+        assert_eq!(date(time, "%I:%M %p").unwrap(), "06:05 PM");
+    }
+
+    #[test]
+    fn test_date_offset() {
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        let value = date_time().assume_utc().to_offset(offset);
+        assert_eq!(date(value, "%H:%M %z").unwrap(), "20:05 +0200");
+        assert_eq!(
+            date(date_time().assume_utc(), "%H:%M %z").unwrap(),
+            "18:05 +0000"
+        );
+    }
+
+    #[test]
+    fn test_date_errors() {
+        assert!(date(date_time(), "%z").is_err());
+        assert!(date(date_time().date(), "%H").is_err());
+        assert!(date(date_time(), "%Q").is_err());
+    }
+}
@@ -2,11 +2,15 @@ use std::convert::Infallible;
 use std::{fmt, io, str};
 
 use serde::Serialize;
-use serde_json::to_writer_pretty;
+use serde_json::ser::{PrettyFormatter, Serializer};
 
 /// Serialize to JSON (requires `json` feature)
 ///
-/// The generated string does not contain ampersands `&`, chevrons `< >`, or apostrophes `'`.
+/// The output is indented by two spaces, use [`json_pretty`] (`{{ data|json(4) }}` in templates)
+/// to use another indentation.
+///
+/// The generated string does not contain ampersands `&`, chevrons `< >`, apostrophes `'`, or the
+/// line and paragraph separators U+2028 and U+2029.
 /// To use it in a `<script>` you can combine it with the safe filter:
 ///
 /// ``` html
@@ -20,16 +24,33 @@ use serde_json::to_writer_pretty;
 /// In HTML texts the output of e.g. `<pre>{{data|json|safe}}</pre>` is safe, too.
 #[inline]
 pub fn json<S: Serialize>(s: S) -> Result<impl fmt::Display, Infallible> {
-    Ok(ToJson(s))
+    json_pretty(s, 2)
+}
+
+/// Serialize to JSON indented by `indent` spaces (requires `json` feature)
+///
+/// This is the [`json`] filter with an argument: `{{ data|json(4) }}`. The output is escaped
+/// in the same way.
+#[inline]
+pub fn json_pretty<S: Serialize>(s: S, indent: usize) -> Result<impl fmt::Display, Infallible> {
+    Ok(ToJson { value: s, indent })
 }
 
 #[derive(Debug, Clone)]
-struct ToJson<S: Serialize>(S);
+struct ToJson<S: Serialize> {
+    value: S,
+    indent: usize,
+}
 
 impl<S: Serialize> fmt::Display for ToJson<S> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        to_writer_pretty(JsonWriter(f), &self.0).map_err(|_| fmt::Error)
+        let indent = vec![b' '; self.indent];
+        let mut serializer =
+            Serializer::with_formatter(JsonWriter(f), PrettyFormatter::with_indent(&indent));
+        self.value
+            .serialize(&mut serializer)
+            .map_err(|_| fmt::Error)
     }
 }
 
@@ -56,18 +77,22 @@ impl io::Write for JsonWriter<'_, '_> {
 fn write(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
     let mut last = 0;
     for (index, byte) in bytes.iter().enumerate() {
-        let escaped = match byte {
-            b'&' => Some(br"\u0026"),
-            b'\'' => Some(br"\u0027"),
-            b'<' => Some(br"\u003c"),
-            b'>' => Some(br"\u003e"),
-            _ => None,
+        let (escaped, len) = match byte {
+            b'&' => (br"\u0026", 1),
+            b'\'' => (br"\u0027", 1),
+            b'<' => (br"\u003c", 1),
+            b'>' => (br"\u003e", 1),
+            // U+2028 and U+2029 end lines in JavaScript before ES2019.
+            0xe2 => match bytes.get(index + 1..index + 3) {
+                Some([0x80, 0xa8]) => (br"\u2028", 3),
+                Some([0x80, 0xa9]) => (br"\u2029", 3),
+                _ => continue,
+            },
+            _ => continue,
         };
-        if let Some(escaped) = escaped {
-            f.write_str(unsafe { str::from_utf8_unchecked(&bytes[last..index]) })?;
-            f.write_str(unsafe { str::from_utf8_unchecked(escaped) })?;
-            last = index + 1;
-        }
+        f.write_str(unsafe { str::from_utf8_unchecked(&bytes[last..index]) })?;
+        f.write_str(unsafe { str::from_utf8_unchecked(escaped) })?;
+        last = index + len;
     }
     f.write_str(unsafe { str::from_utf8_unchecked(&bytes[last..]) })
 }
@@ -94,4 +119,23 @@ mod tests {
 ]"#
         );
     }
+
+    #[test]
+    fn test_json_pretty() {
+        assert_eq!(json_pretty("foo", 4).unwrap().to_string(), r#""foo""#);
+        assert_eq!(
+            json_pretty(vec![vec![1], vec![]], 4).unwrap().to_string(),
+            "[\n    [\n        1\n    ],\n    []\n]"
+        );
+        assert_eq!(
+            json_pretty(vec!["</script>"], 0).unwrap().to_string(),
+            "[\n\"\\u003c/script\\u003e\"\n]"
+        );
+        assert_eq!(
+            json_pretty("a\u{2028}b\u{2029}c&'é", 2)
+                .unwrap()
+                .to_string(),
+            r#""a\u2028b\u2029c\u0026\u0027é""#
+        );
+    }
 }
@@ -5,26 +5,34 @@
 //! For more information, read the [book](https://djc.github.io/askama/filters.html).
 #![allow(clippy::trivially_copy_pass_by_ref)]
 
+use std::borrow::Cow;
 use std::cell::Cell;
+use std::collections::HashSet;
 use std::convert::Infallible;
 use std::fmt::{self, Write};
+use std::hash::Hash;
 
+#[cfg(feature = "time")]
+mod date;
+#[cfg(feature = "time")]
+pub use self::date::{date, FormatDate};
 #[cfg(feature = "serde-json")]
 mod json;
 #[cfg(feature = "serde-json")]
-pub use self::json::json;
+pub use self::json::{json, json_pretty};
 
 use askama_escape::{Escaper, MarkupDisplay};
 #[cfg(feature = "humansize")]
-use humansize::{ISizeFormatter, ToF64, DECIMAL};
+use humansize::{ISizeFormatter, ToF64, BINARY, DECIMAL};
 #[cfg(feature = "num-traits")]
-use num_traits::{cast::NumCast, Signed};
+use num_traits::cast::NumCast;
 #[cfg(feature = "urlencode")]
-use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use percent_encoding::{percent_encode_byte, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 
 use super::Result;
 #[allow(unused_imports)]
 use crate::error::Error::Fmt;
+use crate::locale::group_digits;
 
 #[cfg(feature = "urlencode")]
 // Urlencode char encoding set. Only the characters in the unreserved set don't
@@ -107,17 +115,31 @@ where
 /// ```
 #[inline]
 pub fn filesizeformat(b: &impl ToF64) -> Result<impl fmt::Display, Infallible> {
-    Ok(FilesizeFormatFilter(b.to_f64()))
+    Ok(FilesizeFormatFilter(b.to_f64(), false))
+}
+
+#[cfg(feature = "humansize")]
+/// Like [`filesizeformat()`], but uses binary units if `binary` is `true`
+///
+/// This is the implementation of `{{ size|filesizeformat(true) }}` in templates. Binary units
+/// are powers of 1024 like `KiB` and `MiB`.
+#[inline]
+pub fn filesizeformat_with(b: &impl ToF64, binary: bool) -> Result<impl fmt::Display, Infallible> {
+    Ok(FilesizeFormatFilter(b.to_f64(), binary))
 }
 
 #[cfg(feature = "humansize")]
 #[derive(Debug, Clone, Copy)]
-struct FilesizeFormatFilter(f64);
+struct FilesizeFormatFilter(f64, bool);
 
 #[cfg(feature = "humansize")]
 impl fmt::Display for FilesizeFormatFilter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("{}", ISizeFormatter::new(self.0, &DECIMAL)))
+        let options = match self.1 {
+            true => &BINARY,
+            false => &DECIMAL,
+        };
+        f.write_fmt(format_args!("{}", ISizeFormatter::new(self.0, options)))
     }
 }
 
@@ -166,6 +188,30 @@ pub fn urlencode_strict<T: fmt::Display>(s: T) -> Result<impl fmt::Display, Infa
     Ok(UrlencodeFilter(s, URLENCODE_STRICT_SET))
 }
 
+#[cfg(feature = "urlencode")]
+/// Percent-encodes the argument, keeping the ASCII characters in `safe` unencoded
+///
+/// This is the [`urlencode`] filter with an argument. Like with [`urlencode_strict`], ASCII
+/// letters, digits, and `_.-~` are never encoded, and all other characters are encoded unless
+/// they are in `safe`. Non-ASCII characters are encoded byte by byte, as UTF-8. If `safe`
+/// contains non-ASCII characters, rendering fails.
+///
+/// ```none,ignore
+/// <a href="{{ path|urlencode("/") }}">Path</a>
+/// <a href="/search?q={{ query|urlencode("") }}">Search</a>
+/// <a href="{{ url|urlencode(":/?=&") }}">Link</a>
+/// ```
+#[inline]
+pub fn urlencode_with_safe<T: fmt::Display, S: AsRef<str>>(
+    s: T,
+    safe: S,
+) -> Result<impl fmt::Display> {
+    if !safe.as_ref().is_ascii() {
+        return Err(Fmt(fmt::Error));
+    }
+    Ok(UrlencodeSafeFilter(s, safe))
+}
+
 #[cfg(feature = "urlencode")]
 struct UrlencodeFilter<T>(T, &'static AsciiSet);
 
@@ -185,6 +231,38 @@ impl<T: fmt::Display> fmt::Display for UrlencodeFilter<T> {
     }
 }
 
+#[cfg(feature = "urlencode")]
+struct UrlencodeSafeFilter<T, S>(T, S);
+
+#[cfg(feature = "urlencode")]
+impl<T: fmt::Display, S: AsRef<str>> fmt::Display for UrlencodeSafeFilter<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Writer<'a, 'b>(&'a mut fmt::Formatter<'b>, &'a [u8]);
+
+        impl fmt::Write for Writer<'_, '_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let mut last = 0;
+                for (index, &byte) in s.as_bytes().iter().enumerate() {
+                    let unreserved = byte.is_ascii_alphanumeric() || b"_.-~".contains(&byte);
+                    if unreserved || self.1.contains(&byte) {
+                        continue;
+                    }
+                    // Only ASCII bytes are kept, so a non-empty run of kept bytes starts and
+                    // ends on char boundaries.
+                    if last < index {
+                        self.0.write_str(&s[last..index])?;
+                    }
+                    self.0.write_str(percent_encode_byte(byte))?;
+                    last = index + 1;
+                }
+                self.0.write_str(&s[last..])
+            }
+        }
+
+        write!(Writer(f, self.1.as_ref().as_bytes()), "{}", self.0)
+    }
+}
+
 /// Formats arguments according to the specified format
 ///
 /// The *second* argument to this filter must be a string literal (as in normal
@@ -228,14 +306,25 @@ pub fn linebreaks(s: impl ToString) -> Result<impl fmt::Display, Infallible> {
 }
 
 /// Converts all newlines in a piece of plain text to HTML line breaks
+///
+/// `\r\n` and `\r` are converted like `\n`, and line breaks at the end of the text are
+/// removed. In templates, the text is escaped before the `<br/>` tags are inserted, and the
+/// result is not escaped again.
 #[inline]
 pub fn linebreaksbr(s: impl ToString) -> Result<impl fmt::Display, Infallible> {
     fn linebreaksbr(s: String) -> Result<String, Infallible> {
-        Ok(s.replace('\n', "<br/>"))
+        let s = s.trim_end_matches(['\r', '\n']);
+        Ok(s.replace("\r\n", "\n").replace(['\r', '\n'], "<br/>"))
     }
     linebreaksbr(s.to_string())
 }
 
+/// Alias for the `linebreaksbr()` filter
+#[inline]
+pub fn nl2br(s: impl ToString) -> Result<impl fmt::Display, Infallible> {
+    linebreaksbr(s)
+}
+
 /// Replaces only paragraph breaks in plain text with appropriate HTML
 ///
 /// A new line followed by a blank line becomes a paragraph break `<p>`.
@@ -300,7 +389,84 @@ pub fn trim<T: fmt::Display>(s: T) -> Result<impl fmt::Display> {
     Ok(s)
 }
 
+/// Strips the characters contained in `chars` from both ends
+///
+/// This is the [`trim`] filter with an argument: `{{ path|trim("/") }}`. Every character of
+/// `chars` is stripped, in any order. If `chars` is empty, whitespace is stripped.
+pub fn trim_chars(s: impl fmt::Display, chars: impl AsRef<str>) -> Result<String, Infallible> {
+    Ok(trim_matching(&s.to_string(), chars.as_ref(), true, true).to_owned())
+}
+
+/// Strips leading whitespace, or the leading characters contained in `chars` if not empty
+///
+/// ```none,ignore
+/// {{ "  indented"|trim_start }}, {{ "0042"|trim_start("0") }}
+/// ```
+pub fn trim_start(s: impl fmt::Display, chars: impl AsRef<str>) -> Result<String, Infallible> {
+    Ok(trim_matching(&s.to_string(), chars.as_ref(), true, false).to_owned())
+}
+
+/// Strips trailing whitespace, or the trailing characters contained in `chars` if not empty
+///
+/// ```none,ignore
+/// {{ "line\n"|trim_end }}, {{ "/path/"|trim_end("/") }}
+/// ```
+pub fn trim_end(s: impl fmt::Display, chars: impl AsRef<str>) -> Result<String, Infallible> {
+    Ok(trim_matching(&s.to_string(), chars.as_ref(), false, true).to_owned())
+}
+
+fn trim_matching<'a>(s: &'a str, chars: &str, start: bool, end: bool) -> &'a str {
+    let matches = |c: char| match chars.is_empty() {
+        true => c.is_whitespace(),
+        false => chars.contains(c),
+    };
+    let s = if start {
+        s.trim_start_matches(matches)
+    } else {
+        s
+    };
+    if end {
+        s.trim_end_matches(matches)
+    } else {
+        s
+    }
+}
+
+/// Replaces the occurrences of `from` by `to`, at most `count` times if given
+///
+/// The occurrences are replaced from left to right. The value is formatted to a string first,
+/// so any [`Display`](fmt::Display) value can be used.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ text|replace("o", "0") }} {{ text|replace("o", "0", 2) }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     text: &'a str,
+/// }
+///
+/// let tmpl = Example { text: "foo boo" };
+/// assert_eq!(tmpl.to_string(), "f00 b00 f00 boo");
+/// ```
+pub fn replace(
+    s: impl ToString,
+    from: impl AsRef<str>,
+    to: impl AsRef<str>,
+    count: Option<usize>,
+) -> Result<String, Infallible> {
+    let s = s.to_string();
+    Ok(match count {
+        Some(count) => s.replacen(from.as_ref(), to.as_ref(), count),
+        None => s.replace(from.as_ref(), to.as_ref()),
+    })
+}
+
 /// Limit string length, appends '...' if truncated
+///
+/// The `truncate` filter in templates uses [`truncate_words()`] instead, which keeps whole words.
 #[inline]
 pub fn truncate<S: fmt::Display>(
     source: S,
@@ -373,7 +539,258 @@ impl<S: fmt::Display> fmt::Display for TruncateFilter<S> {
     }
 }
 
+/// Limit string length, appends `ellipsis` if truncated, preferably on a word boundary
+///
+/// This is the implementation of the `truncate` filter in templates. The result contains at
+/// most the first `length` bytes of the value (rounded up to a whole character), followed by
+/// the `ellipsis`. Unless `killwords` is true, the value is cut after the last complete word
+/// instead, if possible, and without trailing whitespace. Values which are not longer than
+/// `length` are returned unchanged.
+///
+/// In templates, `killwords` defaults to `false` and `ellipsis` to `"…"`:
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ s|truncate(9) }} {{ s|truncate(9, true) }} {{ s|truncate(9, false, "...") }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     s: &'a str,
+/// }
+///
+/// let tmpl = Example { s: "Lorem ipsum dolor" };
+/// assert_eq!(tmpl.to_string(), "Lorem… Lorem ips… Lorem...");
+/// ```
+pub fn truncate_words<S: fmt::Display, E: fmt::Display>(
+    source: S,
+    length: usize,
+    killwords: bool,
+    ellipsis: E,
+) -> Result<String, Infallible> {
+    // Only the start of the value is needed: enough to round up to a whole character after
+    // `length` bytes, and to see if another character follows.
+    struct Prefix {
+        buf: String,
+        limit: usize,
+        more: bool,
+    }
+
+    impl fmt::Write for Prefix {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let room = self.limit - self.buf.len();
+            if s.len() <= room {
+                self.buf.push_str(s);
+                return Ok(());
+            }
+            let mut end = room;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.buf.push_str(&s[..end]);
+            self.more = true;
+            // Stop formatting the rest of the value.
+            Err(fmt::Error)
+        }
+    }
+
+    let mut prefix = Prefix {
+        buf: String::new(),
+        limit: length.saturating_add(8),
+        more: false,
+    };
+    let _ = write!(prefix, "{source}");
+    let Prefix { buf: s, more, .. } = prefix;
+
+    let mut cut = length.min(s.len());
+    while !s.is_char_boundary(cut) {
+        cut += 1;
+    }
+    if cut == s.len() && !more {
+        return Ok(s);
+    }
+    if !killwords {
+        if !s[cut..].starts_with(char::is_whitespace) {
+            if let Some(word_end) = s[..cut].rfind(char::is_whitespace) {
+                cut = word_end;
+            }
+        }
+        cut = s[..cut].trim_end().len();
+    }
+    Ok(format!("{}{ellipsis}", &s[..cut]))
+}
+
+/// Limit string length by replacing its middle with `…`
+///
+/// `max_len` is the maximum number of characters of the result, including the ellipsis. The
+/// remaining characters are split evenly between the start and the end of the string (the start
+/// gets the extra one when they cannot be split evenly). Strings that are short enough are
+/// returned unchanged.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ path|truncate_middle(13) }}", ext = "txt")]
+/// struct Example<'a> {
+///     path: &'a str,
+/// }
+///
+/// let tmpl = Example { path: "/usr/share/doc/askama" };
+/// assert_eq!(tmpl.to_string(), "/usr/s…askama");
+/// ```
+pub fn truncate_middle(s: impl ToString, max_len: usize) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let len = s.chars().count();
+    if len <= max_len {
+        return Ok(s);
+    } else if max_len == 0 {
+        return Ok(String::new());
+    }
+
+    let tail = (max_len - 1) / 2;
+    let head = max_len - 1 - tail;
+    let head_end = s.char_indices().nth(head).map_or(s.len(), |(i, _)| i);
+    let tail_start = s.char_indices().nth(len - tail).map_or(s.len(), |(i, _)| i);
+    Ok(format!("{}…{}", &s[..head_end], &s[tail_start..]))
+}
+
+/// Removes whitespace between HTML tags
+///
+/// Only whitespace directly between a `>` and a `<` is removed, whitespace within text is kept.
+/// Use it as a filter block to apply it to the rendered output of a part of a template. The
+/// output of a filter block is escaped in HTML templates, so add the `safe` filter after it:
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{% filter spaceless|safe %}<p>\n  <a href='/'>{{ name }}</a>\n</p>{% endfilter %}",
+///     ext = "html"
+/// )]
+/// struct Example<'a> {
+///     name: &'a str,
+/// }
+///
+/// let tmpl = Example { name: "Home page" };
+/// assert_eq!(tmpl.to_string(), "<p><a href='/'>Home page</a></p>");
+/// ```
+pub fn spaceless(s: impl ToString) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s.as_str();
+    while let Some(end) = rest.find('>') {
+        out.push_str(&rest[..=end]);
+        rest = &rest[end + 1..];
+        let trimmed = rest.trim_start();
+        if trimmed.starts_with('<') {
+            rest = trimmed;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Removes HTML and XML tags, and collapses runs of whitespace into single spaces
+///
+/// A tag starts with a `<` followed by a letter, `/`, `!` or `?`, and ends with the next `>`.
+/// Comments (`<!-- ... -->`) are removed as a whole. A `<` which does not start a tag, or whose
+/// tag is not closed, is kept as text. Entities like `&amp;` are not decoded. Tags are removed
+/// without adding whitespace, so `a<br>b` becomes `ab`. The output is trimmed.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ body|striptags }}", ext = "txt")]
+/// struct Example<'a> {
+///     body: &'a str,
+/// }
+///
+/// let tmpl = Example { body: "<p>Hello <b>Ferris</b>,</p>\n<p>1 &lt; 2</p>" };
+/// assert_eq!(tmpl.to_string(), "Hello Ferris, 1 &lt; 2");
+/// ```
+pub fn striptags(s: impl ToString) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let mut text = String::with_capacity(s.len());
+    let mut rest = s.as_str();
+    while let Some(start) = rest.find('<') {
+        let (before, tag) = rest.split_at(start);
+        text.push_str(before);
+        let end = match tag[1..].chars().next() {
+            Some(_) if tag.starts_with("<!--") => tag[4..].find("-->").map(|end| end + 7),
+            Some(c) if c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?') => {
+                tag.find('>').map(|end| end + 1)
+            }
+            _ => None,
+        };
+        match end {
+            Some(end) => rest = &tag[end..],
+            None => {
+                text.push('<');
+                rest = &tag[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    let mut out = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    Ok(out)
+}
+
+/// Indentations that can be used with [`indent_with()`]
+pub trait Indentation {
+    /// Returns the string prepended to the indented lines
+    fn indentation(&self) -> Cow<'_, str>;
+}
+
+macro_rules! impl_indentation {
+    ($($ty:ty),*) => {
+        $(
+            impl Indentation for $ty {
+                #[inline]
+                fn indentation(&self) -> Cow<'_, str> {
+                    let width = i128::try_from(*self).unwrap_or(i128::MAX);
+                    Cow::Owned(" ".repeat(width.clamp(0, MAX_LEN as i128) as usize))
+                }
+            }
+        )*
+    };
+}
+
+impl_indentation!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl Indentation for str {
+    #[inline]
+    fn indentation(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Indentation for String {
+    #[inline]
+    fn indentation(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: Indentation + ?Sized> Indentation for &T {
+    #[inline]
+    fn indentation(&self) -> Cow<'_, str> {
+        T::indentation(*self)
+    }
+}
+
 /// Indent lines with `width` spaces
+///
+/// The first line is not indented, and neither is the empty line following a trailing newline,
+/// so the output never ends with trailing whitespace. The `indent` filter in templates uses
+/// [`indent_with()`] instead, which also accepts a string and doesn't indent blank lines.
 #[inline]
 pub fn indent(s: impl ToString, width: usize) -> Result<impl fmt::Display, Infallible> {
     fn indent(s: String, width: usize) -> Result<String, Infallible> {
@@ -395,6 +812,133 @@ pub fn indent(s: impl ToString, width: usize) -> Result<impl fmt::Display, Infal
     indent(s.to_string(), width)
 }
 
+/// Indent lines with `width` spaces, or with a string like `"\t"`
+///
+/// Like in Jinja, the first line is only indented if `first` is `true`, and blank lines are
+/// only indented if `blank` is `true`. The empty line following a trailing newline is never
+/// indented, so the output never ends with trailing whitespace.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ text|indent(2) }}|{{ text|indent("\t", first=true, blank=true) }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     text: &'a str,
+/// }
+///
+/// assert_eq!(
+///     Example { text: "a\n\nb" }.to_string(),
+///     "a\n\n  b|\ta\n\t\n\tb"
+/// );
+/// ```
+#[inline]
+pub fn indent_with(
+    s: impl ToString,
+    width: impl Indentation,
+    first: bool,
+    blank: bool,
+) -> Result<String, Infallible> {
+    fn indent(s: String, prefix: &str, first: bool, blank: bool) -> Result<String, Infallible> {
+        if prefix.len() >= MAX_LEN || s.len() >= MAX_LEN {
+            return Ok(s);
+        }
+        let mut indented = String::with_capacity(s.len());
+        let mut lines = s.split('\n').enumerate().peekable();
+        while let Some((i, line)) = lines.next() {
+            if i > 0 {
+                indented.push('\n');
+            }
+            let is_last = lines.peek().is_none();
+            let is_blank = line.trim_end_matches('\r').is_empty();
+            if (i > 0 || first) && !(is_last && line.is_empty()) && (blank || !is_blank) {
+                indented.push_str(prefix);
+            }
+            indented.push_str(line);
+        }
+        Ok(indented)
+    }
+    indent(s.to_string(), &width.indentation(), first, blank)
+}
+
+/// Prefixes the first line with `first` and all following lines with `rest`
+///
+/// This renders hanging indents, e.g. for bullet points in plain text. Like with [`indent`],
+/// the empty line following a trailing newline is not prefixed, and an empty string stays
+/// empty.
+///
+/// ```none,ignore
+/// {{ "first\nsecond"|hang_indent("- ", "  ") }}
+/// ```
+#[inline]
+pub fn hang_indent(
+    s: impl ToString,
+    first: impl fmt::Display,
+    rest: impl fmt::Display,
+) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let first = first.to_string();
+    let rest = rest.to_string();
+    if s.is_empty() || first.len() >= MAX_LEN || rest.len() >= MAX_LEN || s.len() >= MAX_LEN {
+        return Ok(s);
+    }
+    let mut indented = String::with_capacity(s.len() + first.len());
+    indented.push_str(&first);
+    for (i, c) in s.char_indices() {
+        indented.push(c);
+
+        if c == '\n' && i < s.len() - 1 {
+            indented.push_str(&rest);
+        }
+    }
+    Ok(indented)
+}
+
+/// Prepends `before` to a non-empty string
+///
+/// An empty string stays empty, so no stray affix is rendered for a missing value.
+///
+/// ```none,ignore
+/// {{ price|prefix("$") }}
+/// ```
+#[inline]
+pub fn prefix(s: impl ToString, before: impl fmt::Display) -> Result<String, Infallible> {
+    wrap(s, before, "")
+}
+
+/// Appends `after` to a non-empty string
+///
+/// An empty string stays empty, so no stray affix is rendered for a missing value.
+///
+/// ```none,ignore
+/// {{ greeting|suffix("!") }}
+/// ```
+#[inline]
+pub fn suffix(s: impl ToString, after: impl fmt::Display) -> Result<String, Infallible> {
+    wrap(s, "", after)
+}
+
+/// Surrounds a non-empty string with `before` and `after`
+///
+/// An empty string stays empty, so no stray affixes are rendered for a missing value.
+///
+/// ```none,ignore
+/// {{ label|wrap("[", "]") }}
+/// ```
+pub fn wrap(
+    s: impl ToString,
+    before: impl fmt::Display,
+    after: impl fmt::Display,
+) -> Result<String, Infallible> {
+    let s = s.to_string();
+    if s.is_empty() {
+        return Ok(s);
+    }
+    Ok(format!("{before}{s}{after}"))
+}
+
 #[cfg(feature = "num-traits")]
 /// Casts number to f64
 pub fn into_f64<T>(number: T) -> Result<f64>
@@ -413,75 +957,1169 @@ where
     number.to_isize().ok_or(Fmt(fmt::Error))
 }
 
-/// Joins iterable into a string separated by provided argument
-#[inline]
-pub fn join<I, S>(input: I, separator: S) -> Result<impl fmt::Display, Infallible>
-where
-    I: IntoIterator,
-    I::Item: fmt::Display,
-    S: fmt::Display,
-{
-    Ok(JoinFilter(Cell::new(Some((input, separator)))))
+/// Numbers that can be formatted with [`format_float`]
+pub trait ToFloat {
+    fn to_float(&self) -> f64;
 }
 
-/// Result of the filter [`join()`].
-///
-/// ## Note
-///
-/// This struct implements [`fmt::Display`], but only produces a string once.
-/// Any subsequent call to `.to_string()` will result in an empty string, because the iterator is
-/// already consumed.
-//
-// The filter contains a [`Cell`], so we can modify iterator inside a method that takes `self` by
-// reference: [`fmt::Display::fmt()`] normally has the contract that it will produce the same result
-// in multiple invocations for the same object. We break this contract, because have to consume the
-// iterator, unless we want to enforce `I: Clone`, nor do we want to "memorize" the result of the
-// joined data.
-struct JoinFilter<I, S>(Cell<Option<(I, S)>>);
-
-impl<I, S> fmt::Display for JoinFilter<I, S>
-where
-    I: IntoIterator,
-    I::Item: fmt::Display,
-    S: fmt::Display,
-{
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Some((iter, separator)) = self.0.take() else {
-            return Ok(());
-        };
-        for (idx, token) in iter.into_iter().enumerate() {
-            match idx {
-                0 => f.write_fmt(format_args!("{token}"))?,
-                _ => f.write_fmt(format_args!("{separator}{token}"))?,
+macro_rules! impl_to_float {
+    ($($ty:ty),*) => {
+        $(
+            impl ToFloat for $ty {
+                #[inline]
+                fn to_float(&self) -> f64 {
+                    *self as f64
+                }
             }
-        }
-        Ok(())
+        )*
+    };
+}
+
+impl_to_float!(f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl<T: ToFloat + ?Sized> ToFloat for &T {
+    #[inline]
+    fn to_float(&self) -> f64 {
+        T::to_float(self)
     }
 }
 
-#[cfg(feature = "num-traits")]
-/// Absolute value
-pub fn abs<T>(number: T) -> Result<T>
+/// Formats a number with a fixed number of decimal places
+///
+/// If `trim` is `true` (`{{ x|format_float(2, trim=true) }}`), trailing zeros are removed from
+/// the decimal part, as is the decimal point if nothing is left after it.
+///
+/// Large values are never written in scientific notation, and a value that rounds to zero
+/// is written without a minus sign. `NaN` and infinite values are rendered as `NaN`, `inf`
+/// and `-inf`.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{{ price|format_float(2) }} {{ weight|format_float(2, trim=true) }}",
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     price: f64,
+///     weight: f32,
+/// }
+///
+/// let tmpl = Example { price: 3.5, weight: 1.0 };
+/// assert_eq!(tmpl.to_string(), "3.50 1");
+/// ```
+pub fn format_float(
+    number: impl ToFloat,
+    decimals: usize,
+    trim: bool,
+) -> Result<String, Infallible> {
+    let number = number.to_float();
+    let mut s = format!("{number:.decimals$}");
+    if number.is_finite() {
+        if trim && s.contains('.') {
+            let len = s.trim_end_matches('0').trim_end_matches('.').len();
+            s.truncate(len);
+        }
+        if s.starts_with('-') && s[1..].bytes().all(|b| matches!(b, b'0' | b'.')) {
+            s.remove(0);
+        }
+    }
+    Ok(s)
+}
+
+/// Rounds a number to `precision` decimal places
+///
+/// The `method` is `"common"` (round half away from zero), `"ceil"` (always round up) or
+/// `"floor"` (always round down); any other method makes rendering fail. The result is a
+/// float, which is written without trailing zeros: use [`format_float`] to write a fixed
+/// number of decimals. `NaN` and infinite values are returned unchanged.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ value|round }} {{ value|round(1) }} {{ value|round(0, "floor") }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     value: f64,
+/// }
+///
+/// assert_eq!(Example { value: 2.567 }.to_string(), "3 2.6 2");
+/// ```
+pub fn round(number: impl ToFloat, precision: usize, method: &str) -> Result<f64> {
+    let round = match method {
+        "common" => f64::round,
+        "ceil" => f64::ceil,
+        "floor" => f64::floor,
+        _ => return Err(Fmt(fmt::Error)),
+    };
+    let number = number.to_float();
+    let factor = 10f64.powi(precision.try_into().unwrap_or(i32::MAX));
+    let scaled = number * factor;
+    if !scaled.is_finite() {
+        // `NaN`, infinite, or too precise to change anything.
+        return Ok(number);
+    }
+    // Adding `0.0` turns `-0.0` into `0.0`.
+    Ok(round(scaled) / factor + 0.0)
+}
+
+/// Encodes bytes (or the UTF-8 bytes of a string) as hexadecimal
+///
+/// Lowercase digits are used, unless `upper=true` is passed.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ digest|hex }} {{ name|hex(upper=true) }}", ext = "txt")]
+/// struct Example<'a> {
+///     digest: &'a [u8],
+///     name: &'a str,
+/// }
+///
+/// let tmpl = Example { digest: &[0xca, 0xfe], name: "Ok" };
+/// assert_eq!(tmpl.to_string(), "cafe 4F6B");
+/// ```
+pub fn hex(bytes: impl AsRef<[u8]>, upper: bool) -> Result<String, Infallible> {
+    let digits = match upper {
+        true => b"0123456789ABCDEF",
+        false => b"0123456789abcdef",
+    };
+    let bytes = bytes.as_ref();
+    let mut s = String::with_capacity(2 * bytes.len());
+    for &b in bytes {
+        s.push(digits[(b >> 4) as usize] as char);
+        s.push(digits[(b & 0xf) as usize] as char);
+    }
+    Ok(s)
+}
+
+/// Decodes a hexadecimal string, the reverse of [`hex`]
+///
+/// Upper and lowercase digits are accepted. Rendering fails if the input has an odd length,
+/// contains anything but hexadecimal digits, or does not decode to valid UTF-8.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ encoded|unhex }}", ext = "txt")]
+/// struct Example<'a> {
+///     encoded: &'a str,
+/// }
+///
+/// assert_eq!(Example { encoded: "4f6B" }.to_string(), "Ok");
+/// assert!(Example { encoded: "4f6" }.render().is_err());
+/// ```
+pub fn unhex(s: impl ToString) -> Result<String> {
+    fn digit(c: u8) -> Result<u8> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(Fmt(fmt::Error)),
+        }
+    }
+
+    let s = s.to_string();
+    if s.len() % 2 != 0 {
+        return Err(Fmt(fmt::Error));
+    }
+    let bytes = s
+        .as_bytes()
+        .chunks_exact(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect::<Result<Vec<u8>>>()?;
+    String::from_utf8(bytes).map_err(|_| Fmt(fmt::Error))
+}
+
+/// Values that can be replaced by a fallback with the [`default`] filter
+pub trait DefaultValue {
+    /// Returns `true` if the fallback should be used instead of this value
+    ///
+    /// With `strip`, strings containing only whitespace are treated as empty.
+    fn is_default(&self, strip: bool) -> bool;
+
+    /// Returns `true` if the fallback should be used with `boolean=true`, in addition to the
+    /// values for which [`is_default()`](DefaultValue::is_default) returns `true`
+    ///
+    /// By default, this is for the values which are [`is_default()`](DefaultValue::is_default)
+    /// without `strip`, i.e. only empty strings. Numbers use it for zero, and `bool` for `false`.
+    #[inline]
+    fn is_falsy(&self) -> bool {
+        self.is_default(false)
+    }
+
+    /// Writes the value when it is used instead of the fallback
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl DefaultValue for str {
+    #[inline]
+    fn is_default(&self, strip: bool) -> bool {
+        match strip {
+            true => self.trim().is_empty(),
+            false => self.is_empty(),
+        }
+    }
+
+    #[inline]
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl DefaultValue for String {
+    #[inline]
+    fn is_default(&self, strip: bool) -> bool {
+        self.as_str().is_default(strip)
+    }
+
+    #[inline]
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+macro_rules! impl_default_value {
+    ($($ty:ty => $zero:expr),*) => {
+        $(
+            impl DefaultValue for $ty {
+                #[inline]
+                fn is_default(&self, _: bool) -> bool {
+                    false
+                }
+
+                #[inline]
+                fn is_falsy(&self) -> bool {
+                    *self == $zero
+                }
+
+                #[inline]
+                fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Display::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+impl_default_value!(
+    bool => false, f32 => 0.0, f64 => 0.0, i8 => 0, i16 => 0, i32 => 0, i64 => 0,
+    i128 => 0, isize => 0, u8 => 0, u16 => 0, u32 => 0, u64 => 0, u128 => 0, usize => 0
+);
+
+impl DefaultValue for char {
+    #[inline]
+    fn is_default(&self, _: bool) -> bool {
+        false
+    }
+
+    #[inline]
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<T: DefaultValue> DefaultValue for Option<T> {
+    #[inline]
+    fn is_default(&self, strip: bool) -> bool {
+        match self {
+            Some(value) => value.is_default(strip),
+            None => true,
+        }
+    }
+
+    #[inline]
+    fn is_falsy(&self) -> bool {
+        match self {
+            Some(value) => value.is_falsy(),
+            None => true,
+        }
+    }
+
+    #[inline]
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Some(value) => value.fmt_value(f),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: DefaultValue + ?Sized> DefaultValue for &T {
+    #[inline]
+    fn is_default(&self, strip: bool) -> bool {
+        T::is_default(self, strip)
+    }
+
+    #[inline]
+    fn is_falsy(&self) -> bool {
+        T::is_falsy(self)
+    }
+
+    #[inline]
+    fn fmt_value(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt_value(self, f)
+    }
+}
+
+/// Renders `fallback` if `value` is `None` or an empty string, and `value` otherwise
+///
+/// With `strip=true` (`{{ name|default("N/A", strip=true) }}`), strings that only contain
+/// whitespace are treated as empty. With `boolean=true` (`{{ count|default(1, boolean=true) }}`),
+/// falsy values use the fallback too, like in Jinja: `0`, `0.0` and `false`, see
+/// [`DefaultValue::is_falsy()`]. An `Option` uses the fallback if it is `None` or if its value
+/// would use it.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ nickname|default("Anonymous") }}, {{ city|default("N/A", strip=true) }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     nickname: Option<&'a str>,
+///     city: &'a str,
+/// }
+///
+/// let tmpl = Example { nickname: None, city: "  " };
+/// assert_eq!(tmpl.to_string(), "Anonymous, N/A");
+/// ```
+#[inline]
+pub fn default<T: DefaultValue, D: fmt::Display>(
+    value: T,
+    fallback: D,
+    strip: bool,
+    boolean: bool,
+) -> Result<impl fmt::Display, Infallible> {
+    Ok(DefaultFilter {
+        value,
+        fallback,
+        strip,
+        boolean,
+    })
+}
+
+struct DefaultFilter<T, D> {
+    value: T,
+    fallback: D,
+    strip: bool,
+    boolean: bool,
+}
+
+impl<T: DefaultValue, D: fmt::Display> fmt::Display for DefaultFilter<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.is_default(self.strip) || (self.boolean && self.value.is_falsy()) {
+            true => self.fallback.fmt(f),
+            false => self.value.fmt_value(f),
+        }
+    }
+}
+
+/// Values that can be mapped to a label with the [`yesno`] filter
+pub trait YesNoValue {
+    /// Returns the boolean value, or `None` if it is unknown
+    fn to_yes_no(&self) -> Option<bool>;
+}
+
+impl YesNoValue for bool {
+    #[inline]
+    fn to_yes_no(&self) -> Option<bool> {
+        Some(*self)
+    }
+}
+
+impl<T: YesNoValue> YesNoValue for Option<T> {
+    #[inline]
+    fn to_yes_no(&self) -> Option<bool> {
+        self.as_ref().and_then(T::to_yes_no)
+    }
+}
+
+impl<T: YesNoValue + ?Sized> YesNoValue for &T {
+    #[inline]
+    fn to_yes_no(&self) -> Option<bool> {
+        T::to_yes_no(*self)
+    }
+}
+
+/// Maps a boolean to one of the comma-separated `labels`
+///
+/// `labels` contains two labels (`"yes,no"`), used for `true` and `false`, or three labels
+/// (`"yes,no,maybe"`), the third one being used for `None`. With two labels, `None` uses the
+/// label for `false`. Any other number of labels makes rendering fail.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ active|yesno("Active,Inactive") }} {{ verified|yesno("yes,no,unknown") }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     active: bool,
+///     verified: Option<bool>,
+/// }
+///
+/// let tmpl = Example { active: true, verified: None };
+/// assert_eq!(tmpl.to_string(), "Active unknown");
+/// ```
+pub fn yesno(value: impl YesNoValue, labels: &str) -> Result<&str> {
+    let mut labels = labels.split(',');
+    let (Some(yes), Some(no), maybe, None) =
+        (labels.next(), labels.next(), labels.next(), labels.next())
+    else {
+        return Err(Fmt(fmt::Error));
+    };
+    Ok(match value.to_yes_no() {
+        Some(true) => yes,
+        Some(false) => no,
+        None => maybe.unwrap_or(no),
+    })
+}
+
+/// Counts that select the suffix of the [`pluralize`] filter
+pub trait PluralizeCount {
+    /// Returns `true` if the count uses the singular suffix
+    fn is_singular(&self) -> bool;
+}
+
+macro_rules! impl_pluralize_count {
+    ($($ty:ty),*) => {
+        $(
+            impl PluralizeCount for $ty {
+                #[inline]
+                fn is_singular(&self) -> bool {
+                    *self == 1
+                }
+            }
+        )*
+    };
+}
+
+impl_pluralize_count!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl<T: PluralizeCount + ?Sized> PluralizeCount for &T {
+    #[inline]
+    fn is_singular(&self) -> bool {
+        T::is_singular(*self)
+    }
+}
+
+/// Returns `singular` if `count` is 1, and `plural` otherwise
+///
+/// Without arguments, the suffixes are `""` and `"s"`. With one argument, it is the plural
+/// suffix (`pluralize("es")`), and with two arguments they are the singular and the plural
+/// suffix (`pluralize("y", "ies")`).
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ n }} item{{ n|pluralize }}, {{ n }} cherr{{ n|pluralize("y", "ies") }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     n: usize,
+/// }
+///
+/// assert_eq!(Example { n: 1 }.to_string(), "1 item, 1 cherry");
+/// assert_eq!(Example { n: 2 }.to_string(), "2 items, 2 cherries");
+/// ```
+#[inline]
+pub fn pluralize<'a>(
+    count: impl PluralizeCount,
+    singular: &'a str,
+    plural: &'a str,
+) -> Result<&'a str, Infallible> {
+    Ok(match count.is_singular() {
+        true => singular,
+        false => plural,
+    })
+}
+
+/// Numbers that can be formatted with the [`numberformat`] filter
+pub trait NumberFormatValue: fmt::Display {
+    /// Formats the number with exactly `decimals` fractional digits
+    fn fmt_decimals(&self, decimals: usize) -> String;
+}
+
+macro_rules! impl_number_format_value {
+    (int: $($int:ty),*; float: $($float:ty),*) => {
+        $(
+            impl NumberFormatValue for $int {
+                fn fmt_decimals(&self, decimals: usize) -> String {
+                    match decimals {
+                        0 => self.to_string(),
+                        _ => format!("{self}.{:0<decimals$}", ""),
+                    }
+                }
+            }
+        )*
+        $(
+            impl NumberFormatValue for $float {
+                fn fmt_decimals(&self, decimals: usize) -> String {
+                    format!("{self:.decimals$}")
+                }
+            }
+        )*
+    };
+}
+
+impl_number_format_value!(
+    int: i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize;
+    float: f32, f64
+);
+
+impl<T: NumberFormatValue + ?Sized> NumberFormatValue for &T {
+    #[inline]
+    fn fmt_decimals(&self, decimals: usize) -> String {
+        T::fmt_decimals(*self, decimals)
+    }
+}
+
+/// Groups the digits of a number in thousands, optionally with a fixed number of decimals
+///
+/// The arguments are the number of `decimals` (by default, the number is written as it is),
+/// the decimal separator (`"."` by default) and the thousands separator (`","` by default):
+/// `{{ price|numberformat(2, ",", ".") }}`. The sign of negative numbers is kept outside of
+/// the groups. Integers are never converted to floats, so large values keep all their digits.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ count|numberformat }} {{ price|numberformat(2) }} {{ price|numberformat(2, ",", ".") }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     count: u64,
+///     price: f64,
+/// }
+///
+/// let tmpl = Example { count: 1234567, price: -9876.5 };
+/// assert_eq!(tmpl.to_string(), "1,234,567 -9,876.50 -9.876,50");
+/// ```
+pub fn numberformat(
+    number: impl NumberFormatValue,
+    decimals: Option<usize>,
+    decimal_sep: &str,
+    thousands_sep: &str,
+) -> Result<String, Infallible> {
+    let number = match decimals {
+        Some(decimals) => number.fmt_decimals(decimals),
+        None => number.to_string(),
+    };
+    Ok(group_digits(&number, thousands_sep, decimal_sep))
+}
+
+/// Joins iterable into a string separated by provided argument
+#[inline]
+pub fn join<I, S>(input: I, separator: S) -> Result<impl fmt::Display, Infallible>
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+    S: fmt::Display,
+{
+    Ok(JoinFilter(Cell::new(Some((input, separator)))))
+}
+
+/// Result of the filter [`join()`].
+///
+/// ## Note
+///
+/// This struct implements [`fmt::Display`], but only produces a string once.
+/// Any subsequent call to `.to_string()` will result in an empty string, because the iterator is
+/// already consumed.
+//
+// The filter contains a [`Cell`], so we can modify iterator inside a method that takes `self` by
+// reference: [`fmt::Display::fmt()`] normally has the contract that it will produce the same result
+// in multiple invocations for the same object. We break this contract, because have to consume the
+// iterator, unless we want to enforce `I: Clone`, nor do we want to "memorize" the result of the
+// joined data.
+struct JoinFilter<I, S>(Cell<Option<(I, S)>>);
+
+impl<I, S> fmt::Display for JoinFilter<I, S>
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+    S: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((iter, separator)) = self.0.take() else {
+            return Ok(());
+        };
+        for (idx, token) in iter.into_iter().enumerate() {
+            match idx {
+                0 => f.write_fmt(format_args!("{token}"))?,
+                _ => f.write_fmt(format_args!("{separator}{token}"))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pairs each element of an iterable with its zero-based index
+///
+/// This is a lighter alternative to the `loop` variable when only the index is needed:
+///
+/// ```none,ignore
+/// {% for (i, item) in items|enumerate %}
+///     {{ i + 1 }}. {{ item }}
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn enumerate<I: IntoIterator>(
+    iter: I,
+) -> Result<std::iter::Enumerate<I::IntoIter>, Infallible> {
+    Ok(iter.into_iter().enumerate())
+}
+
+/// Yields at most the first `n` elements of an iterable
+///
+/// ```none,ignore
+/// {% for item in items|take(5) %}
+///     {{ item }}
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn take<I: IntoIterator>(
+    iter: I,
+    n: usize,
+) -> Result<std::iter::Take<I::IntoIter>, Infallible> {
+    Ok(iter.into_iter().take(n))
+}
+
+/// Skips the first `n` elements of an iterable
+///
+/// It can be combined with [`take`] to display a page of elements:
+///
+/// ```none,ignore
+/// {% for item in items|skip(page * 10)|take(10) %}
+///     {{ item }}
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn skip<I: IntoIterator>(
+    iter: I,
+    n: usize,
+) -> Result<std::iter::Skip<I::IntoIter>, Infallible> {
+    Ok(iter.into_iter().skip(n))
+}
+
+/// Iterates over two iterables in parallel, yielding pairs of their elements
+///
+/// The iteration stops as soon as one of the iterables is exhausted.
+///
+/// ```none,ignore
+/// {% for (label, value) in labels|zip(values) %}
+///     {{ label }}: {{ value }}
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn zip<I: IntoIterator, J: IntoIterator>(
+    iter: I,
+    other: J,
+) -> Result<std::iter::Zip<I::IntoIter, J::IntoIter>, Infallible> {
+    Ok(iter.into_iter().zip(other))
+}
+
+/// Returns the first element of an iterable, or `None` if it is empty
+///
+/// In templates, this filter also returns the first character of a string. The result is an
+/// `Option`, so use it with the [`default`] filter or with `if let`:
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ items|first|default("-") }} {% if let Some(c) = name|first %}{{ c }}{% endif %}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     items: Vec<u32>,
+///     name: &'a str,
+/// }
+///
+/// let tmpl = Example { items: vec![], name: "Ferris" };
+/// assert_eq!(tmpl.to_string(), "- F");
+/// ```
+#[inline]
+pub fn first<I: IntoIterator>(iter: I) -> Result<Option<I::Item>, Infallible> {
+    Ok(iter.into_iter().next())
+}
+
+/// Returns the last element of an iterable, or `None` if it is empty
+///
+/// In templates, this filter also returns the last character of a string. Like with [`first`],
+/// the result is an `Option`.
+#[inline]
+pub fn last<I: IntoIterator>(iter: I) -> Result<Option<I::Item>, Infallible> {
+    Ok(iter.into_iter().last())
+}
+
+/// Returns the smallest element of an iterable, or `None` if it is empty
+///
+/// If several elements are the smallest, the first one is returned. Elements that cannot be
+/// compared, like `NaN`, are skipped unless they are first. In templates, the name or path of
+/// a field can be passed to return the smallest value of this field (`products|min("price")`).
+/// Like with [`first`], the result is an `Option`:
+///
+/// ```none,ignore
+/// {{ scores|min|default("-") }}
+/// ```
+#[inline]
+pub fn min<I>(items: I) -> Result<Option<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: PartialOrd,
+{
+    Ok(items
+        .into_iter()
+        .reduce(|min, item| if item < min { item } else { min }))
+}
+
+/// Returns the largest element of an iterable, or `None` if it is empty
+///
+/// If several elements are the largest, the first one is returned. Otherwise, this filter
+/// works like [`min`].
+#[inline]
+pub fn max<I>(items: I) -> Result<Option<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: PartialOrd,
+{
+    Ok(items
+        .into_iter()
+        .reduce(|max, item| if item > max { item } else { max }))
+}
+
+/// Numbers that can be added with the [`sum`] filter
+pub trait Summand {
+    /// The type of the sum
+    type Sum: std::iter::Sum<Self::Sum>;
+
+    /// Returns the value which is added to the sum
+    fn summand(&self) -> Self::Sum;
+}
+
+macro_rules! impl_summand {
+    ($($ty:ty),*) => {
+        $(
+            impl Summand for $ty {
+                type Sum = Self;
+
+                #[inline]
+                fn summand(&self) -> Self {
+                    *self
+                }
+            }
+        )*
+    };
+}
+
+impl_summand!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T: Summand + ?Sized> Summand for &T {
+    type Sum = T::Sum;
+
+    #[inline]
+    fn summand(&self) -> Self::Sum {
+        T::summand(*self)
+    }
+}
+
+/// Returns the sum of the elements of an iterable, `0` if it is empty
+///
+/// In templates, the name or path of a field can be passed to add this field
+/// (`products|sum("price")`).
+///
+/// ```none,ignore
+/// {{ prices|sum }}
+/// ```
+#[inline]
+pub fn sum<I>(items: I) -> Result<<I::Item as Summand>::Sum, Infallible>
+where
+    I: IntoIterator,
+    I::Item: Summand,
+{
+    Ok(items.into_iter().map(|item| item.summand()).sum())
+}
+
+// The `first` and `last` filters call the methods of these traits with the method call syntax,
+// so the argument is dereferenced until it is either iterable or a `str`.
+
+#[doc(hidden)]
+pub trait FirstLastIter: IntoIterator + Sized {
+    #[inline]
+    fn filter_first(self) -> Result<Option<Self::Item>, Infallible> {
+        first(self)
+    }
+
+    #[inline]
+    fn filter_last(self) -> Result<Option<Self::Item>, Infallible> {
+        last(self)
+    }
+}
+
+impl<I: IntoIterator> FirstLastIter for I {}
+
+#[doc(hidden)]
+pub trait FirstLastStr {
+    fn filter_first(&self) -> Result<Option<char>, Infallible>;
+
+    fn filter_last(&self) -> Result<Option<char>, Infallible>;
+}
+
+impl FirstLastStr for str {
+    #[inline]
+    fn filter_first(&self) -> Result<Option<char>, Infallible> {
+        Ok(self.chars().next())
+    }
+
+    #[inline]
+    fn filter_last(&self) -> Result<Option<char>, Infallible> {
+        Ok(self.chars().next_back())
+    }
+}
+
+/// Iterates over all contiguous windows of length `size` of a slice
+///
+/// The windows overlap, so for `[1, 2, 3]|windows(2)` this yields `[1, 2]` and `[2, 3]`.
+/// If the slice is shorter than `size`, nothing is yielded. A `size` of zero is an error.
+///
+/// ```none,ignore
+/// {% for pair in items|windows(2) %}
+///     {{ pair[0] }} -> {{ pair[1] }}
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn windows<T>(s: &[T], size: usize) -> Result<std::slice::Windows<'_, T>> {
+    if size == 0 {
+        return Err(Fmt(fmt::Error));
+    }
+    Ok(s.windows(size))
+}
+
+/// Iterates over the substrings of a string, separated by `separator`
+///
+/// Consecutive separators and separators at the start or end of the string produce empty
+/// substrings, so `"a,,b"|split(",")` yields `"a"`, `""` and `"b"`. An empty separator is an
+/// error.
+///
+/// ```none,ignore
+/// {% for part in csv_line|split(",") %}
+///     <td>{{ part }}</td>
+/// {% endfor %}
+/// ```
+#[inline]
+pub fn split<'a, 'b, S: AsRef<str> + ?Sized>(
+    s: &'a S,
+    separator: &'b str,
+) -> Result<std::str::Split<'a, &'b str>> {
+    if separator.is_empty() {
+        return Err(Fmt(fmt::Error));
+    }
+    Ok(s.as_ref().split(separator))
+}
+
+/// Distributes the elements into `n` columns, filled top to bottom
+///
+/// Reading down each column, then the columns from left to right, preserves the order of the
+/// elements, so `[1, 2, 3, 4, 5]|columns(2)` yields `[1, 2, 3]` and `[4, 5]`. If the number of
+/// elements is not divisible by `n`, the first columns get one element more than the last ones.
+/// Exactly `n` columns are returned, some of which are empty if there are fewer than `n`
+/// elements. A `n` of zero is an error.
+///
+/// ```none,ignore
+/// {% for column in items|columns(3) %}
+///     <ul>{% for item in column %}<li>{{ item }}</li>{% endfor %}</ul>
+/// {% endfor %}
+/// ```
+pub fn columns<I: IntoIterator>(items: I, n: usize) -> Result<Vec<Vec<I::Item>>> {
+    if n == 0 {
+        return Err(Fmt(fmt::Error));
+    }
+    let items = items.into_iter().collect::<Vec<_>>();
+    let (size, extra) = (items.len() / n, items.len() % n);
+
+    let mut items = items.into_iter();
+    Ok((0..n)
+        .map(|i| {
+            let len = size + (i < extra) as usize;
+            items.by_ref().take(len).collect()
+        })
+        .collect())
+}
+
+/// Splits the elements into rows of `size` elements
+///
+/// The last row is shorter if the number of elements is not divisible by `size`. In templates,
+/// a fill value can be passed as second argument (`items|batch(3, "-")`) to pad the last row,
+/// see [`batch_filled`]. A `size` of zero is an error.
+///
+/// ```none,ignore
+/// {% for row in items|batch(3) %}
+///     <tr>{% for cell in row %}<td>{{ cell }}</td>{% endfor %}</tr>
+/// {% endfor %}
+/// ```
+pub fn batch<I: IntoIterator>(items: I, size: usize) -> Result<Vec<Vec<I::Item>>> {
+    if size == 0 {
+        return Err(Fmt(fmt::Error));
+    }
+    let mut items = items.into_iter().peekable();
+    let mut rows = Vec::new();
+    while items.peek().is_some() {
+        rows.push(items.by_ref().take(size).collect());
+    }
+    Ok(rows)
+}
+
+/// Splits the elements into rows of `size` elements, padding the last row with `fill`
+///
+/// This is the [`batch`] filter with a fill value. The fill value can have another type than
+/// the elements, so the rows contain [`BatchItem`]s, which are displayed like the value they
+/// contain.
+#[allow(clippy::type_complexity)]
+pub fn batch_filled<I: IntoIterator, F: Clone>(
+    items: I,
+    size: usize,
+    fill: F,
+) -> Result<Vec<Vec<BatchItem<I::Item, F>>>> {
+    let mut rows = batch(items.into_iter().map(BatchItem::Item), size)?;
+    if let Some(last) = rows.last_mut() {
+        last.resize_with(size, || BatchItem::Fill(fill.clone()));
+    }
+    Ok(rows)
+}
+
+/// An element of a row returned by [`batch_filled`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchItem<T, F> {
+    /// An element of the input
+    Item(T),
+    /// The fill value padding the last row
+    Fill(F),
+}
+
+impl<T: fmt::Display, F: fmt::Display> fmt::Display for BatchItem<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchItem::Item(item) => item.fmt(f),
+            BatchItem::Fill(fill) => fill.fmt(f),
+        }
+    }
+}
+
+/// Returns the elements of an iterable in ascending order, or descending order with `reverse`
+///
+/// The sort is stable, so equal elements keep their order, also with `reverse`.
+///
+/// ```none,ignore
+/// {% for name in names|sort %}{{ name }}{% endfor %}
+/// {% for name in names|sort(reverse=true) %}{{ name }}{% endfor %}
+/// ```
+pub fn sort<I>(items: I, reverse: bool) -> Result<Vec<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: Ord,
+{
+    sort_by(items, Ord::cmp, reverse)
+}
+
+/// Returns the elements of an iterable sorted with the comparison function `compare`
+///
+/// In templates, the argument is the name of the field to sort by, or a path like
+/// `"address.city"`, which is turned into a comparison of these fields:
+///
+/// ```none,ignore
+/// {% for user in users|sort_by("age") %}{{ user.name }}{% endfor %}
+/// {% for user in users|sort_by("address.city", reverse=true) %}{{ user.name }}{% endfor %}
+/// ```
+///
+/// The sort is stable, so equal elements keep their order, also with `reverse`.
+pub fn sort_by<I, F>(items: I, mut compare: F, reverse: bool) -> Result<Vec<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item, &I::Item) -> std::cmp::Ordering,
+{
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    match reverse {
+        true => items.sort_by(|a, b| compare(b, a)),
+        false => items.sort_by(compare),
+    }
+    Ok(items)
+}
+
+/// Yields each distinct element of an iterable once, in the order in which they are first seen
+///
+/// The elements are yielded as they are iterated over, so nothing more than needed is
+/// iterated over if the loop stops early. In templates, the name or path of a field can be
+/// passed to deduplicate by this field (`users|unique("name")`), see [`unique_by`].
+///
+/// ```none,ignore
+/// {{ tags|unique|join(", ") }}
+/// ```
+pub fn unique<I>(items: I) -> Result<impl Iterator<Item = I::Item>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: Clone + Hash + Eq,
+{
+    unique_by(items, I::Item::clone)
+}
+
+/// Yields the elements of an iterable for which `key` returns a value it did not return before
+///
+/// This is the [`unique`] filter with a field argument, e.g. `users|unique("address.city")`
+/// yields the first user of every city.
+pub fn unique_by<I, K, F>(items: I, mut key: F) -> Result<impl Iterator<Item = I::Item>, Infallible>
+where
+    I: IntoIterator,
+    K: Hash + Eq,
+    F: FnMut(&I::Item) -> K,
+{
+    let mut seen = HashSet::new();
+    Ok(items.into_iter().filter(move |item| seen.insert(key(item))))
+}
+
+/// Groups the elements of an iterable by the value `key` returns, sorted by this value
+///
+/// Every group is a pair of the key and the elements with this key, in the order in which
+/// they were iterated over. In templates, the name or path of a field is passed instead of
+/// `key`, and the pairs can be destructured in the loop:
+///
+/// ```none,ignore
+/// {% for (category, products) in products|groupby("category") %}
+///   {{ category }}: {{ products|map("name")|join(", ") }}
+/// {% endfor %}
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn groupby<I, K, F>(items: I, mut key: F) -> Result<Vec<(K, Vec<I::Item>)>, Infallible>
 where
-    T: Signed,
+    I: IntoIterator,
+    K: Ord,
+    F: FnMut(&I::Item) -> K,
 {
+    let mut items = items
+        .into_iter()
+        .map(|item| (key(&item), item))
+        .collect::<Vec<_>>();
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut groups: Vec<(K, Vec<I::Item>)> = Vec::new();
+    for (key, item) in items {
+        match groups.last_mut() {
+            Some((last, group)) if *last == key => group.push(item),
+            _ => groups.push((key, vec![item])),
+        }
+    }
+    Ok(groups)
+}
+
+/// Numbers of which the [`abs`] filter returns the absolute value
+pub trait Abs {
+    /// The type of the absolute value
+    type Output;
+
+    /// Returns the absolute value
+    fn abs(&self) -> Self::Output;
+}
+
+macro_rules! impl_abs {
+    (signed: $($signed:ty),*; unsigned: $($unsigned:ty),*; float: $($float:ty),*) => {
+        $(
+            impl Abs for $signed {
+                type Output = Self;
+
+                #[inline]
+                fn abs(&self) -> Self {
+                    self.saturating_abs()
+                }
+            }
+        )*
+        $(
+            impl Abs for $unsigned {
+                type Output = Self;
+
+                #[inline]
+                fn abs(&self) -> Self {
+                    *self
+                }
+            }
+        )*
+        $(
+            impl Abs for $float {
+                type Output = Self;
+
+                #[inline]
+                fn abs(&self) -> Self {
+                    <$float>::abs(*self)
+                }
+            }
+        )*
+    };
+}
+
+impl_abs!(
+    signed: i8, i16, i32, i64, i128, isize;
+    unsigned: u8, u16, u32, u64, u128, usize;
+    float: f32, f64
+);
+
+impl<T: Abs + ?Sized> Abs for &T {
+    type Output = T::Output;
+
+    #[inline]
+    fn abs(&self) -> Self::Output {
+        T::abs(*self)
+    }
+}
+
+/// Absolute value
+///
+/// The absolute value of the smallest signed integer, e.g. `i32::MIN`, does not fit into its
+/// type, so it saturates to the largest integer, e.g. `i32::MAX`. The absolute value of `NaN`
+/// is `NaN`.
+#[inline]
+pub fn abs<T: Abs>(number: T) -> Result<T::Output, Infallible> {
     Ok(number.abs())
 }
 
 /// Capitalize a value. The first character will be uppercase, all others lowercase.
+///
+/// The first character uses the full Unicode uppercase mapping, which can expand to multiple
+/// characters, e.g. `ß` becomes `SS`.
 #[inline]
 pub fn capitalize(s: impl ToString) -> Result<impl fmt::Display, Infallible> {
-    fn capitalize(s: String) -> Result<String, Infallible> {
+    capitalize_with(s, true)
+}
+
+/// Capitalize a value, and lowercase all other characters only if `lower_rest` is `true`
+///
+/// This is the implementation of `capitalize(lower_rest)` in templates. Keeping the other
+/// characters as they are is useful for proper nouns like `"macOS"`.
+#[inline]
+pub fn capitalize_with(
+    s: impl ToString,
+    lower_rest: bool,
+) -> Result<impl fmt::Display, Infallible> {
+    fn capitalize(s: String, lower_rest: bool) -> Result<String, Infallible> {
         match s.chars().next() {
             Some(c) => {
                 let mut replacement: String = c.to_uppercase().collect();
-                replacement.push_str(&s[c.len_utf8()..].to_lowercase());
+                let rest = &s[c.len_utf8()..];
+                match lower_rest {
+                    true => replacement.push_str(&rest.to_lowercase()),
+                    false => replacement.push_str(rest),
+                }
                 Ok(replacement)
             }
             _ => Ok(s),
         }
     }
-    capitalize(s.to_string())
+    capitalize(s.to_string(), lower_rest)
 }
 
 /// Centers the value in a field of a given width
@@ -514,18 +2152,92 @@ pub fn wordcount(s: impl ToString) -> Result<usize, Infallible> {
     wordcount(s.to_string())
 }
 
+/// Wraps the lines of a text at word boundaries, so that they are at most `width` characters long
+///
+/// Existing newlines are kept as paragraph breaks, and the words of each line are separated by
+/// single spaces. A word longer than `width` is put on its own line, unless `break_long_words`
+/// is `true`, in which case it is split every `width` characters.
+///
+/// ```
+/// # use askama::Template;
+/// #[derive(Template)]
+/// #[template(source = "{{ text|wordwrap(12) }}", ext = "txt")]
+/// struct Example<'a> {
+///     text: &'a str,
+/// }
+///
+/// let tmpl = Example { text: "the quick brown fox jumps\nover the lazy dog" };
+/// assert_eq!(tmpl.to_string(), "the quick\nbrown fox\njumps\nover the\nlazy dog");
+/// ```
+pub fn wordwrap(
+    s: impl ToString,
+    width: usize,
+    break_long_words: bool,
+) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let width = width.max(1);
+    let mut out = String::with_capacity(s.len());
+    for (i, line) in s.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut column = 0;
+        for mut word in line.split_whitespace() {
+            let mut len = word.chars().count();
+            if column > 0 {
+                if column + 1 + len <= width {
+                    out.push(' ');
+                    out.push_str(word);
+                    column += 1 + len;
+                    continue;
+                }
+                out.push('\n');
+            }
+            if break_long_words {
+                while len > width {
+                    let (end, _) = word.char_indices().nth(width).unwrap();
+                    out.push_str(&word[..end]);
+                    out.push('\n');
+                    word = &word[end..];
+                    len -= width;
+                }
+            }
+            out.push_str(word);
+            column = len;
+        }
+    }
+    Ok(out)
+}
+
 /// Return a title cased version of the value. Words will start with uppercase letters, all
 /// remaining characters are lowercase.
+///
+/// Words are delimited by whitespace only, so letters after an apostrophe or a hyphen are
+/// lowercased too: `don't stop` becomes `Don't Stop`, and `well-being` becomes `Well-being`.
+/// Punctuation at the start of a word is skipped, so `"quoted"` becomes `"Quoted"`. Use
+/// [`title_segments`] to capitalize each hyphenated part.
 #[inline]
 pub fn title(s: impl ToString) -> Result<String, Infallible> {
-    let s = s.to_string();
+    Ok(title_case(&s.to_string(), char::is_whitespace))
+}
+
+/// Like [`title`], but hyphens, slashes and underscores delimit words, too, so every segment
+/// of `"new-york/us"` is capitalized: `"New-York/Us"`.
+#[inline]
+pub fn title_segments(s: impl ToString) -> Result<String, Infallible> {
+    Ok(title_case(&s.to_string(), |c| {
+        c.is_whitespace() || matches!(c, '-' | '/' | '_')
+    }))
+}
+
+fn title_case(s: &str, is_delimiter: impl Fn(char) -> bool) -> String {
     let mut need_capitalization = true;
 
     // Sadly enough, we can't mutate a string when iterating over its chars, likely because it could
     // change the size of a char, "breaking" the char indices.
     let mut output = String::with_capacity(s.len());
     for c in s.chars() {
-        if c.is_whitespace() {
+        if is_delimiter(c) {
             output.push(c);
             need_capitalization = true;
         } else if need_capitalization {
@@ -533,7 +2245,8 @@ pub fn title(s: impl ToString) -> Result<String, Infallible> {
                 true => output.push(c),
                 false => output.extend(c.to_uppercase()),
             }
-            need_capitalization = false;
+            // Leading punctuation, e.g. a quote, does not start the word.
+            need_capitalization = !c.is_alphanumeric();
         } else {
             match c.is_lowercase() {
                 true => output.push(c),
@@ -541,9 +2254,141 @@ pub fn title(s: impl ToString) -> Result<String, Infallible> {
             }
         }
     }
+    output
+}
+
+/// Converts to a lowercase string which is safe to use in URLs, e.g. `"Hello, World!"` to
+/// `"hello-world"`
+///
+/// Common accented latin letters are replaced by their base letter, e.g. `é` by `e`. All other
+/// characters which are not ASCII letters or digits are replaced by a hyphen, but consecutive
+/// hyphens are merged into one, and there are no leading or trailing hyphens. A string without
+/// letters and digits thus becomes empty.
+pub fn slugify(s: impl ToString) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let mut slug = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        let replacement = match c {
+            'a'..='z' | '0'..='9' => None,
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => Some("a"),
+            'æ' => Some("ae"),
+            'ç' | 'ć' | 'č' => Some("c"),
+            'ď' | 'đ' | 'ð' => Some("d"),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => Some("e"),
+            'ğ' => Some("g"),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => Some("i"),
+            'ł' => Some("l"),
+            'ñ' | 'ń' | 'ň' => Some("n"),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => Some("o"),
+            'œ' => Some("oe"),
+            'ř' => Some("r"),
+            'ß' => Some("ss"),
+            'ś' | 'š' | 'ş' => Some("s"),
+            'ť' | 'ţ' => Some("t"),
+            'þ' => Some("th"),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => Some("u"),
+            'ý' | 'ÿ' => Some("y"),
+            'ź' | 'ż' | 'ž' => Some("z"),
+            _ => Some("-"),
+        };
+        match replacement {
+            None => slug.push(c),
+            Some("-") if slug.is_empty() || slug.ends_with('-') => {}
+            Some(replacement) => slug.push_str(replacement),
+        }
+    }
+    slug.truncate(slug.trim_end_matches('-').len());
+    Ok(slug)
+}
+
+/// Converts to `snake_case`, e.g. `"User name"` to `"user_name"`
+///
+/// Words are delimited by all characters that are not alphanumeric, and by changes from
+/// lowercase to uppercase like in `"userName"`. An uppercase letter followed by a lowercase
+/// letter starts a new word after a run of uppercase letters, so `"HTTPServer"` becomes
+/// `"http_server"`.
+#[inline]
+pub fn snake(s: impl ToString) -> Result<String, Infallible> {
+    Ok(words(&s.to_string())
+        .into_iter()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_"))
+}
+
+/// Converts to `kebab-case`, e.g. `"User name"` to `"user-name"`
+///
+/// Words are split like in [`snake`].
+#[inline]
+pub fn kebab(s: impl ToString) -> Result<String, Infallible> {
+    Ok(words(&s.to_string())
+        .into_iter()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-"))
+}
+
+/// Converts to `camelCase`, e.g. `"User name"` to `"userName"`
+///
+/// Words are split like in [`snake`].
+#[inline]
+pub fn camel(s: impl ToString) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let mut words = words(&s).into_iter();
+    let mut output = words.next().map(str::to_lowercase).unwrap_or_default();
+    words.for_each(|word| push_capitalized(&mut output, word));
+    Ok(output)
+}
+
+/// Converts to `PascalCase`, e.g. `"User name"` to `"UserName"`
+///
+/// Words are split like in [`snake`].
+#[inline]
+pub fn pascal(s: impl ToString) -> Result<String, Infallible> {
+    let s = s.to_string();
+    let mut output = String::with_capacity(s.len());
+    words(&s)
+        .into_iter()
+        .for_each(|word| push_capitalized(&mut output, word));
     Ok(output)
 }
 
+fn push_capitalized(output: &mut String, word: &str) {
+    let mut chars = word.chars();
+    if let Some(c) = chars.next() {
+        output.extend(c.to_uppercase());
+        output.push_str(&chars.as_str().to_lowercase());
+    }
+}
+
+/// Splits an identifier or label into its words, see [`snake`]
+fn words(s: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut prev = ' ';
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !c.is_alphanumeric() {
+            if let Some(start) = start.take() {
+                words.push(&s[start..i]);
+            }
+        } else if let Some(word_start) = start {
+            let next_is_lower = chars.peek().map_or(false, |&(_, c)| c.is_lowercase());
+            if c.is_uppercase() && (!prev.is_uppercase() || next_is_lower) {
+                words.push(&s[word_start..i]);
+                start = Some(i);
+            }
+        } else {
+            start = Some(i);
+        }
+        prev = c;
+    }
+    if let Some(start) = start {
+        words.push(&s[start..]);
+    }
+    words
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -556,6 +2401,37 @@ mod tests {
         assert_eq!(filesizeformat(&1000i32).unwrap().to_string(), "1 kB");
         assert_eq!(filesizeformat(&1023).unwrap().to_string(), "1.02 kB");
         assert_eq!(filesizeformat(&1024usize).unwrap().to_string(), "1.02 kB");
+        assert_eq!(filesizeformat(&-1500).unwrap().to_string(), "-1.50 kB");
+    }
+
+    #[cfg(feature = "humansize")]
+    #[test]
+    fn test_filesizeformat_binary() {
+        assert_eq!(filesizeformat_with(&0, true).unwrap().to_string(), "0 B");
+        assert_eq!(
+            filesizeformat_with(&1000, true).unwrap().to_string(),
+            "1000 B"
+        );
+        assert_eq!(
+            filesizeformat_with(&1023, true).unwrap().to_string(),
+            "1023 B"
+        );
+        assert_eq!(
+            filesizeformat_with(&1024, true).unwrap().to_string(),
+            "1 KiB"
+        );
+        assert_eq!(
+            filesizeformat_with(&1536, true).unwrap().to_string(),
+            "1.50 KiB"
+        );
+        assert_eq!(
+            filesizeformat_with(&1_048_576, true).unwrap().to_string(),
+            "1 MiB"
+        );
+        assert_eq!(
+            filesizeformat_with(&-2048, true).unwrap().to_string(),
+            "-2 KiB"
+        );
     }
 
     #[cfg(feature = "urlencode")]
@@ -604,6 +2480,21 @@ mod tests {
         assert_eq!(urlencode_strict("🦀").unwrap().to_string(), "%F0%9F%A6%80");
     }
 
+    #[cfg(feature = "urlencode")]
+    #[test]
+    fn test_urlencode_with_safe() {
+        let encode = |s: &str, safe: &str| urlencode_with_safe(s, safe).unwrap().to_string();
+        assert_eq!(encode("AZaz09_.-~", ""), "AZaz09_.-~");
+        assert_eq!(encode("/a b/c", "/"), "/a%20b/c");
+        assert_eq!(encode("/a b/c", ""), "%2Fa%20b%2Fc");
+        assert_eq!(encode("a=1&b=2", "=&"), "a=1&b=2");
+        assert_eq!(encode(":/?#[]@", ":/"), ":/%3F%23%5B%5D%40");
+        assert_eq!(encode("Château/🦀", "/"), "Ch%C3%A2teau/%F0%9F%A6%80");
+        assert_eq!(encode("", "/"), "");
+        // A safe non-ASCII character does not keep its bytes unencoded.
+        assert!(urlencode_with_safe("é", "é").is_err());
+    }
+
     #[test]
     fn test_linebreaks() {
         assert_eq!(
@@ -623,6 +2514,14 @@ mod tests {
             linebreaksbr("Foo\nBar\n\nBaz").unwrap().to_string(),
             "Foo<br/>Bar<br/><br/>Baz"
         );
+        assert_eq!(
+            linebreaksbr("Foo\r\nBar\r\n\r\nBaz").unwrap().to_string(),
+            "Foo<br/>Bar<br/><br/>Baz"
+        );
+        assert_eq!(linebreaksbr("Foo\rBar").unwrap().to_string(), "Foo<br/>Bar");
+        assert_eq!(linebreaksbr("Foo\n\r\n").unwrap().to_string(), "Foo");
+        assert_eq!(linebreaksbr("\nFoo").unwrap().to_string(), "<br/>Foo");
+        assert_eq!(nl2br("Foo\nBar\n").unwrap().to_string(), "Foo<br/>Bar");
     }
 
     #[test]
@@ -664,6 +2563,20 @@ mod tests {
         assert_eq!(trim(" Hello\tworld\t").unwrap().to_string(), "Hello\tworld");
     }
 
+    #[test]
+    fn test_trim_chars() {
+        assert_eq!(trim_chars("/a/b/", "/").unwrap(), "a/b");
+        assert_eq!(trim_chars("-=x=-", "=-").unwrap(), "x");
+        assert_eq!(trim_chars(" \tx\n", "").unwrap(), "x");
+        assert_eq!(trim_chars("xx", "x").unwrap(), "");
+        assert_eq!(trim_chars("«ü»", "«»").unwrap(), "ü");
+        assert_eq!(trim_start("//a/", "/").unwrap(), "a/");
+        assert_eq!(trim_start("  a  ", "").unwrap(), "a  ");
+        assert_eq!(trim_end("//a/", "/").unwrap(), "//a");
+        assert_eq!(trim_end("  a  ", "").unwrap(), "  a");
+        assert_eq!(trim_end(42000, "0").unwrap(), "42");
+    }
+
     #[test]
     fn test_truncate() {
         assert_eq!(truncate("hello", 2).unwrap().to_string(), "he...");
@@ -693,11 +2606,79 @@ mod tests {
         assert_eq!(truncate("🤚a🤚", 10).unwrap().to_string(), "🤚a🤚");
     }
 
+    #[test]
+    fn test_truncate_words() {
+        assert_eq!(truncate_words("hello", 2, true, "...").unwrap(), "he...");
+        let a = String::from("您好");
+        assert_eq!(a.len(), 6);
+        assert_eq!(String::from("您").len(), 3);
+        assert_eq!(truncate_words("您好", 1, true, "...").unwrap(), "您...");
+        assert_eq!(truncate_words("您好", 2, true, "...").unwrap(), "您...");
+        assert_eq!(truncate_words("您好", 3, true, "...").unwrap(), "您...");
+        assert_eq!(truncate_words("您好", 4, true, "...").unwrap(), "您好");
+        assert_eq!(truncate_words("您好", 5, true, "...").unwrap(), "您好");
+        assert_eq!(truncate_words("您好", 6, true, "...").unwrap(), "您好");
+        assert_eq!(truncate_words("您好", 7, true, "...").unwrap(), "您好");
+        let s = String::from("🤚a🤚");
+        assert_eq!(s.len(), 9);
+        assert_eq!(String::from("🤚").len(), 4);
+        assert_eq!(truncate_words("🤚a🤚", 1, true, "...").unwrap(), "🤚...");
+        assert_eq!(truncate_words("🤚a🤚", 2, true, "...").unwrap(), "🤚...");
+        assert_eq!(truncate_words("🤚a🤚", 3, true, "...").unwrap(), "🤚...");
+        assert_eq!(truncate_words("🤚a🤚", 4, true, "...").unwrap(), "🤚...");
+        assert_eq!(truncate_words("🤚a🤚", 5, true, "...").unwrap(), "🤚a...");
+        assert_eq!(truncate_words("🤚a🤚", 6, true, "...").unwrap(), "🤚a🤚");
+        assert_eq!(truncate_words("🤚a🤚", 6, true, "...").unwrap(), "🤚a🤚");
+        assert_eq!(truncate_words("🤚a🤚", 7, true, "...").unwrap(), "🤚a🤚");
+        assert_eq!(truncate_words("🤚a🤚", 8, true, "...").unwrap(), "🤚a🤚");
+        assert_eq!(truncate_words("🤚a🤚", 9, true, "...").unwrap(), "🤚a🤚");
+        assert_eq!(truncate_words("🤚a🤚", 10, true, "...").unwrap(), "🤚a🤚");
+
+        assert_eq!(
+            truncate_words("hello world", 8, false, "…").unwrap(),
+            "hello…"
+        );
+        assert_eq!(
+            truncate_words("hello world", 5, false, "…").unwrap(),
+            "hello…"
+        );
+        assert_eq!(
+            truncate_words("hello world", 6, false, "…").unwrap(),
+            "hello…"
+        );
+        assert_eq!(
+            truncate_words("hello  world foo", 14, false, "…").unwrap(),
+            "hello  world…"
+        );
+        assert_eq!(
+            truncate_words("hello world", 3, false, "…").unwrap(),
+            "hel…"
+        );
+        assert_eq!(
+            truncate_words("hello world", 8, true, "…").unwrap(),
+            "hello wo…"
+        );
+        assert_eq!(
+            truncate_words("hello world", 11, false, "…").unwrap(),
+            "hello world"
+        );
+        assert_eq!(
+            truncate_words("hello world", 100, false, "…").unwrap(),
+            "hello world"
+        );
+        assert_eq!(truncate_words("hello", 0, false, "…").unwrap(), "…");
+        assert_eq!(truncate_words("hello", 0, true, "...").unwrap(), "...");
+        assert_eq!(truncate_words("", 0, false, "…").unwrap(), "");
+        assert_eq!(truncate_words("a b 您好", 6, false, "…").unwrap(), "a b…");
+        assert_eq!(truncate_words(1234567, 3, false, "").unwrap(), "123");
+    }
+
     #[test]
     fn test_indent() {
         assert_eq!(indent("hello", 2).unwrap().to_string(), "hello");
         assert_eq!(indent("hello\n", 2).unwrap().to_string(), "hello\n");
         assert_eq!(indent("hello\nfoo", 2).unwrap().to_string(), "hello\n  foo");
+        assert_eq!(indent("a\nb\n", 2).unwrap().to_string(), "a\n  b\n");
         assert_eq!(
             indent("hello\nfoo\n bar", 4).unwrap().to_string(),
             "hello\n    foo\n     bar"
@@ -708,6 +2689,589 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_indent_with() {
+        assert_eq!(indent_with("hello", 2, false, false).unwrap(), "hello");
+        assert_eq!(indent_with("hello\n", 2, false, false).unwrap(), "hello\n");
+        assert_eq!(
+            indent_with("hello\nfoo", 2, false, false).unwrap(),
+            "hello\n  foo"
+        );
+        assert_eq!(indent_with("a\nb\n", 2, false, false).unwrap(), "a\n  b\n");
+        assert_eq!(
+            indent_with("hello\nfoo\n bar", 4, false, false).unwrap(),
+            "hello\n    foo\n     bar"
+        );
+        assert_eq!(
+            indent_with("hello", 267_332_238_858_u64, false, false).unwrap(),
+            "hello"
+        );
+        assert_eq!(indent_with("a\nb", -2, false, false).unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn test_indent_options() {
+        assert_eq!(indent_with("a\nb", 2, true, false).unwrap(), "  a\n  b");
+        assert_eq!(indent_with("", 2, true, false).unwrap(), "");
+        assert_eq!(
+            indent_with("a\n\nb\n", 2, false, false).unwrap(),
+            "a\n\n  b\n"
+        );
+        assert_eq!(
+            indent_with("a\n\nb\n", 2, false, true).unwrap(),
+            "a\n  \n  b\n"
+        );
+        assert_eq!(
+            indent_with("a\r\n\r\nb", 1, false, false).unwrap(),
+            "a\r\n\r\n b"
+        );
+        assert_eq!(indent_with("a\nb", "\t", false, false).unwrap(), "a\n\tb");
+        assert_eq!(
+            indent_with("a\nb", String::from("> "), true, false).unwrap(),
+            "> a\n> b"
+        );
+    }
+
+    #[test]
+    fn test_hang_indent() {
+        assert_eq!(hang_indent("hello", "- ", "  ").unwrap(), "- hello");
+        assert_eq!(hang_indent("", "- ", "  ").unwrap(), "");
+        assert_eq!(
+            hang_indent("one\ntwo\nthree", "1. ", "   ").unwrap(),
+            "1. one\n   two\n   three"
+        );
+        assert_eq!(hang_indent("a\nb\n", "* ", "  ").unwrap(), "* a\n  b\n");
+
+        // Like with `indent`, too long values are returned unchanged.
+        let long = "a\n".repeat(MAX_LEN);
+        assert_eq!(hang_indent(&long, "- ", "  ").unwrap(), long);
+        let prefix = " ".repeat(MAX_LEN);
+        assert_eq!(hang_indent("a\nb", &prefix, "  ").unwrap(), "a\nb");
+        assert_eq!(hang_indent("a\nb", "- ", &prefix).unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn test_affixes() {
+        assert_eq!(prefix("5", "$").unwrap(), "$5");
+        assert_eq!(prefix("", "$").unwrap(), "");
+        assert_eq!(suffix("Hello", '!').unwrap(), "Hello!");
+        assert_eq!(suffix("", '!').unwrap(), "");
+        assert_eq!(wrap(42, "[", "]").unwrap(), "[42]");
+        assert_eq!(wrap("", "[", "]").unwrap(), "");
+    }
+
+    #[test]
+    fn test_zip() {
+        let zipped = zip([1, 2, 3], ["a", "b"]).unwrap().collect::<Vec<_>>();
+        assert_eq!(zipped, [(1, "a"), (2, "b")]);
+        assert_eq!(zip(Vec::<u8>::new(), [1]).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(b"", false).unwrap(), "");
+        assert_eq!(hex([0x00, 0x0f, 0xab, 0xff], false).unwrap(), "000fabff");
+        assert_eq!(hex([0x00, 0x0f, 0xab, 0xff], true).unwrap(), "000FABFF");
+        assert_eq!(hex("hé", false).unwrap(), "68c3a9");
+    }
+
+    #[test]
+    fn test_unhex() {
+        assert_eq!(unhex("").unwrap(), "");
+        assert_eq!(unhex("68C3a9").unwrap(), "hé");
+        for s in ["", "foo", "hé", "\0\n"] {
+            assert_eq!(unhex(hex(s, false).unwrap()).unwrap(), s);
+            assert_eq!(unhex(hex(s, true).unwrap()).unwrap(), s);
+        }
+        assert!(unhex("abc").is_err());
+        assert!(unhex("zz").is_err());
+        assert!(unhex("+1").is_err());
+        assert!(unhex("ff").is_err());
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("", 5).unwrap(), "");
+        assert_eq!(truncate_middle("short", 5).unwrap(), "short");
+        assert_eq!(truncate_middle("short", 10).unwrap(), "short");
+        assert_eq!(truncate_middle("abcdefgh", 5).unwrap(), "ab…gh");
+        assert_eq!(truncate_middle("abcdefgh", 4).unwrap(), "ab…h");
+        assert_eq!(truncate_middle("abcdefgh", 1).unwrap(), "…");
+        assert_eq!(truncate_middle("abcdefgh", 0).unwrap(), "");
+        assert_eq!(truncate_middle("äöüßéèàç", 5).unwrap(), "äö…àç");
+        assert_eq!(
+            truncate_middle("/home/user/projects/askama/templates/index.html", 20).unwrap(),
+            "/home/user…ndex.html"
+        );
+    }
+
+    #[test]
+    fn test_default() {
+        assert_eq!(default("", "N/A", false, false).unwrap().to_string(), "N/A");
+        assert_eq!(default("x", "N/A", false, false).unwrap().to_string(), "x");
+        assert_eq!(
+            default("  ", "N/A", false, false).unwrap().to_string(),
+            "  "
+        );
+        assert_eq!(
+            default("  ", "N/A", true, false).unwrap().to_string(),
+            "N/A"
+        );
+        assert_eq!(
+            default("\t\t", "N/A", true, false).unwrap().to_string(),
+            "N/A"
+        );
+        assert_eq!(
+            default(" \t\n", "N/A", true, false).unwrap().to_string(),
+            "N/A"
+        );
+        assert_eq!(
+            default(" x ", "N/A", true, false).unwrap().to_string(),
+            " x "
+        );
+        assert_eq!(
+            default(&String::new(), "N/A", false, false)
+                .unwrap()
+                .to_string(),
+            "N/A"
+        );
+        assert_eq!(
+            default(None::<&str>, 0, false, false).unwrap().to_string(),
+            "0"
+        );
+        assert_eq!(
+            default(Some("  "), 0, true, false).unwrap().to_string(),
+            "0"
+        );
+        assert_eq!(default(Some(5), 0, false, false).unwrap().to_string(), "5");
+        assert_eq!(default(0, 1, false, false).unwrap().to_string(), "0");
+        assert_eq!(default(0, 1, false, true).unwrap().to_string(), "1");
+        assert_eq!(default(0.0, 1, false, true).unwrap().to_string(), "1");
+        assert_eq!(default(-2, 1, false, true).unwrap().to_string(), "-2");
+        assert_eq!(default(false, "no", false, true).unwrap().to_string(), "no");
+        assert_eq!(
+            default(true, "no", false, true).unwrap().to_string(),
+            "true"
+        );
+        assert_eq!(default(Some(0u8), 1, false, true).unwrap().to_string(), "1");
+        assert_eq!(default("", "N/A", false, true).unwrap().to_string(), "N/A");
+        assert_eq!(default("  ", "N/A", false, true).unwrap().to_string(), "  ");
+        assert_eq!(default("  ", "N/A", true, true).unwrap().to_string(), "N/A");
+    }
+
+    #[test]
+    fn test_yesno() {
+        assert_eq!(yesno(true, "yes,no").unwrap(), "yes");
+        assert_eq!(yesno(false, "yes,no").unwrap(), "no");
+        assert_eq!(yesno(None::<bool>, "yes,no").unwrap(), "no");
+        assert_eq!(yesno(Some(true), "yes,no,maybe").unwrap(), "yes");
+        assert_eq!(yesno(Some(false), "yes,no,maybe").unwrap(), "no");
+        assert_eq!(yesno(None::<bool>, "yes,no,maybe").unwrap(), "maybe");
+        assert_eq!(yesno(true, ",off").unwrap(), "");
+        assert!(yesno(true, "yes").is_err());
+        assert!(yesno(true, "").is_err());
+        assert!(yesno(true, "a,b,c,d").is_err());
+    }
+
+    #[test]
+    fn test_spaceless() {
+        assert_eq!(spaceless("").unwrap(), "");
+        assert_eq!(spaceless("<a> </a>").unwrap(), "<a></a>");
+        assert_eq!(
+            spaceless("<p>\n  <b>bold</b> and <i>italic</i>\n</p>\n").unwrap(),
+            "<p><b>bold</b> and <i>italic</i></p>\n"
+        );
+        assert_eq!(spaceless("  <p> text </p>  ").unwrap(), "  <p> text </p>  ");
+        assert_eq!(spaceless("a > b\t\t< c").unwrap(), "a > b\t\t< c");
+        assert_eq!(spaceless("a >\t\t< c").unwrap(), "a >< c");
+        assert_eq!(spaceless("<br>\r\n\t<br>").unwrap(), "<br><br>");
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(
+            columns([1, 2, 3, 4, 5, 6], 3).unwrap(),
+            vec![vec![1, 2], vec![3, 4], vec![5, 6]]
+        );
+        assert_eq!(
+            columns([1, 2, 3, 4, 5, 6, 7], 3).unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5], vec![6, 7]]
+        );
+        assert_eq!(
+            columns([1, 2, 3, 4, 5], 2).unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5]]
+        );
+        assert_eq!(
+            columns([1, 2], 4).unwrap(),
+            vec![vec![1], vec![2], vec![], Vec::<i32>::new()]
+        );
+        assert_eq!(columns([1, 2], 1).unwrap(), vec![vec![1, 2]]);
+        assert_eq!(columns([0; 0], 2).unwrap(), [[0; 0], [0; 0]]);
+        assert!(columns([1, 2], 0).is_err());
+    }
+
+    #[test]
+    fn test_case_conversions() {
+        for (input, snake_case, kebab_case, camel_case, pascal_case) in [
+            (
+                "user name",
+                "user_name",
+                "user-name",
+                "userName",
+                "UserName",
+            ),
+            ("userName", "user_name", "user-name", "userName", "UserName"),
+            ("UserName", "user_name", "user-name", "userName", "UserName"),
+            (
+                "user_name",
+                "user_name",
+                "user-name",
+                "userName",
+                "UserName",
+            ),
+            (
+                "user-name",
+                "user_name",
+                "user-name",
+                "userName",
+                "UserName",
+            ),
+            (
+                " User  NAME ",
+                "user_name",
+                "user-name",
+                "userName",
+                "UserName",
+            ),
+            (
+                "HTTPServer",
+                "http_server",
+                "http-server",
+                "httpServer",
+                "HttpServer",
+            ),
+            (
+                "parseHTTP2Response",
+                "parse_http2_response",
+                "parse-http2-response",
+                "parseHttp2Response",
+                "ParseHttp2Response",
+            ),
+            ("v2 api", "v2_api", "v2-api", "v2Api", "V2Api"),
+            (
+                "Émile zola",
+                "émile_zola",
+                "émile-zola",
+                "émileZola",
+                "ÉmileZola",
+            ),
+            ("", "", "", "", ""),
+            ("--", "", "", "", ""),
+        ] {
+            assert_eq!(snake(input).unwrap(), snake_case, "{input:?}");
+            assert_eq!(kebab(input).unwrap(), kebab_case, "{input:?}");
+            assert_eq!(camel(input).unwrap(), camel_case, "{input:?}");
+            assert_eq!(pascal(input).unwrap(), pascal_case, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World! 2024").unwrap(), "hello-world-2024");
+        assert_eq!(slugify("  --Hello--  ").unwrap(), "hello");
+        assert_eq!(
+            slugify("Crème brûlée für Ærøskøbing").unwrap(),
+            "creme-brulee-fur-aeroskobing"
+        );
+        assert_eq!(slugify("Straße").unwrap(), "strasse");
+        assert_eq!(slugify("ÉCOLE").unwrap(), "ecole");
+        assert_eq!(slugify("a_b.c/d").unwrap(), "a-b-c-d");
+        assert_eq!(slugify("日本 2024").unwrap(), "2024");
+        assert_eq!(slugify("").unwrap(), "");
+        assert_eq!(slugify("!?, -").unwrap(), "");
+        assert_eq!(slugify(42).unwrap(), "42");
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(
+            replace("foo foo foo", "foo", "bar", None).unwrap(),
+            "bar bar bar"
+        );
+        assert_eq!(
+            replace("foo foo foo", "foo", "bar", Some(2)).unwrap(),
+            "bar bar foo"
+        );
+        assert_eq!(
+            replace("foo foo", "foo", "bar", Some(0)).unwrap(),
+            "foo foo"
+        );
+        assert_eq!(replace("foo", "foo", "bar", Some(5)).unwrap(), "bar");
+        assert_eq!(
+            replace(String::from("aaa"), "a", "", Some(1)).unwrap(),
+            "aa"
+        );
+        assert_eq!(replace(1001, "0", String::from("1"), None).unwrap(), "1111");
+        assert_eq!(replace("abc", "x", "y", None).unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_split() {
+        let split = |s: &'static str, sep| split(s, sep).unwrap().collect::<Vec<_>>();
+        assert_eq!(split("a,b,c", ","), ["a", "b", "c"]);
+        assert_eq!(split("a,,b,", ","), ["a", "", "b", ""]);
+        assert_eq!(split("a, b", ", "), ["a", "b"]);
+        assert_eq!(split("abc", ","), ["abc"]);
+        assert_eq!(split("", ","), [""]);
+        assert!(super::split("abc", "").is_err());
+    }
+
+    #[test]
+    fn test_pluralize() {
+        assert_eq!(pluralize(1, "", "s").unwrap(), "");
+        assert_eq!(pluralize(0, "", "s").unwrap(), "s");
+        assert_eq!(pluralize(2u8, "", "s").unwrap(), "s");
+        assert_eq!(pluralize(-1, "", "s").unwrap(), "s");
+        let (one, five): (&usize, &&i64) = (&1, &&5);
+        assert_eq!(pluralize(one, "y", "ies").unwrap(), "y");
+        assert_eq!(pluralize(five, "y", "ies").unwrap(), "ies");
+    }
+
+    #[test]
+    fn test_numberformat() {
+        assert_eq!(numberformat(0, None, ".", ",").unwrap(), "0");
+        assert_eq!(numberformat(999, None, ".", ",").unwrap(), "999");
+        assert_eq!(numberformat(1234567, None, ".", ",").unwrap(), "1,234,567");
+        assert_eq!(
+            numberformat(-1234567, None, ".", ",").unwrap(),
+            "-1,234,567"
+        );
+        assert_eq!(numberformat(-123, None, ".", ",").unwrap(), "-123");
+        assert_eq!(numberformat(1234.5, None, ".", ",").unwrap(), "1,234.5");
+        assert_eq!(numberformat(1234.5, Some(2), ".", ",").unwrap(), "1,234.50");
+        assert_eq!(numberformat(1234.567, Some(0), ".", ",").unwrap(), "1,235");
+        assert_eq!(
+            numberformat(-1234.5, Some(2), ",", ".").unwrap(),
+            "-1.234,50"
+        );
+        assert_eq!(numberformat(1234, Some(2), ",", " ").unwrap(), "1 234,00");
+        assert_eq!(numberformat(42u8, Some(1), ".", ",").unwrap(), "42.0");
+        assert_eq!(
+            numberformat(u64::MAX, None, ".", ",").unwrap(),
+            "18,446,744,073,709,551,615"
+        );
+        assert_eq!(
+            numberformat(i128::MIN, Some(1), ".", "'").unwrap(),
+            "-170'141'183'460'469'231'731'687'303'715'884'105'728.0"
+        );
+        assert_eq!(numberformat(f64::NAN, Some(2), ".", ",").unwrap(), "NaN");
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_round() {
+        assert_eq!(round(2.567, 1, "common").unwrap(), 2.6);
+        assert_eq!(round(2.5, 0, "common").unwrap(), 3.0);
+        assert_eq!(round(-2.5, 0, "common").unwrap(), -3.0);
+        assert_eq!(round(2.1, 0, "ceil").unwrap(), 3.0);
+        assert_eq!(round(2.9, 0, "floor").unwrap(), 2.0);
+        assert_eq!(round(2.567, 2, "floor").unwrap(), 2.56);
+        assert_eq!(round(-1.234, 1, "ceil").unwrap(), -1.2);
+        assert_eq!(round(1234, 2, "common").unwrap(), 1234.0);
+        assert_eq!(round(1.5f32, 0, "common").unwrap(), 2.0);
+        assert_eq!(round(1.0e300, 100, "common").unwrap(), 1.0e300);
+        assert_eq!(round(-0.4, 0, "common").unwrap().to_string(), "0");
+        assert!(round(f64::NAN, 2, "common").unwrap().is_nan());
+        assert_eq!(round(f64::INFINITY, 0, "floor").unwrap(), f64::INFINITY);
+        assert!(round(1.5, 0, "banker").is_err());
+    }
+
+    #[test]
+    fn test_striptags() {
+        assert_eq!(striptags("").unwrap(), "");
+        assert_eq!(striptags("plain text").unwrap(), "plain text");
+        assert_eq!(
+            striptags("<p>Hello <b>Ferris</b>!</p>").unwrap(),
+            "Hello Ferris!"
+        );
+        assert_eq!(striptags("a<br/>b").unwrap(), "ab");
+        assert_eq!(striptags("a<br/>\nb").unwrap(), "a b");
+        assert_eq!(striptags("  a \n\t b  ").unwrap(), "a b");
+        assert_eq!(striptags("<!-- <b>x</b> -->kept<!---->").unwrap(), "kept");
+        assert_eq!(striptags("<?xml version=\"1.0\"?><a>x</a>").unwrap(), "x");
+        assert_eq!(striptags("1 < 2 and 3 <= 4").unwrap(), "1 < 2 and 3 <= 4");
+        assert_eq!(striptags("text <b").unwrap(), "text <b");
+        assert_eq!(striptags("text <").unwrap(), "text <");
+        assert_eq!(striptags("<!-- unclosed").unwrap(), "<!-- unclosed");
+        assert_eq!(striptags("&amp; <i>&lt;</i>").unwrap(), "&amp; &lt;");
+        assert_eq!(striptags("<p>游戏</p>").unwrap(), "游戏");
+    }
+
+    #[test]
+    fn test_wordwrap() {
+        assert_eq!(wordwrap("", 10, false).unwrap(), "");
+        assert_eq!(wordwrap("short text", 10, false).unwrap(), "short text");
+        assert_eq!(wordwrap("short text", 9, false).unwrap(), "short\ntext");
+        assert_eq!(wordwrap("a  b   c\td", 79, false).unwrap(), "a b c d");
+        assert_eq!(
+            wordwrap("one two\n\nthree four", 7, false).unwrap(),
+            "one two\n\nthree\nfour"
+        );
+        assert_eq!(
+            wordwrap("a verylongword b", 4, false).unwrap(),
+            "a\nverylongword\nb"
+        );
+        assert_eq!(
+            wordwrap("a verylongword b", 4, true).unwrap(),
+            "a\nvery\nlong\nword\nb"
+        );
+        assert_eq!(wordwrap("abcdef", 3, true).unwrap(), "abc\ndef");
+        assert_eq!(wordwrap("äöü äöü", 3, false).unwrap(), "äöü\näöü");
+        assert_eq!(wordwrap("ab cd", 0, true).unwrap(), "a\nb\nc\nd");
+        assert_eq!(wordwrap("line\r\nnext", 79, false).unwrap(), "line\nnext");
+    }
+
+    #[test]
+    fn test_first_last() {
+        assert_eq!(first([1, 2, 3]).unwrap(), Some(1));
+        assert_eq!(last([1, 2, 3]).unwrap(), Some(3));
+        assert_eq!(first(Vec::<u8>::new()).unwrap(), None);
+        assert_eq!(last(&[] as &[u8]).unwrap(), None);
+        assert_eq!(first(&vec!["a", "b"]).unwrap(), Some(&"a"));
+        assert_eq!(last(1..5).unwrap(), Some(4));
+        assert_eq!(FirstLastStr::filter_first("héllo").unwrap(), Some('h'));
+        assert_eq!(FirstLastStr::filter_last("héllö").unwrap(), Some('ö'));
+        assert_eq!(FirstLastStr::filter_last("").unwrap(), None);
+    }
+
+    #[test]
+    fn test_batch() {
+        assert_eq!(
+            batch([1, 2, 3, 4, 5, 6], 3).unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5, 6]]
+        );
+        assert_eq!(
+            batch([1, 2, 3, 4, 5], 2).unwrap(),
+            vec![vec![1, 2], vec![3, 4], vec![5]]
+        );
+        assert_eq!(batch([1, 2], 5).unwrap(), vec![vec![1, 2]]);
+        assert_eq!(batch([0; 0], 2).unwrap(), Vec::<Vec<i32>>::new());
+        assert!(batch([1, 2], 0).is_err());
+
+        assert_eq!(
+            batch_filled([1, 2, 3], 2, "-").unwrap(),
+            vec![
+                vec![BatchItem::Item(1), BatchItem::Item(2)],
+                vec![BatchItem::Item(3), BatchItem::Fill("-")]
+            ]
+        );
+        assert_eq!(
+            batch_filled([1, 2], 2, 0).unwrap(),
+            vec![vec![BatchItem::Item(1), BatchItem::Item(2)]]
+        );
+        assert_eq!(
+            batch_filled([0; 0], 2, 0).unwrap(),
+            Vec::<Vec<BatchItem<i32, i32>>>::new()
+        );
+        assert!(batch_filled([1, 2], 0, 0).is_err());
+        assert_eq!(BatchItem::<i32, &str>::Fill("x").to_string(), "x");
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(sort([3, 1, 2], false).unwrap(), [1, 2, 3]);
+        assert_eq!(sort([3, 1, 2], true).unwrap(), [3, 2, 1]);
+        assert_eq!(sort(["b", "a", "c"], false).unwrap(), ["a", "b", "c"]);
+        assert_eq!(sort([0u8; 0], false).unwrap(), [0u8; 0]);
+
+        let pairs = [(1, 'a'), (0, 'b'), (1, 'c'), (0, 'd')];
+        let by_number = |a: &&(i32, char), b: &&(i32, char)| a.0.cmp(&b.0);
+        assert_eq!(
+            sort_by(&pairs, by_number, false).unwrap(),
+            [&(0, 'b'), &(0, 'd'), &(1, 'a'), &(1, 'c')]
+        );
+        assert_eq!(
+            sort_by(&pairs, by_number, true).unwrap(),
+            [&(1, 'a'), &(1, 'c'), &(0, 'b'), &(0, 'd')]
+        );
+    }
+
+    #[test]
+    fn test_unique() {
+        assert_eq!(
+            unique([3, 1, 3, 2, 1]).unwrap().collect::<Vec<_>>(),
+            [3, 1, 2]
+        );
+        assert_eq!(
+            unique(&["a", "b", "a"]).unwrap().collect::<Vec<_>>(),
+            [&"a", &"b"]
+        );
+        assert_eq!(unique([0u8; 0]).unwrap().count(), 0);
+        assert_eq!(
+            unique_by([(1, 'a'), (2, 'a'), (1, 'b')], |&(n, _)| n)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            [(1, 'a'), (2, 'a')]
+        );
+
+        // Only the elements which are needed are iterated.
+        let mut iterated = 0;
+        let items = (0..).inspect(|_| iterated += 1).map(|i| i / 2);
+        assert_eq!(
+            unique(items).unwrap().take(3).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(iterated, 5);
+    }
+
+    #[test]
+    fn test_groupby() {
+        let words = ["banana", "apple", "cherry", "avocado", "blueberry"];
+        assert_eq!(
+            groupby(words, |w| w.as_bytes()[0]).unwrap(),
+            [
+                (b'a', vec!["apple", "avocado"]),
+                (b'b', vec!["banana", "blueberry"]),
+                (b'c', vec!["cherry"]),
+            ]
+        );
+        assert_eq!(
+            groupby(1..=6, |n| n % 3).unwrap(),
+            [(0, vec![3, 6]), (1, vec![1, 4]), (2, vec![2, 5])]
+        );
+        assert!(groupby(Vec::<i32>::new(), |n| *n).unwrap().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_min_max() {
+        assert_eq!(min([3, 1, 2]).unwrap(), Some(1));
+        assert_eq!(max([3, 1, 2]).unwrap(), Some(3));
+        assert_eq!(min(Vec::<i32>::new()).unwrap(), None);
+        assert_eq!(max(Vec::<i32>::new()).unwrap(), None);
+        assert_eq!(min(&["b", "a", "c"]).unwrap(), Some(&"a"));
+        assert_eq!(max([1.5, f64::NAN, 2.5]).unwrap(), Some(2.5));
+        assert_eq!(min([0.5, -1.5]).unwrap(), Some(-1.5));
+
+        let pairs = [(1, 'a'), (1, 'b')];
+        let (first, last) = (&pairs[0], &pairs[1]);
+        assert_eq!(min(pairs.iter().map(|p| p.0)).unwrap(), Some(1));
+        assert_eq!(min(&pairs).unwrap(), Some(first));
+        assert_eq!(max(&pairs).unwrap(), Some(last));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_sum() {
+        assert_eq!(sum([1, 2, 3]).unwrap(), 6);
+        assert_eq!(sum(&[1u8, 2]).unwrap(), 3u8);
+        assert_eq!(sum(Vec::<i64>::new()).unwrap(), 0);
+        assert_eq!(sum([0.5, 0.25]).unwrap(), 0.75);
+        assert_eq!(sum(Vec::<f64>::new()).unwrap(), 0.0);
+        let prices = [1.5f32, 2.0];
+        assert_eq!(sum(prices.iter().collect::<Vec<_>>()).unwrap(), 3.5);
+    }
+
     #[cfg(feature = "num-traits")]
     #[test]
     #[allow(clippy::float_cmp)]
@@ -768,7 +3332,63 @@ mod tests {
         );
     }
 
-    #[cfg(feature = "num-traits")]
+    #[test]
+    fn test_format_float() {
+        assert_eq!(format_float(1.23456, 2, false).unwrap(), "1.23");
+        assert_eq!(format_float(2.5f32, 2, false).unwrap(), "2.50");
+        assert_eq!(format_float(2.5, 2, true).unwrap(), "2.5");
+        assert_eq!(format_float(2.0, 2, true).unwrap(), "2");
+        assert_eq!(format_float(10, 0, true).unwrap(), "10");
+        assert_eq!(format_float(7u8, 1, false).unwrap(), "7.0");
+        assert_eq!(format_float(-0.0, 2, false).unwrap(), "0.00");
+        assert_eq!(format_float(-0.001, 2, false).unwrap(), "0.00");
+        assert_eq!(format_float(-0.001, 2, true).unwrap(), "0");
+        assert_eq!(format_float(-1.5, 1, false).unwrap(), "-1.5");
+        assert_eq!(
+            format_float(1e21, 1, true).unwrap(),
+            "1000000000000000000000"
+        );
+        assert_eq!(format_float(f64::NAN, 2, false).unwrap(), "NaN");
+        assert_eq!(format_float(f64::INFINITY, 2, true).unwrap(), "inf");
+        assert_eq!(format_float(f64::NEG_INFINITY, 2, false).unwrap(), "-inf");
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let input = ["a", "b"];
+        let mut iter = enumerate(&input).unwrap();
+        assert_eq!(iter.next(), Some((0, &"a")));
+        assert_eq!(iter.next(), Some((1, &"b")));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_take_skip() {
+        let input = [1, 2, 3, 4];
+        assert_eq!(take(&input, 2).unwrap().collect::<Vec<_>>(), [&1, &2]);
+        assert_eq!(take(&input, 10).unwrap().count(), 4);
+        assert_eq!(skip(&input, 3).unwrap().collect::<Vec<_>>(), [&4]);
+        assert_eq!(skip(&input, 10).unwrap().count(), 0);
+        let iter = take(skip(0..10, 2).unwrap(), 3).unwrap();
+        assert_eq!(iter.collect::<Vec<_>>(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn test_windows() {
+        let input = [1, 2, 3, 4];
+        let mut iter = windows(&input, 2).unwrap();
+        assert_eq!(iter.next(), Some(&[1, 2][..]));
+        assert_eq!(iter.next(), Some(&[2, 3][..]));
+        assert_eq!(iter.next(), Some(&[3, 4][..]));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(windows(&input, 4).unwrap().count(), 1);
+        assert_eq!(windows(&input, 5).unwrap().count(), 0);
+        let empty: &[i32] = &[];
+        assert_eq!(windows(empty, 1).unwrap().count(), 0);
+        assert!(windows(&input, 0).is_err());
+    }
+
     #[test]
     #[allow(clippy::float_cmp)]
     fn test_abs() {
@@ -778,6 +3398,16 @@ mod tests {
         assert_eq!(abs(-1.0).unwrap(), 1.0);
         assert_eq!(abs(1.0_f64).unwrap(), 1.0_f64);
         assert_eq!(abs(-1.0_f64).unwrap(), 1.0_f64);
+        assert_eq!(abs(-2.5_f32).unwrap(), 2.5_f32);
+        assert_eq!(abs(7u8).unwrap(), 7u8);
+        assert_eq!(abs(i32::MIN).unwrap(), i32::MAX);
+        assert_eq!(abs(i8::MIN + 1).unwrap(), i8::MAX);
+        assert_eq!(abs(-0.0_f64).unwrap().to_bits(), 0.0_f64.to_bits());
+        assert!(abs(f64::NAN).unwrap().is_nan());
+        assert_eq!(abs(f64::NEG_INFINITY).unwrap(), f64::INFINITY);
+
+        let value: &&i64 = &&-3;
+        assert_eq!(abs(value).unwrap(), 3);
     }
 
     #[test]
@@ -797,6 +3427,23 @@ mod tests {
         );
         assert_eq!(capitalize("ß").unwrap().to_string(), "SS".to_string());
         assert_eq!(capitalize("ßß").unwrap().to_string(), "SSß".to_string());
+        assert_eq!(capitalize("ŉ").unwrap().to_string(), "ʼN");
+        assert_eq!(capitalize("ﬁx").unwrap().to_string(), "FIx");
+    }
+
+    #[test]
+    fn test_capitalize_keep_rest() {
+        assert_eq!(
+            capitalize_with("macOS", false).unwrap().to_string(),
+            "MacOS"
+        );
+        assert_eq!(
+            capitalize_with("iPhone X", false).unwrap().to_string(),
+            "IPhone X"
+        );
+        assert_eq!(capitalize_with("", false).unwrap().to_string(), "");
+        assert_eq!(capitalize_with("ßÄ", false).unwrap().to_string(), "SSÄ");
+        assert_eq!(capitalize_with("ﬀOO", false).unwrap().to_string(), "FFOO");
     }
 
     #[test]
@@ -833,11 +3480,31 @@ mod tests {
         assert_eq!(&title("foo  bar ").unwrap(), "Foo  Bar ");
         assert_eq!(&title("fOO").unwrap(), "Foo");
         assert_eq!(&title("fOo BaR").unwrap(), "Foo Bar");
+        assert_eq!(&title("well-known").unwrap(), "Well-known");
+        assert_eq!(&title("it's").unwrap(), "It's");
+        assert_eq!(&title("don't STOP").unwrap(), "Don't Stop");
+        assert_eq!(&title("o'NEILL's").unwrap(), "O'neill's");
+        assert_eq!(&title("well-being").unwrap(), "Well-being");
+        assert_eq!(&title("\"quoted\" (text)").unwrap(), "\"Quoted\" (Text)");
+        assert_eq!(&title("-dash ...dots").unwrap(), "-Dash ...Dots");
+        assert_eq!(&title("1st place").unwrap(), "1st Place");
+        assert_eq!(&title("«élan»").unwrap(), "«Élan»");
+    }
+
+    #[test]
+    fn test_title_segments() {
+        assert_eq!(&title_segments("").unwrap(), "");
+        assert_eq!(&title_segments("foo bar").unwrap(), "Foo Bar");
+        assert_eq!(&title_segments("well-known").unwrap(), "Well-Known");
+        assert_eq!(&title_segments("new-york/us").unwrap(), "New-York/Us");
+        assert_eq!(&title_segments("SNAKE_CASE--x").unwrap(), "Snake_Case--X");
+        assert_eq!(&title_segments("(new-york)").unwrap(), "(New-York)");
     }
 
     #[test]
     fn fuzzed_indent_filter() {
         let s = "hello\nfoo\nbar".to_string().repeat(1024);
         assert_eq!(indent(s.clone(), 4).unwrap().to_string(), s);
+        assert_eq!(indent_with(s.clone(), 4, false, false).unwrap(), s);
     }
 }
@@ -1,4 +1,11 @@
+use std::fmt::{self, Display, Write};
 use std::iter::{Enumerate, Peekable};
+use std::ops::Deref;
+use std::vec;
+
+use askama_escape::{Escaper, MarkupDisplay};
+
+use crate::HtmlSafe;
 
 pub struct TemplateLoop<I>
 where
@@ -46,3 +53,189 @@ pub struct LoopItem {
     pub first: bool,
     pub last: bool,
 }
+
+/// Returns an iterator over the items of `iter` and their number, for `loop.length`
+///
+/// If the size hint of `iter` does not tell its exact length, e.g. because it is filtered,
+/// the items are collected first.
+pub fn loop_length<I: Iterator>(iter: I) -> (LoopLength<I>, usize) {
+    match iter.size_hint() {
+        (lower, Some(upper)) if lower == upper => (LoopLength::Known(iter), lower),
+        _ => {
+            let items = iter.collect::<Vec<_>>();
+            let length = items.len();
+            (LoopLength::Collected(items.into_iter()), length)
+        }
+    }
+}
+
+/// The iterator returned by [`loop_length()`]
+pub enum LoopLength<I: Iterator> {
+    Known(I),
+    Collected(vec::IntoIter<I::Item>),
+}
+
+impl<I: Iterator> Iterator for LoopLength<I> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            LoopLength::Known(iter) => iter.next(),
+            LoopLength::Collected(iter) => iter.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            LoopLength::Known(iter) => iter.size_hint(),
+            LoopLength::Collected(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// The values on which the `?.` operator can be used in templates: `Option`s and references to
+/// them
+pub trait OptionalChain {
+    type Value;
+
+    fn into_option(self) -> Option<Self::Value>;
+}
+
+impl<T> OptionalChain for Option<T> {
+    type Value = T;
+
+    #[inline]
+    fn into_option(self) -> Option<T> {
+        self
+    }
+}
+
+impl<'a, T> OptionalChain for &'a Option<T> {
+    type Value = &'a T;
+
+    #[inline]
+    fn into_option(self) -> Option<&'a T> {
+        self.as_ref()
+    }
+}
+
+impl<'a, T> OptionalChain for &'a &Option<T> {
+    type Value = &'a T;
+
+    #[inline]
+    fn into_option(self) -> Option<&'a T> {
+        self.as_ref()
+    }
+}
+
+/// Wraps an expression written in an HTML template, so that values whose type implements
+/// [`HtmlSafe`] are not escaped.
+///
+/// The safe `markup()` method only exists if `T: HtmlSafe`. Otherwise method resolution
+/// dereferences to [`Unescaped`] and picks its `markup()` method instead.
+pub struct MaybeSafe<'a, T: ?Sized>(Unescaped<'a, T>);
+
+impl<'a, T: ?Sized> MaybeSafe<'a, T> {
+    #[inline]
+    pub fn new(value: &'a T) -> Self {
+        MaybeSafe(Unescaped(value))
+    }
+}
+
+impl<'a, T: HtmlSafe + ?Sized> MaybeSafe<'a, T> {
+    #[inline]
+    pub fn markup<E: Escaper>(&self, escaper: E) -> MarkupDisplay<E, &'a T> {
+        MarkupDisplay::new_safe(self.0 .0, escaper)
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MaybeSafe<'a, T> {
+    type Target = Unescaped<'a, T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Unescaped<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Display + ?Sized> Unescaped<'a, T> {
+    #[inline]
+    pub fn markup<E: Escaper>(&self, escaper: E) -> MarkupDisplay<E, &'a T> {
+        MarkupDisplay::new_unsafe(self.0, escaper)
+    }
+}
+
+/// The writer into which a template is rendered
+///
+/// If the escaper [finalizes](Escaper::finalize) the output, the output is collected first, and
+/// written to the actual writer by [`finish()`](OutputWriter::finish). Otherwise it is written
+/// directly to the actual writer.
+pub struct OutputWriter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    output: Option<String>,
+}
+
+impl<'a, W: Write + ?Sized> OutputWriter<'a, W> {
+    #[inline]
+    pub fn new<E: Escaper>(writer: &'a mut W, _escaper: &E, size_hint: usize) -> Self {
+        let output = E::FINALIZES.then(|| {
+            let mut output = String::new();
+            let _ = output.try_reserve(size_hint);
+            output
+        });
+        Self { writer, output }
+    }
+
+    #[inline]
+    pub fn finish<E: Escaper>(self, escaper: &E) -> fmt::Result {
+        match self.output {
+            Some(mut output) => {
+                escaper.finalize(&mut output);
+                self.writer.write_str(&output)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write + ?Sized> Write for OutputWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match &mut self.output {
+            Some(output) => {
+                output.push_str(s);
+                Ok(())
+            }
+            None => self.writer.write_str(s),
+        }
+    }
+}
+
+/// Writes the variables dumped by `{% debug %}` as an HTML comment
+///
+/// Consecutive dashes in the values are separated by a space, so they cannot close the comment.
+pub fn write_debug_scope(
+    dest: &mut (impl Write + ?Sized),
+    vars: &[(&str, &dyn fmt::Debug)],
+) -> fmt::Result {
+    dest.write_str("<!-- debug:")?;
+    if vars.is_empty() {
+        dest.write_str(" no variables in scope")?;
+    }
+    for (name, value) in vars {
+        write!(dest, "\n{name} = ")?;
+        let mut prev = None;
+        for c in format!("{value:?}").chars() {
+            if c == '-' && prev == Some('-') {
+                dest.write_char(' ')?;
+            }
+            dest.write_char(c)?;
+            prev = Some(c);
+        }
+    }
+    dest.write_str("\n-->")
+}
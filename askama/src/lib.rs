@@ -50,8 +50,8 @@
 //!   Cannot be used together with `path`.
 //! * `print` (as `print = "code"`): enable debugging by printing nothing
 //!   (`none`), the parsed syntax tree (`ast`), the generated code (`code`)
-//!   or `all` for both. The requested data will be printed to stdout at
-//!   compile time.
+//!   or `all` for both, or size statistics of the templates (`stats`). The
+//!   requested data will be printed to stderr at compile time.
 //! * `escape` (as `escape = "none"`): override the template's extension used for
 //!   the purpose of determining the escaper for this template. See the section
 //!   on configuring custom escapers for more information.
@@ -65,15 +65,17 @@
 mod error;
 pub mod filters;
 pub mod helpers;
+mod locale;
 
 use std::fmt;
 
 pub use askama_derive::Template;
-pub use askama_escape::{Html, MarkupDisplay, Text};
+pub use askama_escape::{Escaper, Html, MarkupDisplay, Text};
 
 #[doc(hidden)]
 pub use crate as shared;
-pub use crate::error::{Error, Result};
+pub use crate::error::{Error, LimitExceeded, RenderError, Result};
+pub use crate::locale::{DefaultFormatter, LocaleFormatter};
 
 /// Main `Template` trait; implementations are generally derived
 ///
@@ -90,6 +92,122 @@ pub trait Template: fmt::Display {
     /// Renders the template to the given `writer` fmt buffer
     fn render_into(&self, writer: &mut (impl std::fmt::Write + ?Sized)) -> Result<()>;
 
+    /// Helper method which allocates a new `String` and renders into it, escaping all
+    /// expressions with `escaper` instead of the escaper selected by the template's extension
+    ///
+    /// This allows rendering the same template e.g. as HTML or as plain text, depending on the
+    /// output channel. Values marked with the `safe` filter are still written as they are, and
+    /// `escape` filters naming an escaper, like `|escape("html")`, still use that escaper.
+    ///
+    /// ```
+    /// # use askama::Template;
+    /// #[derive(Template)]
+    /// #[template(source = "Hello, {{ name }}!", ext = "html")]
+    /// struct Hello<'a> {
+    ///     name: &'a str,
+    /// }
+    ///
+    /// let hello = Hello { name: "<world>" };
+    /// assert_eq!(hello.render().unwrap(), "Hello, &lt;world&gt;!");
+    /// assert_eq!(hello.render_with_escaper(askama::Text).unwrap(), "Hello, <world>!");
+    /// ```
+    fn render_with_escaper(&self, escaper: impl Escaper) -> Result<String> {
+        let mut buf = String::new();
+        let _ = buf.try_reserve(Self::SIZE_HINT);
+        self.render_into_with_escaper(&mut buf, escaper)?;
+        Ok(buf)
+    }
+
+    /// Renders the template to the given `writer` fmt buffer, escaping all expressions with
+    /// `escaper` instead of the escaper selected by the template's extension
+    ///
+    /// See [`render_with_escaper()`](Template::render_with_escaper) for details. The default
+    /// implementation ignores `escaper` and calls [`render_into()`](Template::render_into);
+    /// derived templates implement this method to use the `escaper`.
+    fn render_into_with_escaper(
+        &self,
+        writer: &mut (impl std::fmt::Write + ?Sized),
+        escaper: impl Escaper,
+    ) -> Result<()> {
+        let _ = escaper;
+        self.render_into(writer)
+    }
+
+    /// Renders the template to the given `writer` fmt buffer, writing at most `max_bytes` bytes
+    ///
+    /// This is a safeguard against runaway output, e.g. when rendering untrusted data. As soon
+    /// as a write would exceed the limit, rendering stops with an [`Error::Custom`] containing
+    /// [`LimitExceeded`]. The output is not truncated at the limit: the `writer` only contains
+    /// the output written before the write that failed.
+    fn render_into_limited(
+        &self,
+        writer: &mut (impl std::fmt::Write + ?Sized),
+        max_bytes: usize,
+    ) -> Result<()> {
+        let mut writer = LimitedWriter {
+            writer,
+            remaining: max_bytes,
+            exceeded: false,
+        };
+        match self.render_into(&mut writer) {
+            Err(_) if writer.exceeded => Err(Error::Custom(Box::new(LimitExceeded(max_bytes)))),
+            result => result,
+        }
+    }
+
+    /// Renders the template to the given `writer`, keeping the writer's own error type
+    ///
+    /// Rendering into a [`fmt::Write`] flattens the errors of the writer into [`fmt::Error`].
+    /// If rendering stops because the `writer` failed, this method returns its error as
+    /// [`RenderError::Write`] instead. All other errors are returned as [`RenderError::Render`].
+    ///
+    /// ```
+    /// # use askama::{FallibleWrite, RenderError, Template};
+    /// struct Chunks {
+    ///     chunks: Vec<String>,
+    ///     capacity: usize,
+    /// }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Full;
+    ///
+    /// impl FallibleWrite for Chunks {
+    ///     type Error = Full;
+    ///
+    ///     fn write_str(&mut self, s: &str) -> Result<(), Full> {
+    ///         if self.chunks.len() == self.capacity {
+    ///             return Err(Full);
+    ///         }
+    ///         self.chunks.push(s.to_owned());
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// #[derive(Template)]
+    /// #[template(source = "Hello, {{ name }}!", ext = "txt")]
+    /// struct Hello<'a> {
+    ///     name: &'a str,
+    /// }
+    ///
+    /// let mut writer = Chunks { chunks: vec![], capacity: 1 };
+    /// let err = Hello { name: "world" }.render_into_fallible(&mut writer).unwrap_err();
+    /// assert!(matches!(err, RenderError::Write(Full)));
+    /// ```
+    fn render_into_fallible<W: FallibleWrite + ?Sized>(
+        &self,
+        writer: &mut W,
+    ) -> std::result::Result<(), RenderError<W::Error>> {
+        let mut writer = FallibleWriter {
+            writer,
+            error: None,
+        };
+        let result = self.render_into(&mut writer);
+        match writer.error {
+            Some(err) => Err(RenderError::Write(err)),
+            None => result.map_err(RenderError::Render),
+        }
+    }
+
     /// Renders the template to the given `writer` io buffer
     #[inline]
     fn write_into(&self, writer: &mut (impl std::io::Write + ?Sized)) -> std::io::Result<()> {
@@ -112,6 +230,27 @@ pub trait Template: fmt::Display {
 
     /// The MIME type (Content-Type) of the data that gets rendered by this Template
     const MIME_TYPE: &'static str;
+
+    /// The names of the blocks defined by this template and the templates it extends, in
+    /// alphabetical order
+    const BLOCKS: &'static [&'static str] = &[];
+
+    /// The template's `path` as given in the `template()` attribute, or `None` if the template
+    /// was defined with `source`
+    ///
+    /// Together with [`SIZE_HINT`](Template::SIZE_HINT), this lets generic code instrument the
+    /// rendering of any template:
+    ///
+    /// ```
+    /// # use askama::Template;
+    /// fn render_logged<T: Template>(tmpl: &T) -> askama::Result<String> {
+    ///     let mut buf = String::with_capacity(T::SIZE_HINT);
+    ///     tmpl.render_into(&mut buf)?;
+    ///     eprintln!("rendered {}", T::TEMPLATE_PATH.unwrap_or("<inline>"));
+    ///     Ok(buf)
+    /// }
+    /// ```
+    const TEMPLATE_PATH: Option<&'static str> = None;
 }
 
 impl<T: Template + ?Sized> Template for &T {
@@ -125,6 +264,20 @@ impl<T: Template + ?Sized> Template for &T {
         T::render(self)
     }
 
+    #[inline]
+    fn render_with_escaper(&self, escaper: impl Escaper) -> Result<String> {
+        T::render_with_escaper(self, escaper)
+    }
+
+    #[inline]
+    fn render_into_with_escaper(
+        &self,
+        writer: &mut (impl std::fmt::Write + ?Sized),
+        escaper: impl Escaper,
+    ) -> Result<()> {
+        T::render_into_with_escaper(self, writer, escaper)
+    }
+
     #[inline]
     fn write_into(&self, writer: &mut (impl std::io::Write + ?Sized)) -> std::io::Result<()> {
         T::write_into(self, writer)
@@ -135,8 +288,151 @@ impl<T: Template + ?Sized> Template for &T {
     const SIZE_HINT: usize = T::SIZE_HINT;
 
     const MIME_TYPE: &'static str = T::MIME_TYPE;
+
+    const BLOCKS: &'static [&'static str] = T::BLOCKS;
+
+    const TEMPLATE_PATH: Option<&'static str> = T::TEMPLATE_PATH;
 }
 
+/// Writer adapter used by [`Template::render_into_limited()`]
+struct LimitedWriter<'a, W: ?Sized> {
+    writer: &'a mut W,
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for LimitedWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.remaining.checked_sub(s.len()) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                self.writer.write_str(s)
+            }
+            None => {
+                self.exceeded = true;
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+/// A writer with its own error type, used by [`Template::render_into_fallible()`]
+pub trait FallibleWrite {
+    /// The error returned when writing fails
+    type Error;
+
+    /// Writes a string slice into this writer
+    fn write_str(&mut self, s: &str) -> std::result::Result<(), Self::Error>;
+}
+
+impl<W: FallibleWrite + ?Sized> FallibleWrite for &mut W {
+    type Error = W::Error;
+
+    #[inline]
+    fn write_str(&mut self, s: &str) -> std::result::Result<(), Self::Error> {
+        W::write_str(self, s)
+    }
+}
+
+/// Writer adapter used by [`Template::render_into_fallible()`]
+struct FallibleWriter<'a, W: FallibleWrite + ?Sized> {
+    writer: &'a mut W,
+    error: Option<W::Error>,
+}
+
+impl<W: FallibleWrite + ?Sized> fmt::Write for FallibleWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_str(s).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// Writer that forwards everything written to it to two writers
+///
+/// This renders a template into two sinks in a single pass, e.g. to send a response while
+/// storing it in a cache:
+///
+/// ```
+/// # use askama::{Template, TeeWriter};
+/// #[derive(Template)]
+/// #[template(source = "Hello, {{ name }}!", ext = "txt")]
+/// struct Hello<'a> {
+///     name: &'a str,
+/// }
+///
+/// let (mut response, mut cache) = (String::new(), String::new());
+/// let mut writer = TeeWriter::new(&mut response, &mut cache);
+/// Hello { name: "world" }.render_into(&mut writer).unwrap();
+/// assert_eq!(response, "Hello, world!");
+/// assert_eq!(cache, "Hello, world!");
+/// ```
+///
+/// Each write goes to the first writer, then to the second one. If either of them fails, the
+/// write fails immediately, so rendering stops: the second writer does not receive the write
+/// that failed in the first one, and both writers only contain the output written before it.
+#[derive(Debug, Clone, Default)]
+pub struct TeeWriter<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: fmt::Write, B: fmt::Write> TeeWriter<A, B> {
+    /// Creates a writer that forwards to `first` and `second`
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+
+    /// Returns the two writers
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: fmt::Write, B: fmt::Write> fmt::Write for TeeWriter<A, B> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.first.write_str(s)?;
+        self.second.write_str(s)
+    }
+}
+
+/// Marker trait for types whose [`Display`](fmt::Display) output is already valid, safe HTML
+///
+/// Values of a type implementing `HtmlSafe` are not escaped when they are written in a template
+/// using the HTML escaper, as if the `safe` filter was applied to them. This is useful for
+/// newtypes around sanitized HTML, e.g. the output of a Markdown renderer.
+///
+/// ```
+/// # use askama::{HtmlSafe, Template};
+/// struct SafeHtml(String);
+///
+/// impl std::fmt::Display for SafeHtml {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         f.write_str(&self.0)
+///     }
+/// }
+///
+/// impl HtmlSafe for SafeHtml {}
+///
+/// #[derive(Template)]
+/// #[template(source = "<div>{{ body }}</div>", ext = "html")]
+/// struct Page {
+///     body: SafeHtml,
+/// }
+///
+/// let page = Page { body: SafeHtml("<p>Hello</p>".into()) };
+/// assert_eq!(page.render().unwrap(), "<div><p>Hello</p></div>");
+/// ```
+///
+/// The marker only applies to the type itself: a generic value is escaped unless its
+/// type is known to implement `HtmlSafe` where the template is defined.
+pub trait HtmlSafe: fmt::Display {}
+
+impl<T: HtmlSafe + ?Sized> HtmlSafe for &T {}
+
+impl<T: HtmlSafe + ?Sized> HtmlSafe for Box<T> {}
+
 /// Object-safe wrapper trait around [`Template`] implementers
 ///
 /// This trades reduced performance (mostly due to writing into `dyn Write`) for object safety.
@@ -158,6 +454,12 @@ pub trait DynTemplate {
 
     /// The MIME type (Content-Type) of the data that gets rendered by this Template
     fn mime_type(&self) -> &'static str;
+
+    /// The names of the blocks defined by this template and the templates it extends
+    fn blocks(&self) -> &'static [&'static str];
+
+    /// The template's `path`, or `None` if it was defined with `source`
+    fn template_path(&self) -> Option<&'static str>;
 }
 
 impl<T: Template> DynTemplate for T {
@@ -185,6 +487,14 @@ impl<T: Template> DynTemplate for T {
     fn mime_type(&self) -> &'static str {
         Self::MIME_TYPE
     }
+
+    fn blocks(&self) -> &'static [&'static str] {
+        Self::BLOCKS
+    }
+
+    fn template_path(&self) -> Option<&'static str> {
+        Self::TEMPLATE_PATH
+    }
 }
 
 impl fmt::Display for dyn DynTemplate {
@@ -238,4 +548,30 @@ mod tests {
         test.dyn_write_into(&mut vec).unwrap();
         assert_eq!(vec, vec![b't', b'e', b's', b't']);
     }
+
+    #[test]
+    fn tee_writer() {
+        use std::fmt::Write;
+
+        struct Failing;
+        impl Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let mut tee = TeeWriter::new(String::new(), String::new());
+        write!(tee, "{}-{}", 1, 2).unwrap();
+        assert_eq!(tee.into_inner(), ("1-2".to_owned(), "1-2".to_owned()));
+
+        let mut second = String::new();
+        let mut tee = TeeWriter::new(Failing, &mut second);
+        assert!(tee.write_str("test").is_err());
+        assert_eq!(second, "");
+
+        let mut first = String::new();
+        let mut tee = TeeWriter::new(&mut first, Failing);
+        assert!(tee.write_str("test").is_err());
+        assert_eq!(first, "test");
+    }
 }
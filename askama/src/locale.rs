@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::Result;
+
+/// Locale-dependent formatting used by the `thousands` and `currency` filters
+///
+/// All methods have a default implementation using English conventions, which is what
+/// [`DefaultFormatter`] uses. To format numbers for another locale (or to use a library like
+/// ICU), implement this trait on your own type and set its path as `formatter` in the
+/// `[general]` section of `askama.toml`. The generated code then calls your implementation
+/// for these filters in all templates of the crate:
+///
+/// ```
+/// # use std::fmt::Display;
+/// struct German;
+///
+/// impl askama::LocaleFormatter for German {
+///     fn thousands(number: impl Display) -> askama::Result<String> {
+///         let grouped = askama::DefaultFormatter::thousands(number)?;
+///         Ok(grouped
+///             .chars()
+///             .map(|c| match c {
+///                 ',' => '.',
+///                 '.' => ',',
+///                 c => c,
+///             })
+///             .collect())
+///     }
+/// }
+/// ```
+///
+/// Numbers are passed as the value to format, and are usually formatted using their
+/// [`Display`](fmt::Display) implementation, which never loses precision.
+pub trait LocaleFormatter {
+    /// Groups the digits of the integer part of `number` in thousands
+    ///
+    /// The default implementation separates the groups with `,` and keeps the fractional
+    /// part (after a `.`) as it is. A sign is kept outside of the groups. If the
+    /// displayed value is not a number, e.g. `NaN`, it is returned unchanged.
+    fn thousands(number: impl fmt::Display) -> Result<String> {
+        Ok(group_digits(&number.to_string(), ",", "."))
+    }
+
+    /// Formats an `amount` of money in the given `currency`
+    ///
+    /// The default implementation writes the amount grouped in thousands followed by the
+    /// currency, e.g. `1,234.50 EUR`. Use the `format_float` filter first to set the number
+    /// of decimals.
+    fn currency(amount: impl fmt::Display, currency: impl fmt::Display) -> Result<String> {
+        Ok(format!("{} {currency}", Self::thousands(amount)?))
+    }
+}
+
+/// Groups the digits of the integer part of `number` with `thousands_sep`, and replaces the `.`
+/// before the fractional part with `decimal_sep`
+///
+/// A sign is kept outside of the groups. If `number` is not a number, e.g. `NaN`, it is
+/// returned unchanged.
+pub(crate) fn group_digits(number: &str, thousands_sep: &str, decimal_sep: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix(['-', '+']) {
+        Some(unsigned) => number.split_at(number.len() - unsigned.len()),
+        None => ("", number),
+    };
+    let (int, frac) = unsigned.split_at(unsigned.find('.').unwrap_or(unsigned.len()));
+    if int.is_empty() || !int.bytes().all(|b| b.is_ascii_digit()) {
+        return number.to_owned();
+    }
+
+    let mut s = String::with_capacity(number.len() + int.len() / 3 * thousands_sep.len());
+    s.push_str(sign);
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            s.push_str(thousands_sep);
+        }
+        s.push(digit);
+    }
+    if let Some(frac) = frac.strip_prefix('.') {
+        s.push_str(decimal_sep);
+        s.push_str(frac);
+    }
+    s
+}
+
+/// The [`LocaleFormatter`] used when none is configured
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFormatter;
+
+impl LocaleFormatter for DefaultFormatter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thousands() {
+        let thousands = |n: &dyn fmt::Display| DefaultFormatter::thousands(n).unwrap();
+        assert_eq!(thousands(&0), "0");
+        assert_eq!(thousands(&999), "999");
+        assert_eq!(thousands(&1000), "1,000");
+        assert_eq!(thousands(&1234567), "1,234,567");
+        assert_eq!(thousands(&-1234567), "-1,234,567");
+        assert_eq!(thousands(&-123), "-123");
+        assert_eq!(thousands(&1234.5678), "1,234.5678");
+        assert_eq!(thousands(&-0.5), "-0.5");
+        assert_eq!(thousands(&"+12345"), "+12,345");
+        assert_eq!(thousands(&u64::MAX), "18,446,744,073,709,551,615");
+        assert_eq!(
+            thousands(&i128::MIN),
+            "-170,141,183,460,469,231,731,687,303,715,884,105,728"
+        );
+        assert_eq!(thousands(&f64::NAN), "NaN");
+        assert_eq!(thousands(&f64::NEG_INFINITY), "-inf");
+        assert_eq!(thousands(&"abc"), "abc");
+        assert_eq!(thousands(&""), "");
+    }
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(
+            DefaultFormatter::currency("1234.50", "EUR").unwrap(),
+            "1,234.50 EUR"
+        );
+        assert_eq!(DefaultFormatter::currency(-5, "$").unwrap(), "-5 $");
+    }
+}
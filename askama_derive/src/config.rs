@@ -1,5 +1,7 @@
-use std::collections::{BTreeMap, HashSet};
-use std::path::{Path, PathBuf};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::{env, fs};
 
@@ -14,10 +16,25 @@ use parser::Syntax;
 #[derive(Debug)]
 pub(crate) struct Config<'a> {
     pub(crate) dirs: Vec<PathBuf>,
+    /// The environment variables used in `dirs`, so the generated code can depend on them.
+    pub(crate) env_vars: BTreeSet<String>,
     pub(crate) syntaxes: BTreeMap<String, Syntax<'a>>,
     pub(crate) default_syntax: &'a str,
     pub(crate) escapers: Vec<(HashSet<String>, String)>,
     pub(crate) whitespace: WhitespaceHandling,
+    pub(crate) strict: bool,
+    /// The filters of the local `filters` module, which are the only unknown filters allowed in
+    /// strict mode.
+    pub(crate) custom_filters: Vec<&'a str>,
+    pub(crate) case_insensitive_paths: bool,
+    pub(crate) unroll_loops_below: usize,
+    pub(crate) dedup_exprs: bool,
+    /// Path of the `LocaleFormatter` used by the `thousands` and `currency` filters.
+    pub(crate) formatter: String,
+    /// Warnings about template paths found by `find_template()`.
+    pub(crate) warnings: RefCell<Vec<String>>,
+    /// The files which got a warning, so the same file is only warned about once.
+    warned_paths: RefCell<HashSet<PathBuf>>,
 }
 
 impl<'a> Config<'a> {
@@ -37,24 +54,27 @@ impl<'a> Config<'a> {
             RawConfig::from_toml_str(s)?
         };
 
-        let (dirs, default_syntax, mut whitespace) = match raw.general {
-            Some(General {
-                dirs,
-                default_syntax,
-                whitespace,
-            }) => (
-                dirs.map_or(default_dirs, |v| {
-                    v.into_iter().map(|dir| root.join(dir)).collect()
-                }),
-                default_syntax.unwrap_or(DEFAULT_SYNTAX_NAME),
-                whitespace,
-            ),
-            None => (
-                default_dirs,
-                DEFAULT_SYNTAX_NAME,
-                WhitespaceHandling::default(),
-            ),
+        let General {
+            dirs,
+            default_syntax,
+            mut whitespace,
+            strict,
+            custom_filters,
+            case_insensitive_paths,
+            unroll_loops_below,
+            dedup_exprs,
+            formatter,
+        } = raw.general.unwrap_or_default();
+        // Environment variables are expanded first, so a variable can hold an absolute path.
+        let mut env_vars = BTreeSet::new();
+        let dirs = match dirs {
+            Some(dirs) => dirs
+                .into_iter()
+                .map(|dir| Ok(root.join(expand_env_vars(dir, &mut env_vars)?)))
+                .collect::<Result<_, CompileError>>()?,
+            None => default_dirs,
         };
+        let default_syntax = default_syntax.unwrap_or(DEFAULT_SYNTAX_NAME);
         if let Some(template_whitespace) = template_whitespace {
             whitespace = match template_whitespace {
                 "suppress" => WhitespaceHandling::Suppress,
@@ -100,10 +120,20 @@ impl<'a> Config<'a> {
 
         Ok(Config {
             dirs,
+            env_vars,
             syntaxes,
             default_syntax,
             escapers,
             whitespace,
+            strict,
+            custom_filters,
+            case_insensitive_paths,
+            unroll_loops_below,
+            dedup_exprs,
+            formatter: formatter
+                .map_or_else(|| format!("{CRATE}::DefaultFormatter"), ToString::to_string),
+            warnings: RefCell::default(),
+            warned_paths: RefCell::default(),
         })
     }
 
@@ -112,17 +142,38 @@ impl<'a> Config<'a> {
         path: &str,
         start_at: Option<&Path>,
     ) -> std::result::Result<Rc<Path>, CompileError> {
-        if let Some(root) = start_at {
-            let relative = root.with_file_name(path);
-            if relative.exists() {
-                return Ok(relative.into());
+        if self.case_insensitive_paths {
+            let relative_dir = start_at.and_then(Path::parent);
+            for dir in relative_dir
+                .into_iter()
+                .chain(self.dirs.iter().map(PathBuf::as_path))
+            {
+                if let Some(found) = find_ignoring_case(dir, path) {
+                    let actual = found.strip_prefix(dir).unwrap_or(&found);
+                    if actual != Path::new(path)
+                        && self.warned_paths.borrow_mut().insert(found.clone())
+                    {
+                        self.warnings.borrow_mut().push(format!(
+                            "template path {path:?} does not match the case of the file {actual:?}, \
+                            so it will not be found on case-sensitive file systems",
+                        ));
+                    }
+                    return Ok(found.into());
+                }
+            }
+        } else {
+            if let Some(root) = start_at {
+                let relative = root.with_file_name(path);
+                if relative.exists() {
+                    return Ok(relative.into());
+                }
             }
-        }
 
-        for dir in &self.dirs {
-            let rooted = dir.join(path);
-            if rooted.exists() {
-                return Ok(rooted.into());
+            for dir in &self.dirs {
+                let rooted = dir.join(path);
+                if rooted.exists() {
+                    return Ok(rooted.into());
+                }
             }
         }
 
@@ -134,6 +185,33 @@ impl<'a> Config<'a> {
     }
 }
 
+/// Looks up `path` in `dir`, comparing each path component with the actual file names while
+/// ignoring their case. A file name that matches exactly is preferred.
+fn find_ignoring_case(dir: &Path, path: &str) -> Option<PathBuf> {
+    let mut found = dir.to_path_buf();
+    for component in Path::new(path).components() {
+        let Component::Normal(name) = component else {
+            found.push(component);
+            continue;
+        };
+
+        let lowercase = name.to_string_lossy().to_lowercase();
+        let mut matched = None;
+        for entry in fs::read_dir(&found).ok()?.flatten() {
+            let entry_name = entry.file_name();
+            if entry_name == name {
+                matched = Some(entry_name);
+                break;
+            } else if matched.is_none() && entry_name.to_string_lossy().to_lowercase() == lowercase
+            {
+                matched = Some(entry_name);
+            }
+        }
+        found.push(matched?);
+    }
+    found.exists().then_some(found)
+}
+
 impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
     type Error = CompileError;
 
@@ -146,6 +224,18 @@ impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
             expr_end: self.expr_end.unwrap_or(default.expr_end),
             comment_start: self.comment_start.unwrap_or(default.comment_start),
             comment_end: self.comment_end.unwrap_or(default.comment_end),
+            raw_expr_start: self.raw_expr_start,
+            raw_expr_end: self.raw_expr_end,
+        };
+
+        let raw_expr = match (syntax.raw_expr_start, syntax.raw_expr_end) {
+            (Some(start), Some(end)) => Some((start, end)),
+            (None, None) => None,
+            _ => {
+                return Err(
+                    "raw_expr_start and raw_expr_end delimiters must be set together".into(),
+                )
+            }
         };
 
         for (s, kind) in [
@@ -155,7 +245,11 @@ impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
             (syntax.expr_end, "expr_end"),
             (syntax.comment_start, "comment_start"),
             (syntax.comment_end, "comment_end"),
-        ] {
+        ]
+        .into_iter()
+        .chain(raw_expr.map(|(start, _)| (start, "raw_expr_start")))
+        .chain(raw_expr.map(|(_, end)| (end, "raw_expr_end")))
+        {
             if s.len() < 2 {
                 return Err(format!(
                     "{kind} delimiter must be at least two characters long: {s:?}"
@@ -181,6 +275,26 @@ impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
             }
         }
 
+        if let Some((start, _)) = raw_expr {
+            // The raw expression delimiter is tried first, so it may extend `expr_start`
+            // (like `{{{` extends `{{`), but not the other way around.
+            for s in [syntax.block_start, syntax.comment_start] {
+                if start.starts_with(s) || s.starts_with(start) {
+                    return Err(format!(
+                        "a delimiter may not be the prefix of another delimiter: {start:?} vs {s:?}",
+                    )
+                    .into());
+                }
+            }
+            if syntax.expr_start.starts_with(start) {
+                return Err(format!(
+                    "raw_expr_start delimiter may not be a prefix of expr_start: {start:?} vs {:?}",
+                    syntax.expr_start,
+                )
+                .into());
+            }
+        }
+
         Ok(syntax)
     }
 }
@@ -233,12 +347,24 @@ impl From<WhitespaceHandling> for Whitespace {
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize))]
+#[derive(Default)]
 struct General<'a> {
     #[cfg_attr(feature = "serde", serde(borrow))]
     dirs: Option<Vec<&'a str>>,
     default_syntax: Option<&'a str>,
     #[cfg_attr(feature = "serde", serde(default))]
     whitespace: WhitespaceHandling,
+    #[cfg_attr(feature = "serde", serde(default))]
+    strict: bool,
+    #[cfg_attr(feature = "serde", serde(default, borrow))]
+    custom_filters: Vec<&'a str>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    case_insensitive_paths: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    unroll_loops_below: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    dedup_exprs: bool,
+    formatter: Option<&'a str>,
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize))]
@@ -250,6 +376,8 @@ struct RawSyntax<'a> {
     expr_end: Option<&'a str>,
     comment_start: Option<&'a str>,
     comment_end: Option<&'a str>,
+    raw_expr_start: Option<&'a str>,
+    raw_expr_end: Option<&'a str>,
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize))]
@@ -277,6 +405,57 @@ pub(crate) fn read_config_file(
     }
 }
 
+/// Replaces `$VAR` and `${VAR}` in `dir` with the value of the environment variable `VAR`,
+/// and `$$` with `$`. The names of the variables are added to `used`.
+fn expand_env_vars(
+    dir: &str,
+    used: &mut BTreeSet<String>,
+) -> std::result::Result<PathBuf, CompileError> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut expanded = OsString::new();
+    let mut rest = dir;
+    while let Some(start) = rest.find('$') {
+        expanded.push(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(tail) = after.strip_prefix('$') {
+            expanded.push("$");
+            rest = tail;
+            continue;
+        }
+
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').ok_or_else(|| {
+                    CompileError::from(format!("missing `}}` after `${{` in dir {dir:?}"))
+                })?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => after.split_at(after.find(|c| !is_name_char(c)).unwrap_or(after.len())),
+        };
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(format!(
+                "`$` in dir {dir:?} must be followed by the name of an environment variable, \
+                 or be escaped as `$$`"
+            )
+            .into());
+        }
+        used.insert(name.to_owned());
+        match env::var_os(name) {
+            Some(value) => expanded.push(value),
+            None => {
+                return Err(format!(
+                    "environment variable `{name}` used in dir {dir:?} is not set"
+                )
+                .into());
+            }
+        }
+        rest = tail;
+    }
+    expanded.push(rest);
+    Ok(expanded.into())
+}
+
 fn str_set<T>(vals: &[T]) -> HashSet<String>
 where
     T: ToString,
@@ -341,6 +520,57 @@ mod tests {
         assert_eq!(config.dirs, vec![root]);
     }
 
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_dirs_env_vars() {
+        env::set_var("ASKAMA_TEST_TEMPLATES", "tpl");
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        let config = Config::new(
+            r#"[general]
+            dirs = ["$ASKAMA_TEST_TEMPLATES/a", "${ASKAMA_TEST_TEMPLATES}_b", "$$c"]"#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.dirs,
+            [root.join("tpl/a"), root.join("tpl_b"), root.join("$c")]
+        );
+        assert_eq!(
+            config.env_vars.iter().collect::<Vec<_>>(),
+            ["ASKAMA_TEST_TEMPLATES"]
+        );
+
+        // An absolute path replaces the crate root.
+        let absolute = env::temp_dir().join("askama-templates");
+        env::set_var("ASKAMA_TEST_ABSOLUTE_TEMPLATES", &absolute);
+        let config = Config::new(
+            "[general]\ndirs = [\"${ASKAMA_TEST_ABSOLUTE_TEMPLATES}\"]",
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.dirs, [absolute]);
+    }
+
+    #[test]
+    fn test_expand_env_vars_errors() {
+        env::remove_var("ASKAMA_TEST_UNSET");
+        assert_eq!(
+            expand_env_vars("$ASKAMA_TEST_UNSET/tpl", &mut BTreeSet::new())
+                .unwrap_err()
+                .msg,
+            "environment variable `ASKAMA_TEST_UNSET` used in dir \"$ASKAMA_TEST_UNSET/tpl\" is \
+             not set"
+        );
+        assert!(expand_env_vars("${ASKAMA_TEST_UNSET", &mut BTreeSet::new()).is_err());
+        assert!(expand_env_vars("${}", &mut BTreeSet::new()).is_err());
+        assert!(expand_env_vars("tpl/$", &mut BTreeSet::new()).is_err());
+        assert!(expand_env_vars("$-tpl", &mut BTreeSet::new()).is_err());
+        assert_eq!(
+            expand_env_vars("a$$b", &mut BTreeSet::new()).unwrap(),
+            Path::new("a$b")
+        );
+    }
+
     fn assert_eq_rooted(actual: &Path, expected: &str) {
         let mut root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
         root.push("templates");
@@ -381,6 +611,39 @@ mod tests {
         assert_eq_rooted(&path, "sub/sub1/d.html");
     }
 
+    #[cfg(feature = "config")]
+    #[test]
+    fn find_ignoring_case() {
+        let config = Config::new("", None).unwrap();
+        assert!(config.find_template("SUB/B.html", None).is_err());
+
+        let raw_config = r#"
+        [general]
+        case_insensitive_paths = true
+        "#;
+        let config = Config::new(raw_config, None).unwrap();
+        let root = config.find_template("sub/b.html", None).unwrap();
+        assert_eq_rooted(&root, "sub/b.html");
+        assert!(config.warnings.borrow().is_empty());
+
+        let path = config.find_template("SUB/B.html", None).unwrap();
+        assert_eq_rooted(&path, "sub/b.html");
+        let path = config.find_template("Sub1/D.HTML", Some(&root)).unwrap();
+        assert_eq_rooted(&path, "sub/sub1/d.html");
+        // The same file is resolved again, e.g. when building the heritage of a template.
+        let path = config.find_template("SUB/B.html", None).unwrap();
+        assert_eq_rooted(&path, "sub/b.html");
+        assert_eq!(
+            *config.warnings.borrow(),
+            [
+                "template path \"SUB/B.html\" does not match the case of the file \"sub/b.html\", \
+                so it will not be found on case-sensitive file systems",
+                "template path \"Sub1/D.HTML\" does not match the case of the file \
+                \"sub1/d.html\", so it will not be found on case-sensitive file systems",
+            ],
+        );
+    }
+
     #[cfg(feature = "config")]
     #[test]
     fn add_syntax() {
@@ -516,6 +779,58 @@ mod tests {
             config.unwrap_err().msg,
             r#"a delimiter may not be the prefix of another delimiter: "{{" vs "{{$""#,
         );
+
+        let raw_config = r#"
+        [[syntax]]
+        name = "raw_start_only"
+        raw_expr_start = "{{{"
+        "#;
+        let config = Config::new(raw_config, None);
+        assert_eq!(
+            config.unwrap_err().msg,
+            "raw_expr_start and raw_expr_end delimiters must be set together",
+        );
+
+        let raw_config = r#"
+        [[syntax]]
+        name = "raw_is_prefix"
+        expr_start = "{{{"
+        raw_expr_start = "{{"
+        raw_expr_end = "}}"
+        "#;
+        let config = Config::new(raw_config, None);
+        assert_eq!(
+            config.unwrap_err().msg,
+            r#"raw_expr_start delimiter may not be a prefix of expr_start: "{{" vs "{{{""#,
+        );
+
+        let raw_config = r#"
+        [[syntax]]
+        name = "raw_is_block"
+        raw_expr_start = "{%"
+        raw_expr_end = "%}"
+        "#;
+        let config = Config::new(raw_config, None);
+        assert_eq!(
+            config.unwrap_err().msg,
+            r#"a delimiter may not be the prefix of another delimiter: "{%" vs "{%""#,
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_raw_expr_delimiters() {
+        let raw_config = r#"
+        [[syntax]]
+        name = "mustache"
+        raw_expr_start = "{{{"
+        raw_expr_end = "}}}"
+        "#;
+        let config = Config::new(raw_config, None).unwrap();
+        let mustache = config.syntaxes.get("mustache").unwrap();
+        assert_eq!(mustache.raw_expr_start, Some("{{{"));
+        assert_eq!(mustache.raw_expr_end, Some("}}}"));
+        assert_eq!(config.syntaxes.get("default").unwrap().raw_expr_start, None);
     }
 
     #[cfg(feature = "config")]
@@ -639,6 +954,77 @@ mod tests {
         assert_eq!(config.whitespace, WhitespaceHandling::Minimize);
     }
 
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_strict_parsing() {
+        let config = Config::new("", None).unwrap();
+        assert!(!config.strict);
+        assert!(config.custom_filters.is_empty());
+
+        let config = Config::new(
+            r#"
+            [general]
+            strict = true
+            custom_filters = ["myfilter", "other"]
+            "#,
+            None,
+        )
+        .unwrap();
+        assert!(config.strict);
+        assert_eq!(config.custom_filters, ["myfilter", "other"]);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_unroll_loops_parsing() {
+        let config = Config::new("", None).unwrap();
+        assert_eq!(config.unroll_loops_below, 0);
+
+        let config = Config::new(
+            r#"
+            [general]
+            unroll_loops_below = 4
+            "#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.unroll_loops_below, 4);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_dedup_exprs_parsing() {
+        let config = Config::new("", None).unwrap();
+        assert!(!config.dedup_exprs);
+
+        let config = Config::new(
+            r#"
+            [general]
+            dedup_exprs = true
+            "#,
+            None,
+        )
+        .unwrap();
+        assert!(config.dedup_exprs);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_formatter_parsing() {
+        let config = Config::new("", None).unwrap();
+        assert_eq!(config.formatter, "::askama::DefaultFormatter");
+
+        let config = Config::new(
+            r#"
+            [general]
+            formatter = "crate::locale::German"
+            "#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.formatter, "crate::locale::German");
+    }
+
     #[test]
     fn test_config_whitespace_error() {
         let config = Config::new(r#""#, Some("trim"));
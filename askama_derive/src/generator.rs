@@ -1,18 +1,19 @@
 use std::borrow::Cow;
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::BTreeMap;
 use std::ops::Deref;
 use std::path::Path;
 use std::rc::Rc;
-use std::{cmp, hash, mem, str};
+use std::{cmp, fs, hash, mem, ptr, str};
 
 use crate::config::WhitespaceHandling;
-use crate::heritage::{Context, Heritage};
+use crate::heritage::{error_at, Context, DynamicExtends, Heritage};
 use crate::input::{Source, TemplateInput};
 use crate::{CompileError, CRATE};
 
 use parser::node::{
-    Call, Comment, CondTest, FilterBlock, If, Include, Let, Lit, Loop, Match, Target, Whitespace,
-    Ws,
+    Call, Comment, CondTest, Embed, FilterBlock, If, Include, Let, LetBlock, Lit, Loop, Macro,
+    Match, Target, Whitespace, Ws,
 };
 use parser::{Expr, Filter, Node};
 use quote::quote;
@@ -35,6 +36,25 @@ pub(crate) struct Generator<'a> {
     skip_ws: WhitespaceHandling,
     // If currently in a block, this will contain the name of a potential parent block
     super_block: Option<(&'a str, usize)>,
+    // Whether the current block was declared `scoped`, so `super()` calls share its variables
+    scoped_block: bool,
+    // Whether expressions are escaped, `false` within `{% autoescape false %}`
+    autoescape: bool,
+    // Whether the body of the current loop uses `loop.length`
+    uses_loop_length: bool,
+    // The macros being expanded, to detect a macro which calls itself
+    called_macros: Vec<&'a Macro<'a>>,
+    // The blocks with parameters being expanded, to detect a block which calls itself
+    called_blocks: Vec<&'a str>,
+    // Whether the template was included with `with`, so it can only use the variables passed
+    // to it and not the fields of the template struct
+    isolated: bool,
+    // The variables of the scopes calling a macro with a `uses` clause, which its body can
+    // only read if they are declared, with the name of that macro
+    hidden_locals: HashMap<String, &'a str>,
+    // With `dedup_exprs`, the calls evaluated into a local at the start of an enclosing scope,
+    // with the name and the display wrap of that local
+    hoisted_calls: Vec<(&'a Expr<'a>, String, DisplayWrap)>,
     // Buffer for writable
     buf_writable: WritableBuffer<'a>,
     // Counter for write! hash named arguments
@@ -56,6 +76,14 @@ impl<'a> Generator<'a> {
             next_ws: None,
             skip_ws: WhitespaceHandling::Preserve,
             super_block: None,
+            scoped_block: false,
+            autoescape: true,
+            uses_loop_length: false,
+            called_macros: Vec::new(),
+            called_blocks: Vec::new(),
+            isolated: false,
+            hidden_locals: HashMap::new(),
+            hoisted_calls: Vec::new(),
             buf_writable: WritableBuffer {
                 discard: input.block.is_some(),
                 ..Default::default()
@@ -89,6 +117,20 @@ impl<'a> Generator<'a> {
         buf.write("fn render_into(&self, writer: &mut (impl ::std::fmt::Write + ?Sized)) -> ");
         buf.write(CRATE);
         buf.writeln("::Result<()> {")?;
+        buf.writeln(&format!(
+            "{CRATE}::Template::render_into_with_escaper(self, writer, {})",
+            self.input.escaper
+        ))?;
+        buf.writeln("}")?;
+
+        // The escaper is a parameter, so it can be chosen at runtime with `render_with_escaper()`.
+        buf.writeln("fn render_into_with_escaper(")?;
+        buf.writeln("&self,")?;
+        buf.writeln("writer: &mut (impl ::std::fmt::Write + ?Sized),")?;
+        buf.writeln(&format!("_escaper: impl {CRATE}::Escaper,"))?;
+        buf.write(") -> ");
+        buf.write(CRATE);
+        buf.writeln("::Result<()> {")?;
 
         // Make sure the compiler understands that the generated code depends on the template files.
         for path in self.contexts.keys() {
@@ -109,13 +151,36 @@ impl<'a> Generator<'a> {
             }
         }
 
-        let size_hint = if let Some(heritage) = self.heritage {
-            self.handle(heritage.root, heritage.root.nodes, buf, AstLevel::Top)
-        } else {
-            self.handle(ctx, ctx.nodes, buf, AstLevel::Top)
-        }?;
+        // Likewise, the template dirs may depend on environment variables.
+        for name in &self.input.config.env_vars {
+            buf.writeln(
+                &quote! {
+                    ::std::option_env!(#name);
+                }
+                .to_string(),
+            )?;
+        }
+
+        // The output goes through an `OutputWriter`, so the escaper can post-process it with
+        // `finalize()`, whichever method was used to render the template.
+        buf.writeln(&format!(
+            "let mut _output = {CRATE}::helpers::OutputWriter::new(\
+             writer, &_escaper, <Self as {CRATE}::Template>::SIZE_HINT);"
+        ))?;
+        buf.writeln("{")?;
+        buf.writeln("use ::std::fmt::Write as _;")?;
+        buf.writeln("let writer = &mut _output;")?;
+
+        // When rendering a single block, the code outside of it is not generated at all, so the
+        // fields it uses are not required.
+        buf.discard = self.input.block.is_some();
+        let size_hint = self.handle_template(ctx, buf)?;
+        buf.discard = false;
 
         self.flush_ws(Ws(None, None));
+        self.write_buf_writable(buf)?;
+        buf.writeln("}")?;
+        buf.writeln("_output.finish(&_escaper)?;")?;
         buf.write(CRATE);
         buf.writeln("::Result::Ok(())")?;
         buf.writeln("}")?;
@@ -132,10 +197,103 @@ impl<'a> Generator<'a> {
         buf.writeln(&format!("{:?}", &self.input.mime_type))?;
         buf.writeln(";")?;
 
+        let mut blocks: Vec<&str> = match &ctx.dynamic_extends {
+            Some(dynamic) => dynamic
+                .parents
+                .iter()
+                .flat_map(|(_, parent)| {
+                    Heritage::with_parent(ctx, Some(parent), self.contexts)
+                        .blocks
+                        .into_keys()
+                })
+                .collect(),
+            None => self
+                .heritage
+                .map(|heritage| heritage.blocks.keys().copied().collect())
+                .unwrap_or_default(),
+        };
+        if self.heritage.is_some() || ctx.dynamic_extends.is_some() {
+            blocks.sort();
+            blocks.dedup();
+            buf.writeln("const BLOCKS: &'static [&'static ::std::primitive::str] = ")?;
+            buf.writeln(&format!("&{blocks:?}"))?;
+            buf.writeln(";")?;
+        }
+
+        if let Source::Path(path) = self.input.source {
+            buf.writeln(
+                "const TEMPLATE_PATH: ::std::option::Option<&'static ::std::primitive::str> = ",
+            )?;
+            buf.writeln(&format!("::std::option::Option::Some({path:?})"))?;
+            buf.writeln(";")?;
+        }
+
         buf.writeln("}")?;
         Ok(())
     }
 
+    // Renders the template of `ctx` as a whole, i.e. starting from its root ancestor.
+    fn handle_template(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+    ) -> Result<usize, CompileError> {
+        if let Some(dynamic) = &ctx.dynamic_extends {
+            return self.write_dynamic_extends(ctx, dynamic, buf);
+        }
+        match self.heritage {
+            Some(heritage) => self.handle(heritage.root, heritage.root.nodes, buf, AstLevel::Top),
+            None => self.handle(ctx, ctx.nodes, buf, AstLevel::Top),
+        }
+    }
+
+    // With `{% extends name in [...] %}`, the template is generated once for each candidate
+    // parent, and the one to render is selected at render time.
+    fn write_dynamic_extends(
+        &mut self,
+        ctx: &Context<'a>,
+        dynamic: &DynamicExtends<'a>,
+        buf: &mut Buffer,
+    ) -> Result<usize, CompileError> {
+        let selector = self.visit_expr_root(dynamic.selector)?;
+        // The `match` is needed even if only a block is rendered, as it can differ between the
+        // candidates.
+        let discard = mem::replace(&mut buf.discard, false);
+        buf.writeln(&format!(
+            "match ::core::convert::AsRef::<::core::primitive::str>::as_ref(&({selector})) {{"
+        ))?;
+        let mut arm_sizes = Vec::new();
+        for (name, parent) in &dynamic.parents {
+            let heritage = Heritage::with_parent(ctx, Some(parent), self.contexts);
+            let locals = MapChain::with_parent(&self.locals);
+            let mut child = Self::new(self.input, self.contexts, Some(&heritage), locals);
+            child.buf_writable.discard = self.buf_writable.discard;
+            child.autoescape = self.autoescape;
+            child.isolated = self.isolated;
+
+            buf.writeln(&format!("{name:?} => {{"))?;
+            buf.discard = discard;
+            let size_hint = child.handle(heritage.root, heritage.root.nodes, buf, AstLevel::Top)?;
+            arm_sizes.push(size_hint + child.write_buf_writable(buf)?);
+            buf.discard = false;
+            buf.writeln("}")?;
+        }
+        let names = dynamic
+            .parents
+            .iter()
+            .map(|(name, _)| format!("{name:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        buf.writeln(&format!(
+            "_name => return ::core::result::Result::Err({CRATE}::shared::Error::Custom(\
+             ::core::convert::Into::into(::std::format!(\
+             \"{{:?}} is not one of the parent templates {{}}\", _name, {names:?})))),"
+        ))?;
+        buf.writeln("}")?;
+        buf.discard = discard;
+        Ok(median(&mut arm_sizes))
+    }
+
     // Implement `Display` for the given context struct.
     fn impl_display(&mut self, buf: &mut Buffer) -> Result<(), CompileError> {
         self.write_header(buf, "::std::fmt::Display", None)?;
@@ -253,6 +411,18 @@ impl<'a> Generator<'a> {
         level: AstLevel,
     ) -> Result<usize, CompileError> {
         let mut size_hint = 0;
+        let hoisted = self.hoisted_calls.len();
+        let calls = self.calls_to_hoist(nodes);
+        if !calls.is_empty() {
+            size_hint += self.write_buf_writable(buf)?;
+            for call in calls {
+                let mut expr_buf = Buffer::new(0);
+                let wrap = self.visit_expr(&mut expr_buf, call)?;
+                let name = format!("_call{}", self.hoisted_calls.len());
+                buf.writeln(&format!("let {name} = &({});", expr_buf.buf))?;
+                self.hoisted_calls.push((call, name, wrap));
+            }
+        }
         for n in nodes {
             match *n {
                 Node::Lit(ref lit) => {
@@ -261,11 +431,17 @@ impl<'a> Generator<'a> {
                 Node::Comment(ref comment) => {
                     self.write_comment(comment);
                 }
+                // `{{ super() }}` is the same as `{% call super() %}`, like in Jinja
+                Node::Expr(ws, Expr::Call(ref callee, ref args))
+                    if matches!(**callee, Expr::Var("super")) && args.is_empty() =>
+                {
+                    size_hint += self.write_block(ctx, buf, None, self.scoped_block, ws)?;
+                }
                 Node::Expr(ws, ref val) => {
                     self.write_expr(ws, val);
                 }
                 Node::Let(ref l) => {
-                    self.write_let(buf, l)?;
+                    self.write_let(ctx, buf, l)?;
                 }
                 Node::If(ref i) => {
                     size_hint += self.write_if(ctx, buf, i)?;
@@ -276,18 +452,34 @@ impl<'a> Generator<'a> {
                 Node::Loop(ref loop_block) => {
                     size_hint += self.write_loop(ctx, buf, loop_block)?;
                 }
+                // Like a macro, a block with parameters is only rendered where it is called.
+                Node::BlockDef(ref b) if !b.args.is_empty() => {
+                    self.flush_ws(b.ws1);
+                    self.prepare_ws(b.ws2);
+                }
                 Node::BlockDef(ref b) => {
-                    size_hint += self.write_block(ctx, buf, Some(b.name), Ws(b.ws1.0, b.ws2.1))?;
+                    size_hint +=
+                        self.write_block(ctx, buf, Some(b.name), b.scoped, Ws(b.ws1.0, b.ws2.1))?;
                 }
                 Node::Include(ref i) => {
                     size_hint += self.handle_include(ctx, buf, i)?;
                 }
+                Node::Embed(ref e) => {
+                    size_hint += self.write_embed(buf, e)?;
+                }
                 Node::Call(ref call) => {
                     size_hint += self.write_call(ctx, buf, call)?;
                 }
                 Node::FilterBlock(ref filter) => {
                     size_hint += self.write_filter_block(ctx, buf, filter)?;
                 }
+                Node::Autoescape(ref a) => {
+                    self.handle_ws(a.ws1);
+                    let outer = mem::replace(&mut self.autoescape, a.enabled);
+                    size_hint += self.handle(ctx, &a.nodes, buf, AstLevel::Nested)?;
+                    self.autoescape = outer;
+                    self.handle_ws(a.ws2);
+                }
                 Node::Macro(ref m) => {
                     if level != AstLevel::Top {
                         return Err("macro blocks only allowed at the top level".into());
@@ -323,6 +515,11 @@ impl<'a> Generator<'a> {
                     self.write_buf_writable(buf)?;
                     buf.writeln("continue;")?;
                 }
+                Node::Debug(ws) => {
+                    self.handle_ws(ws);
+                    self.write_buf_writable(buf)?;
+                    self.write_debug(buf)?;
+                }
             }
         }
 
@@ -334,9 +531,38 @@ impl<'a> Generator<'a> {
 
             size_hint += self.write_buf_writable(buf)?;
         }
+        if self.hoisted_calls.len() > hoisted {
+            // The writes using the hoisted calls must stay in the scope of their locals.
+            size_hint += self.write_buf_writable(buf)?;
+            self.hoisted_calls.truncate(hoisted);
+        }
         Ok(size_hint)
     }
 
+    // With `dedup_exprs`, returns the calls on `self` to evaluate only once, at the start of the
+    // scope of `nodes`: those written more than once in the scope, including its nested blocks,
+    // and at least once directly in it, so that hoisting them does not evaluate a call which
+    // would not be evaluated otherwise.
+    fn calls_to_hoist(&self, nodes: &'a [Node<'a>]) -> Vec<&'a Expr<'a>> {
+        if !self.input.config.dedup_exprs {
+            return Vec::new();
+        }
+        let mut calls = Vec::new();
+        collect_self_calls(nodes, true, &mut calls);
+        calls
+            .into_iter()
+            .filter(|&(call, direct, count)| {
+                direct
+                    && count > 1
+                    && !self
+                        .hoisted_calls
+                        .iter()
+                        .any(|(hoisted, ..)| *hoisted == call)
+            })
+            .map(|(call, ..)| call)
+            .collect()
+    }
+
     fn write_if(
         &mut self,
         ctx: &Context<'a>,
@@ -346,68 +572,111 @@ impl<'a> Generator<'a> {
         let mut flushed = 0;
         let mut arm_sizes = Vec::new();
         let mut has_else = false;
+        // Whether an `if` (or a plain block) was opened in the generated code.
+        let mut opened = false;
+        // The discarding state to restore once the current dead branch is done.
+        let mut restore_discard = None;
         for (i, cond) in i.branches.iter().enumerate() {
             self.handle_ws(cond.ws);
             flushed += self.write_buf_writable(buf)?;
+            if let Some((writable_discard, buf_discard)) = restore_discard.take() {
+                self.buf_writable.discard = writable_discard;
+                buf.discard = buf_discard;
+            }
             if i > 0 {
                 self.locals.pop();
             }
 
             self.locals.push();
+
+            // Branches with a literal `true` or `false` condition are resolved at compile time.
+            let constant = match &cond.cond {
+                None => Some(true),
+                Some(CondTest {
+                    target: None,
+                    expr: Expr::BoolLit(value),
+                }) => Some(*value == "true"),
+                Some(_) => None,
+            };
+            if has_else || constant == Some(false) {
+                // This branch can never be taken: it still goes through the generator, to keep
+                // the whitespace handling consistent, but none of its code is emitted.
+                restore_discard = Some((
+                    mem::replace(&mut self.buf_writable.discard, true),
+                    mem::replace(&mut buf.discard, true),
+                ));
+                self.handle(ctx, &cond.nodes, buf, AstLevel::Nested)?;
+                continue;
+            }
+
             let mut arm_size = 0;
-            if let Some(CondTest { target, expr }) = &cond.cond {
-                if i == 0 {
-                    buf.write("if ");
-                } else {
-                    buf.dedent()?;
-                    buf.write("} else if ");
-                }
+            match &cond.cond {
+                Some(CondTest { target, expr }) if constant.is_none() => {
+                    if !opened {
+                        buf.write("if ");
+                    } else {
+                        buf.dedent()?;
+                        buf.write("} else if ");
+                    }
 
-                if let Some(target) = target {
-                    let mut expr_buf = Buffer::new(0);
-                    buf.write("let ");
-                    // If this is a chain condition, then we need to declare the variable after the
-                    // left expression has been handled but before the right expression is handled
-                    // but this one should have access to the let-bound variable.
-                    match expr {
-                        Expr::BinOp(op, ref left, ref right) if *op == "||" || *op == "&&" => {
-                            self.visit_expr(&mut expr_buf, left)?;
-                            self.visit_target(buf, true, true, target);
-                            expr_buf.write(&format!(" {op} "));
-                            self.visit_expr(&mut expr_buf, right)?;
-                        }
-                        _ => {
-                            self.visit_expr(&mut expr_buf, expr)?;
-                            self.visit_target(buf, true, true, target);
+                    if let Some(target) = target {
+                        let mut expr_buf = Buffer::new(0);
+                        buf.write("let ");
+                        // If this is a chain condition, then we need to declare the variable after
+                        // the left expression has been handled but before the right expression is
+                        // handled but this one should have access to the let-bound variable.
+                        match expr {
+                            Expr::BinOp(op, ref left, ref right) if *op == "||" || *op == "&&" => {
+                                self.visit_expr(&mut expr_buf, left)?;
+                                self.visit_target(buf, true, true, target);
+                                expr_buf.write(&format!(" {op} "));
+                                self.visit_expr(&mut expr_buf, right)?;
+                            }
+                            _ => {
+                                self.visit_expr(&mut expr_buf, expr)?;
+                                self.visit_target(buf, true, true, target);
+                            }
                         }
+                        buf.write(" = &");
+                        buf.write(&expr_buf.buf);
+                    } else {
+                        // The following syntax `*(&(...) as &bool)` is used to
+                        // trigger Rust's automatic dereferencing, to coerce
+                        // e.g. `&&&&&bool` to `bool`. First `&(...) as &bool`
+                        // coerces e.g. `&&&bool` to `&bool`. Then `*(&bool)`
+                        // finally dereferences it to `bool`.
+                        buf.write("*(&(");
+                        let expr_code = self.visit_expr_root(expr)?;
+                        buf.write(&expr_code);
+                        buf.write(") as &bool)");
                     }
-                    buf.write(" = &");
-                    buf.write(&expr_buf.buf);
-                } else {
-                    // The following syntax `*(&(...) as &bool)` is used to
-                    // trigger Rust's automatic dereferencing, to coerce
-                    // e.g. `&&&&&bool` to `bool`. First `&(...) as &bool`
-                    // coerces e.g. `&&&bool` to `&bool`. Then `*(&bool)`
-                    // finally dereferences it to `bool`.
-                    buf.write("*(&(");
-                    let expr_code = self.visit_expr_root(expr)?;
-                    buf.write(&expr_code);
-                    buf.write(") as &bool)");
                 }
-            } else {
-                buf.dedent()?;
-                buf.write("} else");
-                has_else = true;
+                // An `else` or a literal `true` condition: if no branch was written yet, this
+                // branch is always taken so it doesn't need a condition.
+                _ => {
+                    if opened {
+                        buf.dedent()?;
+                        buf.write("} else");
+                    }
+                    has_else = true;
+                }
             }
 
             buf.writeln(" {")?;
+            opened = true;
 
             arm_size += self.handle(ctx, &cond.nodes, buf, AstLevel::Nested)?;
             arm_sizes.push(arm_size);
         }
         self.handle_ws(i.ws);
         flushed += self.write_buf_writable(buf)?;
-        buf.writeln("}")?;
+        if let Some((writable_discard, buf_discard)) = restore_discard {
+            self.buf_writable.discard = writable_discard;
+            buf.discard = buf_discard;
+        }
+        if opened {
+            buf.writeln("}")?;
+        }
 
         self.locals.pop();
 
@@ -451,6 +720,11 @@ impl<'a> Generator<'a> {
 
             self.locals.push();
             self.visit_target(buf, true, true, &arm.target);
+            if let Some(guard) = &arm.guard {
+                // Same coercion to `bool` as in `{% if %}`.
+                let guard = self.visit_expr_root(guard)?;
+                buf.write(&format!(" if *(&({guard}) as &bool)"));
+            }
             buf.writeln(" => {")?;
 
             arm_size = self.handle(ctx, &arm.nodes, buf, AstLevel::Nested)?;
@@ -474,6 +748,9 @@ impl<'a> Generator<'a> {
         loop_block: &'a Loop<'_>,
     ) -> Result<usize, CompileError> {
         self.handle_ws(loop_block.ws1);
+        if let Some(values) = self.unrolled_range(loop_block) {
+            return self.write_unrolled_loop(ctx, buf, loop_block, values);
+        }
         self.locals.push();
 
         let expr_code = self.visit_expr_root(&loop_block.iter)?;
@@ -505,30 +782,68 @@ impl<'a> Generator<'a> {
             // Otherwise, we borrow `iter` assuming that it implements `IntoIterator`.
             _ => buf.writeln(&format!("let _iter = ({expr_code}).into_iter();")),
         }?;
+        if loop_block.reversed {
+            buf.writeln("let _iter = ::std::iter::Iterator::rev(_iter);")?;
+        }
         if let Some(cond) = &loop_block.cond {
             self.locals.push();
-            buf.write("let _iter = _iter.filter(|");
-            self.visit_target(buf, true, true, &loop_block.var);
-            buf.write("| -> bool {");
-            self.visit_expr(buf, cond)?;
-            buf.writeln("});")?;
+            if loop_block.try_item {
+                // Errors are kept, so they get propagated in the loop body.
+                buf.write("let _iter = _iter.filter(|_item| -> bool { match _item { ");
+                buf.write("::core::result::Result::Ok(");
+                self.visit_target(buf, true, true, &loop_block.var);
+                buf.write(") => {");
+                self.visit_expr(buf, cond)?;
+                buf.writeln("} ::core::result::Result::Err(_) => true, } });")?;
+            } else {
+                buf.write("let _iter = _iter.filter(|");
+                self.visit_target(buf, true, true, &loop_block.var);
+                buf.write("| -> bool {");
+                self.visit_expr(buf, cond)?;
+                buf.writeln("});")?;
+            }
             self.locals.pop();
         }
 
         self.locals.push();
-        buf.write("for (");
-        self.visit_target(buf, true, true, &loop_block.var);
-        buf.write(", _loop_item) in ");
-        buf.write(CRATE);
-        buf.writeln("::helpers::TemplateLoop::new(_iter) {")?;
-
+        let mut head = Buffer::new(buf.indent);
+        head.discard = buf.discard;
+        head.write("for (");
+        if loop_block.try_item {
+            head.write("_item");
+        } else {
+            self.visit_target(&mut head, true, true, &loop_block.var);
+        }
+        head.write(", _loop_item) in ");
+        head.write(CRATE);
+        head.writeln("::helpers::TemplateLoop::new(_iter) {")?;
+
+        // The body is generated first, so the length of the loop is only computed if the body
+        // uses `loop.length`.
+        let mut body = Buffer::new(head.indent);
+        body.discard = buf.discard;
+        let outer_uses_length = mem::replace(&mut self.uses_loop_length, false);
+        if loop_block.try_item {
+            body.write("let ");
+            self.visit_target(&mut body, true, true, &loop_block.var);
+            body.write(" = ::core::result::Result::map_err(_item, |err| ");
+            body.write(CRATE);
+            body.writeln("::shared::Error::Custom(::core::convert::Into::into(err)))?;")?;
+        }
         if has_else_nodes {
-            buf.writeln("_did_loop = true;")?;
+            body.writeln("_did_loop = true;")?;
         }
-        let mut size_hint1 = self.handle(ctx, &loop_block.body, buf, AstLevel::Nested)?;
+        let mut size_hint1 = self.handle(ctx, &loop_block.body, &mut body, AstLevel::Nested)?;
         self.handle_ws(loop_block.ws2);
-        size_hint1 += self.write_buf_writable(buf)?;
+        size_hint1 += self.write_buf_writable(&mut body)?;
         self.locals.pop();
+        if mem::replace(&mut self.uses_loop_length, outer_uses_length) {
+            buf.write("let (_iter, _loop_length) = ");
+            buf.write(CRATE);
+            buf.writeln("::helpers::loop_length(_iter);")?;
+        }
+        buf.append(head);
+        buf.append(body);
         buf.writeln("}")?;
 
         let mut size_hint2;
@@ -551,6 +866,96 @@ impl<'a> Generator<'a> {
         Ok(flushed + ((size_hint1 * 3) + size_hint2) / 2)
     }
 
+    // Dumps the initialized local variables if the `debug` feature is enabled, the template
+    // fields being always available.
+    fn write_debug(&mut self, buf: &mut Buffer) -> Result<(), CompileError> {
+        if !cfg!(feature = "debug") {
+            return Ok(());
+        }
+        let vars = self.locals.visible();
+        buf.writeln(&format!("{CRATE}::helpers::write_debug_scope(writer, &["))?;
+        for (name, code) in vars {
+            buf.writeln(&format!("({name:?}, &({code}) as &dyn ::std::fmt::Debug),"))?;
+        }
+        buf.writeln("])?;")
+    }
+
+    // Returns the values of a loop over a tiny range with literal bounds, if the loop should be
+    // unrolled according to the `unroll_loops_below` option.
+    fn unrolled_range(&self, loop_block: &Loop<'_>) -> Option<std::ops::Range<u64>> {
+        let threshold = self.input.config.unroll_loops_below as u64;
+        if threshold == 0
+            || loop_block.cond.is_some()
+            || loop_block.try_item
+            || loop_block.reversed
+            || !loop_block.else_nodes.is_empty()
+            || !matches!(loop_block.var, Target::Name(_))
+            || has_loop_control(&loop_block.body)
+        {
+            return None;
+        }
+
+        let Expr::Range(op, Some(start), Some(end)) = &loop_block.iter else {
+            return None;
+        };
+        let (Expr::NumLit(start), Expr::NumLit(end)) = (&**start, &**end) else {
+            return None;
+        };
+        let start: u64 = start.parse().ok()?;
+        let mut end: u64 = end.parse().ok()?;
+        if *op == "..=" {
+            end = end.checked_add(1)?;
+        }
+        let len = end.checked_sub(start)?;
+        (len > 0 && len < threshold).then_some(start..end)
+    }
+
+    // Writes the body of the loop once for every value, each in its own scope. The whitespace
+    // handling matches `write_loop()`: every copy of the body starts in the same state.
+    fn write_unrolled_loop(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        loop_block: &'a Loop<'_>,
+        values: std::ops::Range<u64>,
+    ) -> Result<usize, CompileError> {
+        let flushed = self.write_buf_writable(buf)?;
+        let skip_ws = self.skip_ws;
+        let len = values.end - values.start;
+
+        let mut size_hint = 0;
+        for (index, value) in values.enumerate() {
+            self.skip_ws = skip_ws;
+            self.locals.push();
+            buf.writeln("{")?;
+            buf.write("let ");
+            self.visit_target(buf, true, true, &loop_block.var);
+            buf.writeln(&format!(" = {value};"))?;
+            buf.writeln(&format!(
+                "let _loop_item = {CRATE}::helpers::LoopItem {{ \
+                    index: {index}, first: {}, last: {} }};",
+                index == 0,
+                index as u64 + 1 == len,
+            ))?;
+            let mut body = Buffer::new(buf.indent);
+            body.discard = buf.discard;
+            let outer_uses_length = mem::replace(&mut self.uses_loop_length, false);
+            size_hint += self.handle(ctx, &loop_block.body, &mut body, AstLevel::Nested)?;
+            self.handle_ws(loop_block.ws2);
+            size_hint += self.write_buf_writable(&mut body)?;
+            if mem::replace(&mut self.uses_loop_length, outer_uses_length) {
+                buf.writeln(&format!("let _loop_length: usize = {len};"))?;
+            }
+            buf.append(body);
+            buf.writeln("}")?;
+            self.locals.pop();
+        }
+
+        self.handle_ws(loop_block.ws3);
+        size_hint += self.write_buf_writable(buf)?;
+        Ok(flushed + size_hint)
+    }
+
     fn write_call(
         &mut self,
         ctx: &Context<'a>,
@@ -564,140 +969,97 @@ impl<'a> Generator<'a> {
             ref args,
         } = *call;
         if name == "super" {
-            return self.write_block(ctx, buf, None, ws);
+            return self.write_block(ctx, buf, None, self.scoped_block, ws);
         }
 
         let (def, own_ctx) = match scope {
             Some(s) => {
                 let path = ctx.imports.get(s).ok_or_else(|| {
-                    CompileError::from(format!("no import found for scope {s:?}"))
+                    self.name_error(format!("no import found for scope {s:?}"), s)
                 })?;
                 let mctx = self
                     .contexts
                     .get(path)
                     .ok_or_else(|| CompileError::from(format!("context for {path:?} not found")))?;
                 let def = mctx.macros.get(name).ok_or_else(|| {
-                    CompileError::from(format!("macro {name:?} not found in scope {s:?}"))
+                    self.name_error(format!("macro {name:?} not found in scope {s:?}"), name)
                 })?;
                 (def, mctx)
             }
-            None => {
-                let def = ctx
-                    .macros
-                    .get(name)
-                    .ok_or_else(|| CompileError::from(format!("macro {name:?} not found")))?;
-                (def, ctx)
-            }
+            None => match ctx.macros.get(name) {
+                Some(def) => (def, ctx),
+                None => return self.write_block_call(ctx, buf, call),
+            },
         };
 
+        if self
+            .called_macros
+            .iter()
+            .any(|called| ptr::eq(*called, *def))
+        {
+            return Err(CompileError::from(format!(
+                "macro {name:?} calls itself, directly or through other macros, so it cannot \
+                 be expanded"
+            )));
+        }
+
         self.flush_ws(ws); // Cannot handle_ws() here: whitespace from macro definition comes first
         self.locals.push();
         self.write_buf_writable(buf)?;
         buf.writeln("{")?;
         self.prepare_ws(def.ws1);
 
-        let mut names = Buffer::new(0);
-        let mut values = Buffer::new(0);
-        let mut is_first_variable = true;
-        if args.len() != def.args.len() {
-            return Err(CompileError::from(format!(
-                "macro {name:?} expected {} argument{}, found {}",
-                def.args.len(),
-                if def.args.len() != 1 { "s" } else { "" },
-                args.len()
-            )));
-        }
-        let mut named_arguments = HashMap::new();
-        // Since named arguments can only be passed last, we only need to check if the last argument
-        // is a named one.
-        if let Some(Expr::NamedArgument(_, _)) = args.last() {
-            // First we check that all named arguments actually exist in the called item.
-            for arg in args.iter().rev() {
-                let Expr::NamedArgument(arg_name, _) = arg else {
-                    break;
-                };
-                if !def.args.iter().any(|arg| arg == arg_name) {
-                    return Err(CompileError::from(format!(
-                        "no argument named `{arg_name}` in macro {name:?}"
-                    )));
-                }
-                named_arguments.insert(Cow::Borrowed(arg_name), arg);
-            }
-        }
-
-        // Handling both named and unnamed arguments requires to be careful of the named arguments
-        // order. To do so, we iterate through the macro defined arguments and then check if we have
-        // a named argument with this name:
-        //
-        // * If there is one, we add it and move to the next argument.
-        // * If there isn't one, then we pick the next argument (we can do it without checking
-        //   anything since named arguments are always last).
-        let mut allow_positional = true;
-        for (index, arg) in def.args.iter().enumerate() {
-            let expr = match named_arguments.get(&Cow::Borrowed(arg)) {
-                Some(expr) => {
-                    allow_positional = false;
-                    expr
-                }
-                None => {
-                    if !allow_positional {
-                        // If there is already at least one named argument, then it's not allowed
-                        // to use unnamed ones at this point anymore.
-                        return Err(CompileError::from(format!(
-                            "cannot have unnamed argument (`{arg}`) after named argument in macro \
-                             {name:?}"
-                        )));
+        self.write_call_args(buf, "macro", name, &def.args, args)?;
+
+        // With `uses`, the body only sees its arguments and the declared variables of the
+        // calling scope, so the other variables are replaced by a new `MapChain`.
+        let caller = match def.uses.is_empty() {
+            true => None,
+            false => {
+                let mut locals = MapChain::default();
+                for var in &def.uses {
+                    let Some(resolved) = self.locals.resolve(var) else {
+                        return Err(format!(
+                            "macro {name:?} uses `{var}`, which is not a variable where it is \
+                             called"
+                        )
+                        .into());
+                    };
+                    if def.args.iter().any(|(arg, _)| arg == var) {
+                        return Err(format!(
+                            "`{var}` is both an argument of macro {name:?} and declared in its \
+                             `uses`"
+                        )
+                        .into());
                     }
-                    &args[index]
-                }
-            };
-            match expr {
-                // If `expr` is already a form of variable then
-                // don't reintroduce a new variable. This is
-                // to avoid moving non-copyable values.
-                &Expr::Var(name) if name != "self" => {
-                    let var = self.locals.resolve_or_self(name);
-                    self.locals
-                        .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
-                }
-                Expr::Attr(obj, attr) => {
-                    let mut attr_buf = Buffer::new(0);
-                    self.visit_attr(&mut attr_buf, obj, attr)?;
-
-                    let var = self.locals.resolve(&attr_buf.buf).unwrap_or(attr_buf.buf);
-                    self.locals
-                        .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
+                    locals.insert(
+                        Cow::Borrowed(normalize_identifier(var)),
+                        LocalMeta::with_ref(resolved),
+                    );
                 }
-                // Everything else still needs to become variables,
-                // to avoid having the same logic be executed
-                // multiple times, e.g. in the case of macro
-                // parameters being used multiple times.
-                _ => {
-                    if is_first_variable {
-                        is_first_variable = false
-                    } else {
-                        names.write(", ");
-                        values.write(", ");
-                    }
-                    names.write(arg);
+                let arguments = self.locals.scopes.pop().unwrap();
+                locals.scopes[0].extend(arguments);
 
-                    values.write("(");
-                    values.write(&self.visit_expr_root(expr)?);
-                    values.write(")");
-                    self.locals.insert_with_default(Cow::Borrowed(arg));
+                let mut hidden = self.hidden_locals.clone();
+                for (var, _) in self.locals.visible() {
+                    hidden.insert(var, def.name);
                 }
+                let hidden = mem::replace(&mut self.hidden_locals, hidden);
+                Some((mem::replace(&mut self.locals, locals), hidden))
             }
-        }
-
-        debug_assert_eq!(names.buf.is_empty(), values.buf.is_empty());
-        if !names.buf.is_empty() {
-            buf.writeln(&format!("let ({}) = ({});", names.buf, values.buf))?;
-        }
+        };
 
+        self.called_macros.push(def);
         let mut size_hint = self.handle(own_ctx, &def.nodes, buf, AstLevel::Nested)?;
+        self.called_macros.pop();
 
         self.flush_ws(def.ws2);
         size_hint += self.write_buf_writable(buf)?;
+        if let Some((locals, hidden)) = caller {
+            self.locals = locals;
+            self.locals.push();
+            self.hidden_locals = hidden;
+        }
         buf.writeln("}")?;
         self.locals.pop();
         self.prepare_ws(ws);
@@ -759,7 +1121,10 @@ impl<'a> Generator<'a> {
 
         insert_first_filter_argument(&mut arguments, var_name.clone());
 
-        let wrap = self.visit_filter(&mut filter_buf, filter_name, &arguments)?;
+        let mut wrap = self.visit_filter(&mut filter_buf, filter_name, &arguments)?;
+        if !self.autoescape {
+            wrap = DisplayWrap::Wrapped;
+        }
 
         self.buf_writable
             .push(Writable::Generated(filter_buf.buf, wrap));
@@ -819,20 +1184,117 @@ impl<'a> Generator<'a> {
             None
         };
 
-        let handle_ctx = match &heritage {
-            Some(heritage) => heritage.root,
-            None => child_ctx,
+        // With `with`, the child can only use the variables passed to it, which are evaluated
+        // in a new scope, like the arguments of a macro.
+        let locals = match &i.with {
+            Some(vars) => {
+                buf.writeln("{")?;
+                let mut locals = MapChain::default();
+                for (name, value) in vars {
+                    let meta = match value {
+                        Expr::Var(_) | Expr::Attr(..) => {
+                            LocalMeta::with_ref(self.visit_expr_root(value)?)
+                        }
+                        _ => {
+                            buf.writeln(&format!(
+                                "let {} = {};",
+                                normalize_identifier(name),
+                                self.visit_expr_root(value)?,
+                            ))?;
+                            LocalMeta::initialized()
+                        }
+                    };
+                    locals.insert(Cow::Owned((*name).to_owned()), meta);
+                }
+                locals
+            }
+            None => MapChain::with_parent(&self.locals),
         };
-        let locals = MapChain::with_parent(&self.locals);
         let mut child = Self::new(self.input, self.contexts, heritage.as_ref(), locals);
         child.buf_writable.discard = self.buf_writable.discard;
-        let mut size_hint = child.handle(handle_ctx, handle_ctx.nodes, buf, AstLevel::Top)?;
+        child.autoescape = self.autoescape;
+        child.isolated = i.with.is_some() || self.isolated;
+        child.hidden_locals.clone_from(&self.hidden_locals);
+
+        let Some(empty) = &i.empty else {
+            let mut size_hint = child.handle_template(child_ctx, buf)?;
+            size_hint += child.write_buf_writable(buf)?;
+            if i.with.is_some() {
+                buf.writeln("}")?;
+            }
+            self.prepare_ws(i.ws);
+            return Ok(size_hint);
+        };
+
+        // With fallback content, the included template is rendered into a buffer first, so we
+        // know if it is empty. The generated code always writes to `writer`, so we shadow it.
+        buf.writeln("{")?;
+        buf.writeln("let mut __include_buf = ::std::string::String::new();")?;
+        buf.writeln("{")?;
+        buf.writeln("let writer: &mut dyn ::std::fmt::Write = &mut __include_buf;")?;
+        let mut size_hint = child.handle_template(child_ctx, buf)?;
         size_hint += child.write_buf_writable(buf)?;
-        self.prepare_ws(i.ws);
+        buf.writeln("}")?;
+
+        buf.writeln("if __include_buf.trim().is_empty() {")?;
+        self.prepare_ws(empty.ws1);
+        size_hint += self.handle(ctx, &empty.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(empty.ws2);
+        size_hint += self.write_buf_writable(buf)?;
+        buf.writeln("} else {")?;
+        buf.writeln("writer.write_str(&__include_buf)?;")?;
+        buf.writeln("}")?;
+        buf.writeln("}")?;
+        if i.with.is_some() {
+            buf.writeln("}")?;
+        }
+        self.prepare_ws(empty.ws2);
 
         Ok(size_hint)
     }
 
+    fn write_embed(&mut self, buf: &mut Buffer, e: &'a Embed<'_>) -> Result<usize, CompileError> {
+        self.flush_ws(e.ws);
+        self.write_buf_writable(buf)?;
+        let path = self
+            .input
+            .config
+            .find_template(e.path, Some(&self.input.path))?;
+        let bytes = fs::read(&path)
+            .map_err(|err| CompileError::from(format!("unable to read {path:?}: {err}")))?;
+        if std::str::from_utf8(&bytes).is_err() {
+            return Err(format!("embedded file {:?} is not valid UTF-8", e.path).into());
+        }
+        let embed_path = path
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.to_str().map(str::to_owned))
+            .ok_or_else(|| {
+                CompileError::from(format!("invalid path of embedded file {:?}", e.path))
+            })?;
+
+        // The bytes are embedded by the compiler, which also makes it aware of the dependency,
+        // and checks again that they are valid UTF-8 when the file changes.
+        buf.writeln(
+            &quote! {
+                {
+                    const EMBED: &::std::primitive::str =
+                        match ::std::str::from_utf8(::std::include_bytes!(#embed_path)) {
+                            ::std::result::Result::Ok(s) => s,
+                            ::std::result::Result::Err(_) => {
+                                ::std::panic!("embedded file is not valid UTF-8")
+                            }
+                        };
+                    writer.write_str(EMBED)?;
+                }
+            }
+            .to_string(),
+        )?;
+        self.prepare_ws(e.ws);
+
+        Ok(bytes.len())
+    }
+
     fn is_shadowing_variable(&self, var: &Target<'a>) -> Result<bool, CompileError> {
         match var {
             Target::Name(name) => {
@@ -868,18 +1330,25 @@ impl<'a> Generator<'a> {
         }
     }
 
-    fn write_let(&mut self, buf: &mut Buffer, l: &'a Let<'_>) -> Result<(), CompileError> {
-        self.handle_ws(l.ws);
-
-        let Some(val) = &l.val else {
-            self.write_buf_writable(buf)?;
-            buf.write("let ");
-            self.visit_target(buf, false, true, &l.var);
-            return buf.writeln(";");
-        };
-
+    fn write_let(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        l: &'a Let<'_>,
+    ) -> Result<(), CompileError> {
         let mut expr_buf = Buffer::new(0);
-        self.visit_expr(&mut expr_buf, val)?;
+        if let Some(block) = &l.block {
+            self.write_let_block(ctx, buf, &mut expr_buf, l, block)?;
+        } else {
+            self.handle_ws(l.ws);
+            let Some(val) = &l.val else {
+                self.write_buf_writable(buf)?;
+                buf.write("let ");
+                self.visit_target(buf, false, true, &l.var);
+                return buf.writeln(";");
+            };
+            self.visit_expr(&mut expr_buf, val)?;
+        }
 
         let shadowed = self.is_shadowing_variable(&l.var)?;
         if shadowed {
@@ -895,25 +1364,260 @@ impl<'a> Generator<'a> {
         }
 
         self.visit_target(buf, true, true, &l.var);
+        if let (Some(_), Target::Name(name)) = (&l.block, &l.var) {
+            self.locals.insert(
+                Cow::Borrowed(normalize_identifier(name)),
+                LocalMeta::captured(),
+            );
+        }
         buf.writeln(&format!(" = {};", &expr_buf.buf))
     }
 
-    // If `name` is `Some`, this is a call to a block definition, and we have to find
-    // the first block for that name from the ancestry chain. If name is `None`, this
-    // is from a `super()` call, and we can get the name from `self.super_block`.
-    fn write_block(
+    // Renders the content of `{% set var %}...{% endset %}` into a `String`. Like for includes
+    // with fallback content, the generated code writes to a shadowed `writer`. The variable is
+    // marked as captured, so writing it does not escape its content a second time.
+    fn write_let_block(
         &mut self,
         ctx: &Context<'a>,
         buf: &mut Buffer,
-        name: Option<&'a str>,
-        outer: Ws,
-    ) -> Result<usize, CompileError> {
-        // Flush preceding whitespace according to the outer WS spec
-        self.flush_ws(outer);
+        expr_buf: &mut Buffer,
+        l: &'a Let<'_>,
+        block: &'a LetBlock<'_>,
+    ) -> Result<(), CompileError> {
+        if !matches!(l.var, Target::Name(_)) {
+            return Err("a block assignment `{% set %}` needs a single variable name".into());
+        }
 
-        let cur = match (name, self.super_block) {
-            // The top-level context contains a block definition
-            (Some(cur_name), None) => (cur_name, 0),
+        self.flush_ws(l.ws);
+        self.write_buf_writable(buf)?;
+        self.prepare_ws(l.ws);
+
+        let mut block_buf = Buffer::new(0);
+        block_buf.discard = buf.discard;
+        self.locals.push();
+        self.handle(ctx, &block.nodes, &mut block_buf, AstLevel::Nested)?;
+        self.flush_ws(block.ws);
+        self.write_buf_writable(&mut block_buf)?;
+        self.locals.pop();
+        self.prepare_ws(block.ws);
+
+        expr_buf.writeln("{")?;
+        expr_buf.writeln("let mut __set_buf = ::std::string::String::new();")?;
+        expr_buf.writeln("{")?;
+        expr_buf.writeln("let writer: &mut dyn ::std::fmt::Write = &mut __set_buf;")?;
+        expr_buf.write(&block_buf.buf);
+        expr_buf.writeln("}")?;
+        expr_buf.writeln("__set_buf")?;
+        expr_buf.write("}");
+        Ok(())
+    }
+
+    // Writes `{% call card("Title") %}` of a block declared as `{% block card(title) %}`. The
+    // arguments are bound in a new scope, and passed to the block like the variables of a
+    // `scoped` block.
+    fn write_block_call(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        call: &'a Call<'_>,
+    ) -> Result<usize, CompileError> {
+        let Call {
+            ws, name, ref args, ..
+        } = *call;
+        let def = match self.heritage.and_then(|heritage| heritage.blocks.get(name)) {
+            Some(defs) => defs[0].1,
+            None => return Err(self.name_error(format!("macro {name:?} not found"), name)),
+        };
+        if def.args.is_empty() {
+            return Err(format!(
+                "block {name:?} has no parameters, so it is rendered where it is defined and \
+                 cannot be called"
+            )
+            .into());
+        }
+        if self.called_blocks.contains(&name) {
+            return Err(format!(
+                "block {name:?} calls itself, directly or through other blocks, so it cannot be \
+                 expanded"
+            )
+            .into());
+        }
+
+        self.flush_ws(ws);
+        self.locals.push();
+        self.write_buf_writable(buf)?;
+        buf.writeln("{")?;
+        self.write_call_args(buf, "block", name, &def.args, args)?;
+
+        self.called_blocks.push(name);
+        let mut size_hint = self.write_block(ctx, buf, Some(name), false, ws)?;
+        self.called_blocks.pop();
+
+        size_hint += self.write_buf_writable(buf)?;
+        buf.writeln("}")?;
+        self.locals.pop();
+        Ok(size_hint)
+    }
+
+    // Binds the arguments of a call of a macro or of a block with parameters to the names of
+    // the `params`, in the current scope of `self.locals`.
+    fn write_call_args(
+        &mut self,
+        buf: &mut Buffer,
+        kind: &str,
+        name: &str,
+        params: &'a [(&'a str, Option<Expr<'a>>)],
+        args: &'a [Expr<'a>],
+    ) -> Result<(), CompileError> {
+        let mut names = Buffer::new(0);
+        let mut values = Buffer::new(0);
+        let mut is_first_variable = true;
+        let has_defaults = params.iter().any(|(_, default)| default.is_some());
+        if args.len() > params.len() || (!has_defaults && args.len() < params.len()) {
+            return Err(CompileError::from(format!(
+                "{kind} {name:?} expected {}{} argument{}, found {}",
+                if has_defaults { "at most " } else { "" },
+                params.len(),
+                if params.len() != 1 { "s" } else { "" },
+                args.len()
+            )));
+        }
+        // Named arguments can only be passed last.
+        let positional = args
+            .iter()
+            .take_while(|arg| !matches!(arg, Expr::NamedArgument(..)))
+            .count();
+        let mut named_arguments = HashMap::new();
+        for arg in &args[positional..] {
+            let Expr::NamedArgument(arg_name, _) = arg else {
+                continue;
+            };
+            // First we check that all named arguments actually exist in the called item, and
+            // that they are not passed by position too.
+            match params.iter().position(|(arg, _)| arg == arg_name) {
+                None => {
+                    let known = params
+                        .iter()
+                        .map(|(arg, _)| format!("`{arg}`"))
+                        .collect::<Vec<_>>();
+                    let known = match known.is_empty() {
+                        true => "it has no arguments".to_owned(),
+                        false => format!("its arguments are {}", known.join(", ")),
+                    };
+                    return Err(CompileError::from(format!(
+                        "no argument named `{arg_name}` in {kind} {name:?}, {known}"
+                    )));
+                }
+                Some(index) if index < positional => {
+                    return Err(CompileError::from(format!(
+                        "argument `{arg_name}` of {kind} {name:?} was already passed by position"
+                    )));
+                }
+                Some(_) => {}
+            }
+            named_arguments.insert(Cow::Borrowed(arg_name), arg);
+        }
+
+        // We iterate through the defined arguments, and use:
+        //
+        // * the positional argument at the same index, if there is one,
+        // * or else the named argument with this name, if there is one,
+        // * or else the default value of the argument. It is evaluated after the other
+        //   arguments, so it can use the arguments before it.
+        let mut defaults = Vec::new();
+        for (index, (arg, default)) in params.iter().enumerate() {
+            let expr = if index < positional {
+                &args[index]
+            } else if let Some(expr) = named_arguments.get(&Cow::Borrowed(arg)) {
+                expr
+            } else if let Some(default) = default {
+                defaults.push((*arg, default));
+                continue;
+            } else {
+                return Err(CompileError::from(format!(
+                    "missing argument `{arg}` in call of {kind} {name:?}"
+                )));
+            };
+            match expr {
+                // If `expr` is already a form of variable then
+                // don't reintroduce a new variable. This is
+                // to avoid moving non-copyable values.
+                &Expr::Var(name) if name != "self" => {
+                    let var = self.resolve_or_self(name)?;
+                    self.locals
+                        .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
+                }
+                Expr::Attr(obj, attr) => {
+                    let mut attr_buf = Buffer::new(0);
+                    self.visit_attr(&mut attr_buf, obj, attr)?;
+
+                    let var = self.locals.resolve(&attr_buf.buf).unwrap_or(attr_buf.buf);
+                    self.locals
+                        .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
+                }
+                // Everything else still needs to become variables,
+                // to avoid having the same logic be executed
+                // multiple times, e.g. in the case of
+                // parameters being used multiple times.
+                _ => {
+                    if is_first_variable {
+                        is_first_variable = false
+                    } else {
+                        names.write(", ");
+                        values.write(", ");
+                    }
+                    names.write(normalize_identifier(arg));
+
+                    values.write("(");
+                    values.write(&self.visit_expr_root(expr)?);
+                    values.write(")");
+                    self.locals
+                        .insert(Cow::Borrowed(arg), LocalMeta::initialized());
+                }
+            }
+        }
+
+        debug_assert_eq!(names.buf.is_empty(), values.buf.is_empty());
+        if !names.buf.is_empty() {
+            buf.writeln(&format!("let ({}) = ({});", names.buf, values.buf))?;
+        }
+        for (arg, default) in defaults {
+            match default {
+                &Expr::Var(name) if name != "self" => {
+                    let var = self.resolve_or_self(name)?;
+                    self.locals
+                        .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
+                }
+                _ => {
+                    let value = self.visit_expr_root(default)?;
+                    buf.writeln(&format!("let {} = ({value});", normalize_identifier(arg)))?;
+                    self.locals
+                        .insert(Cow::Borrowed(arg), LocalMeta::initialized());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // If `name` is `Some`, this is a call to a block definition, and we have to find
+    // the first block for that name from the ancestry chain. If name is `None`, this
+    // is from a `super()` call, and we can get the name from `self.super_block`.
+    // If `scoped` is `true`, the variables of the current scope are made available to the
+    // block definition.
+    fn write_block(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        name: Option<&'a str>,
+        scoped: bool,
+        outer: Ws,
+    ) -> Result<usize, CompileError> {
+        // Flush preceding whitespace according to the outer WS spec
+        self.flush_ws(outer);
+
+        let cur = match (name, self.super_block) {
+            // The top-level context contains a block definition
+            (Some(cur_name), None) => (cur_name, 0),
             // A block definition contains a block definition of the same name
             (Some(cur_name), Some((prev_name, _))) if cur_name == prev_name => {
                 return Err(format!("cannot define recursive blocks ({cur_name})").into());
@@ -929,6 +1633,23 @@ impl<'a> Generator<'a> {
         self.write_buf_writable(buf)?;
 
         let block_fragment_write = self.input.block == name && self.buf_writable.discard;
+        let visible = match block_fragment_write && scoped {
+            true => self.locals.visible(),
+            false => Vec::new(),
+        };
+        if !visible.is_empty() {
+            let names = visible
+                .iter()
+                .map(|(name, _)| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(format!(
+                "block {:?} is `scoped`, so it cannot be rendered on its own, without the \
+                 variables defined around it: {names}",
+                cur.0,
+            )
+            .into());
+        }
         // Allow writing to the buffer if we're in the block fragment
         if block_fragment_write {
             self.buf_writable.discard = false;
@@ -941,7 +1662,10 @@ impl<'a> Generator<'a> {
             .ok_or_else(|| CompileError::from("no block ancestors available"))?;
         let (child_ctx, def) = *heritage.blocks[cur.0].get(cur.1).ok_or_else(|| {
             CompileError::from(match name {
-                None => format!("no super() block found for block '{}'", cur.0),
+                None => format!(
+                    "`super()` was called in block '{}', but no parent template defines this block",
+                    cur.0
+                ),
                 Some(name) => format!("no block found for name '{name}'"),
             })
         })?;
@@ -960,14 +1684,29 @@ impl<'a> Generator<'a> {
                 .or_insert_with(|| import.clone());
         }
 
-        let mut child = Self::new(
-            self.input,
-            self.contexts,
-            Some(heritage),
-            // Variables are NOT inherited from the parent scope.
-            MapChain::default(),
-        );
+        // Variables are NOT inherited from the parent scope, unless the block is `scoped`.
+        let mut locals = match scoped {
+            true => {
+                let mut locals = self.locals.clone();
+                locals.push();
+                locals
+            }
+            false => MapChain::default(),
+        };
+        // The arguments of a block with parameters are passed along to its `super()` block.
+        for (arg, _) in &def.args {
+            if let Some(var) = self.locals.resolve(arg) {
+                locals.insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
+            }
+        }
+        let mut child = Self::new(self.input, self.contexts, Some(heritage), locals);
         child.buf_writable = mem::take(&mut self.buf_writable);
+        child.scoped_block = scoped;
+        child.called_macros.clone_from(&self.called_macros);
+        child.called_blocks.clone_from(&self.called_blocks);
+        child.autoescape = self.autoescape;
+        child.isolated = self.isolated;
+        child.hidden_locals.clone_from(&self.hidden_locals);
 
         // Handle inner whitespace suppression spec and process block nodes
         child.prepare_ws(def.ws1);
@@ -987,6 +1726,11 @@ impl<'a> Generator<'a> {
         // succeeding whitespace according to the outer WS spec
         self.prepare_ws(outer);
 
+        // The rendered block fragment must be written before the code is discarded again.
+        if prev_buf_discard && !buf.discard {
+            self.write_buf_writable(buf)?;
+        }
+
         // Restore the original buffer discarding state
         if block_fragment_write {
             self.buf_writable.discard = true;
@@ -998,7 +1742,7 @@ impl<'a> Generator<'a> {
 
     fn write_expr(&mut self, ws: Ws, s: &'a Expr<'a>) {
         self.handle_ws(ws);
-        self.buf_writable.push(Writable::Expr(s));
+        self.buf_writable.push(Writable::Expr(s, self.autoescape));
     }
 
     // Write expression buffer and empty
@@ -1069,10 +1813,25 @@ impl<'a> Generator<'a> {
                     buf_format.write(&s.replace('{', "{{").replace('}', "}}"));
                     size_hint += s.len();
                 }
-                Writable::Expr(s) => {
+                Writable::Expr(s, autoescape) => {
                     let mut expr_buf = Buffer::new(0);
-                    let wrapped = self.visit_expr(&mut expr_buf, s)?;
-                    let cacheable = is_cacheable(s);
+                    let hoisted = self.hoisted_calls.iter().find(|(call, ..)| *call == s);
+                    let mut wrapped = match s {
+                        Expr::BinOp("~", left, right) if autoescape => {
+                            self.visit_concat_escaped(&mut expr_buf, left, right)?
+                        }
+                        _ => match hoisted {
+                            Some((_, name, wrap)) => {
+                                expr_buf.write(name);
+                                *wrap
+                            }
+                            None => self.visit_expr(&mut expr_buf, s)?,
+                        },
+                    };
+                    if !autoescape {
+                        wrapped = DisplayWrap::Wrapped;
+                    }
+                    let cacheable = is_cacheable(s, self.input.config.dedup_exprs);
                     size_hint += self.named_expression(
                         &mut buf_expr,
                         &mut buf_format,
@@ -1114,10 +1873,14 @@ impl<'a> Generator<'a> {
     ) -> Result<usize, CompileError> {
         let expression = match wrapped {
             DisplayWrap::Wrapped => expr,
-            DisplayWrap::Unwrapped => format!(
-                "{CRATE}::MarkupDisplay::new_unsafe(&({}), {})",
-                expr, self.input.escaper
-            ),
+            // Values which never need HTML escaping, like numbers, don't need escaping with any
+            // other escaper either, so this shortcut is also valid for a runtime escaper.
+            DisplayWrap::Unwrapped if self.input.escaper == format!("{CRATE}::Html") => {
+                format!("{CRATE}::helpers::MaybeSafe::new(&({expr})).markup(&_escaper)")
+            }
+            DisplayWrap::Unwrapped => {
+                format!("{CRATE}::MarkupDisplay::new_unsafe(&({expr}), &_escaper)")
+            }
         };
         let id = match expr_cache.entry(expression) {
             Entry::Occupied(e) if cacheable => *e.get(),
@@ -1175,6 +1938,13 @@ impl<'a> Generator<'a> {
 
     fn write_comment(&mut self, comment: &'a Comment<'_>) {
         self.handle_ws(comment.ws);
+        if cfg!(feature = "debug-comments") {
+            if let Some(text) = comment.debug {
+                self.buf_writable.push(Writable::Lit("<!-- "));
+                self.buf_writable.push(Writable::Lit(text));
+                self.buf_writable.push(Writable::Lit(" -->"));
+            }
+        }
     }
 
     /* Visitor methods for expression types */
@@ -1195,7 +1965,7 @@ impl<'a> Generator<'a> {
             Expr::NumLit(s) => self.visit_num_lit(buf, s),
             Expr::StrLit(s) => self.visit_str_lit(buf, s),
             Expr::CharLit(s) => self.visit_char_lit(buf, s),
-            Expr::Var(s) => self.visit_var(buf, s),
+            Expr::Var(s) => self.visit_var(buf, s)?,
             Expr::Path(ref path) => self.visit_path(buf, path),
             Expr::Array(ref elements) => self.visit_array(buf, elements)?,
             Expr::Attr(ref obj, name) => self.visit_attr(buf, obj, name)?,
@@ -1213,12 +1983,119 @@ impl<'a> Generator<'a> {
             Expr::Call(ref obj, ref args) => self.visit_call(buf, obj, args)?,
             Expr::RustMacro(ref path, args) => self.visit_rust_macro(buf, path, args),
             Expr::Try(ref expr) => self.visit_try(buf, expr)?,
+            Expr::Closure(ref params, ref body) => self.visit_closure(buf, params, body)?,
+            Expr::OptionalChain(ref obj, ref rest) => self.visit_optional_chain(buf, obj, rest)?,
+            Expr::OptionalValue => {
+                buf.write("_opt_value");
+                DisplayWrap::Unwrapped
+            }
+            Expr::IfElse(ref cond, ref then, ref otherwise) => {
+                self.visit_if_else(buf, cond, then, otherwise)?
+            }
             Expr::Tuple(ref exprs) => self.visit_tuple(buf, exprs)?,
             Expr::NamedArgument(_, ref expr) => self.visit_named_argument(buf, expr)?,
             Expr::Generated(ref s) => self.visit_generated(buf, s),
         })
     }
 
+    // The closure borrows the template's fields through `self`, so it can be passed to methods
+    // like `Iterator::filter()` without moving anything out of the template.
+    fn visit_closure(
+        &mut self,
+        buf: &mut Buffer,
+        params: &[&str],
+        body: &Expr<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.locals.push();
+        buf.write("|");
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                buf.write(", ");
+            }
+            buf.write(normalize_identifier(param));
+            self.locals
+                .insert(Cow::Owned(param.to_string()), LocalMeta::initialized());
+        }
+        buf.write("| ");
+        let result = self.visit_expr(buf, body);
+        self.locals.pop();
+        result?;
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_optional_chain(
+        &mut self,
+        buf: &mut Buffer,
+        obj: &Expr<'_>,
+        rest: &Expr<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        buf.write("::core::option::Option::map(");
+        self.visit_optional_obj(buf, obj)?;
+        buf.write(", |_opt_value| ");
+        self.visit_optional_rest(buf, rest)?;
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // Writes `obj` as an `Option` of a reference to its value. In `a?.b?.c`, the object of
+    // `?.c` is the chain `a?.b`, which is flattened with `and_then()`.
+    fn visit_optional_obj(&mut self, buf: &mut Buffer, obj: &Expr<'_>) -> Result<(), CompileError> {
+        let into_option = format!("{CRATE}::helpers::OptionalChain::into_option(");
+        match obj {
+            Expr::OptionalChain(obj, rest) => {
+                buf.write("::core::option::Option::and_then(");
+                self.visit_optional_obj(buf, obj)?;
+                buf.write(", |_opt_value| ");
+                buf.write(&into_option);
+                self.visit_optional_rest(buf, rest)?;
+                buf.write("))");
+            }
+            obj => {
+                buf.write(&into_option);
+                self.visit_optional_rest(buf, obj)?;
+                buf.write(")");
+            }
+        }
+        Ok(())
+    }
+
+    // Fields are borrowed, while the results of calls are used as they are, because they could
+    // not be borrowed out of the closure.
+    fn visit_optional_rest(
+        &mut self,
+        buf: &mut Buffer,
+        rest: &Expr<'_>,
+    ) -> Result<(), CompileError> {
+        if matches!(rest, Expr::Attr(..) | Expr::Index(..) | Expr::Var(_)) {
+            buf.write("&");
+        }
+        buf.write("(");
+        self.visit_expr(buf, rest)?;
+        buf.write(")");
+        Ok(())
+    }
+
+    fn visit_if_else(
+        &mut self,
+        buf: &mut Buffer,
+        cond: &Expr<'_>,
+        then: &Expr<'_>,
+        otherwise: &Expr<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        // Same coercion to `bool` as in `{% if %}`.
+        buf.write("(if *(&(");
+        self.visit_expr(buf, cond)?;
+        buf.write(") as &bool) { ");
+        let then = self.visit_expr(buf, then)?;
+        buf.write(" } else { ");
+        let otherwise = self.visit_expr(buf, otherwise)?;
+        buf.write(" })");
+        Ok(match (then, otherwise) {
+            (DisplayWrap::Wrapped, DisplayWrap::Wrapped) => DisplayWrap::Wrapped,
+            _ => DisplayWrap::Unwrapped,
+        })
+    }
+
     fn visit_try(
         &mut self,
         buf: &mut Buffer,
@@ -1244,143 +2121,1088 @@ impl<'a> Generator<'a> {
     fn visit_filter(
         &mut self,
         buf: &mut Buffer,
-        name: &str,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if !matches!(
+            name,
+            "capitalize"
+                | "default"
+                | "filesizeformat"
+                | "format_float"
+                | "hex"
+                | "indent"
+                | "join"
+                | "map"
+                | "max"
+                | "min"
+                | "replace"
+                | "round"
+                | "sort"
+                | "sort_by"
+                | "sum"
+                | "truncate"
+                | "wordwrap"
+        ) {
+            if let Some(Expr::NamedArgument(arg, _)) = args.last() {
+                return Err(format!("filter `{name}` has no argument named `{arg}`").into());
+            }
+        }
+
+        match name {
+            "as_ref" => return self._visit_as_ref_filter(buf, args),
+            "batch" => return self._visit_batch_filter(buf, args),
+            "capitalize" => return self._visit_capitalize_filter(buf, args),
+            "date" => return self._visit_date_filter(buf, args),
+            "default" => return self._visit_default_filter(buf, args),
+            "deref" => return self._visit_deref_filter(buf, args),
+            "enumerate" | "skip" | "take" | "zip" => {
+                return self._visit_iterator_filter(buf, name, args)
+            }
+            "escape" | "e" => return self._visit_escape_filter(buf, args),
+            "filesizeformat" => return self._visit_filesizeformat_filter(buf, args),
+            "first" | "last" => return self._visit_first_last_filter(buf, name, args),
+            "fmt" => return self._visit_fmt_filter(buf, args),
+            "format" => return self._visit_format_filter(buf, args),
+            "format_float" => return self._visit_format_float_filter(buf, args),
+            "groupby" => return self._visit_groupby_filter(buf, args),
+            "hex" => return self._visit_hex_filter(buf, args),
+            "indent" => return self._visit_indent_filter(buf, args),
+            "join" => return self._visit_join_filter(buf, args),
+            "json" | "tojson" => return self._visit_json_filter(buf, args),
+            "replace" => return self._visit_replace_filter(buf, args),
+            "round" => return self._visit_round_filter(buf, args),
+            "map" => return self._visit_map_filter(buf, args),
+            "linebreaksbr" | "nl2br" => return self._visit_linebreaksbr_filter(buf, name, args),
+            "min" | "max" | "sum" => return self._visit_aggregate_filter(buf, name, args),
+            "numberformat" => return self._visit_numberformat_filter(buf, args),
+            "pluralize" => return self._visit_pluralize_filter(buf, args),
+            "safe" => return self._visit_safe_filter(buf, args),
+            "select" | "reject" => return self._visit_select_filter(buf, name, args),
+            "sort" | "sort_by" => return self._visit_sort_filter(buf, name, args),
+            "thousands" | "currency" => return self._visit_locale_filter(buf, name, args),
+            "trim" | "trim_start" | "trim_end" if name != "trim" || args.len() == 2 => {
+                return self._visit_trim_filter(buf, name, args)
+            }
+            "truncate" => return self._visit_truncate_filter(buf, args),
+            "unique" => return self._visit_unique_filter(buf, args),
+            "urlencode" if args.len() == 2 => return self._visit_urlencode_filter(buf, args),
+            "wordwrap" => return self._visit_wordwrap_filter(buf, args),
+            _ => {}
+        }
+
+        if crate::BUILT_IN_FILTERS.contains(&name) {
+            buf.write(&format!("{CRATE}::filters::{name}("));
+        } else {
+            if self.input.config.strict && !self.input.config.custom_filters.contains(&name) {
+                let msg =
+                    format!("filter `{name}` is neither built-in nor listed in `custom_filters`");
+                return Err(self.name_error(msg, name));
+            }
+            buf.write(&format!("filters::{name}("));
+        }
+        self._visit_args(buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // Iterator adapters take their input by value when it's already an iterator, so that they
+    // can be chained (`items|skip(2)|take(5)`): a borrowed iterator cannot be iterated over.
+    fn _visit_iterator_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let Some((iter, rest)) = args.split_first() else {
+            return Err(format!("filter `{name}` needs an iterable to iterate over").into());
+        };
+        buf.write(&format!("{CRATE}::filters::{name}("));
+        self._visit_iterable(buf, iter)?;
+        match (name, rest) {
+            (_, []) => {}
+            // The argument of `zip` is iterated over, too.
+            ("zip", [other]) => {
+                buf.write(", ");
+                self._visit_iterable(buf, other)?;
+            }
+            _ => {
+                buf.write(", ");
+                self._visit_args(buf, rest)?;
+            }
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `items|map("name")` borrows the field `name` of every element, and
+    // `items|map("name", args...)` calls the method `name` with the arguments instead.
+    // `users|map("address.city")` borrows the field of every element, and
+    // `names|map(filter="lower")` applies the built-in filter to every element and collects the
+    // results. A bare name is always a field or method, even if a built-in filter has that name.
+    fn _visit_map_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (iter, name, rest) = match args {
+            [iter, Expr::StrLit(name), rest @ ..] => (iter, *name, rest),
+            [iter, Expr::NamedArgument("attribute", name), rest @ ..] => match **name {
+                Expr::StrLit(name) => (iter, name, rest),
+                _ => return Err("`attribute` of `map` filter must be a string literal".into()),
+            },
+            [iter, Expr::NamedArgument("filter", name), rest @ ..] => {
+                let Expr::StrLit(name) = **name else {
+                    return Err("`filter` of `map` filter must be a string literal".into());
+                };
+                if name == "map" || !crate::BUILT_IN_FILTERS.contains(&name) {
+                    let msg =
+                        format!("`{name}` in `map` filter is not the name of a built-in filter");
+                    return Err(msg.into());
+                }
+                // The arguments of the filter are passed as `args=(2, true)`, because positional
+                // arguments cannot follow the named `filter` argument.
+                let rest = match rest {
+                    [] => &[],
+                    [Expr::NamedArgument("args", args)] => match &**args {
+                        Expr::Tuple(args) => args.as_slice(),
+                        args => std::slice::from_ref(args),
+                    },
+                    [.., Expr::NamedArgument(arg, _)] => {
+                        return Err(format!("filter `map` has no argument named `{arg}`").into());
+                    }
+                    _ => {
+                        let msg = "`map` filter expects the arguments of the filter as `args=(…)`";
+                        return Err(msg.into());
+                    }
+                };
+                let mut filter_args = Vec::with_capacity(rest.len() + 1);
+                // Unlike a variable, a path is passed by value, so the result does not borrow
+                // the closure argument.
+                filter_args.push(Expr::Path(vec!["__item"]));
+                filter_args.extend_from_slice(rest);
+                buf.write(&format!(
+                    "::std::iter::Iterator::collect::<::std::result::Result<\
+                        ::std::vec::Vec<_>, {CRATE}::Error>>(\
+                        ::std::iter::Iterator::map(::std::iter::IntoIterator::into_iter("
+                ));
+                self._visit_iterable(buf, iter)?;
+                buf.write(&format!(
+                    "), |__item| ::std::result::Result::Ok::<_, {CRATE}::Error>("
+                ));
+                self.visit_filter(buf, name, &filter_args)?;
+                buf.write(")))?");
+                return Ok(DisplayWrap::Unwrapped);
+            }
+            _ => {
+                let msg = "`map` filter expects a field path or a method name as string literal, \
+                           or a built-in filter name as `filter=\"…\"`";
+                return Err(msg.into());
+            }
+        };
+
+        let named = rest
+            .iter()
+            .find(|arg| matches!(arg, Expr::NamedArgument(..)));
+        if let Some(Expr::NamedArgument(arg, _)) = named {
+            return Err(format!("filter `map` has no argument named `{arg}`").into());
+        }
+        let fields = split_field_path(name).ok_or_else(|| {
+            format!("`{name}` in `map` filter is not a field path like `address.city`")
+        })?;
+        buf.write("::std::iter::Iterator::map(::std::iter::IntoIterator::into_iter(");
+        self._visit_iterable(buf, iter)?;
+        buf.write("), |__item| ");
+        if rest.is_empty() {
+            buf.write(&format!("&__item.{fields}"));
+        } else {
+            buf.write(&format!("__item.{fields}("));
+            self._visit_args(buf, rest)?;
+            buf.write(")");
+        }
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `users|select("active")` keeps the elements whose field `active` is `true`, with
+    // `select("is_active()")` or `select("has_role", "admin")` a method is called instead.
+    fn _visit_select_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [iter, Expr::StrLit(test), rest @ ..] = args else {
+            let msg = format!("`{name}` filter expects a field or method name as string literal");
+            return Err(msg.into());
+        };
+        if let Some(Expr::NamedArgument(arg, _)) = rest.last() {
+            return Err(format!("filter `{name}` has no argument named `{arg}`").into());
+        }
+        let (path, call) = match test.strip_suffix("()") {
+            Some(path) if rest.is_empty() => (path, true),
+            _ => (*test, !rest.is_empty()),
+        };
+        let fields = split_field_path(path)
+            .ok_or_else(|| format!("invalid field or method name `{test}` in `{name}` filter"))?;
+
+        buf.write("::std::iter::Iterator::filter(::std::iter::IntoIterator::into_iter(");
+        self._visit_iterable(buf, iter)?;
+        buf.write("), |__item| ");
+        if name == "reject" {
+            buf.write("!");
+        }
+        buf.write(&format!("__item.{fields}"));
+        if call {
+            buf.write("(");
+            self._visit_args(buf, rest)?;
+            buf.write(")");
+        }
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `users|sort_by("address.city")` compares `&__a.address.city` and `&__b.address.city`.
+    fn _visit_sort_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (iter, path, rest) = match (name, args) {
+            ("sort", [iter, rest @ ..]) => (iter, None, rest),
+            ("sort_by", [iter, Expr::StrLit(path), rest @ ..]) => (iter, Some(*path), rest),
+            _ => {
+                let msg = "`sort_by` filter expects a field name or path as string literal";
+                return Err(msg.into());
+            }
+        };
+        let reverse = match rest {
+            [] => None,
+            [Expr::NamedArgument("reverse", reverse)] => Some(&**reverse),
+            [reverse] if !matches!(reverse, Expr::NamedArgument(..)) => Some(reverse),
+            _ => {
+                return Err(
+                    format!("filter `{name}` expects optionally `reverse` as argument").into(),
+                )
+            }
+        };
+
+        buf.write(&format!("{CRATE}::filters::{name}("));
+        self._visit_iterable(buf, iter)?;
+        if let Some(path) = path {
+            let fields = split_field_path(path)
+                .ok_or_else(|| format!("invalid field path `{path}` in `sort_by` filter"))?;
+            buf.write(&format!(
+                ", |__a, __b| ::std::cmp::Ord::cmp(&__a.{fields}, &__b.{fields})"
+            ));
+        }
+        buf.write(", ");
+        match reverse {
+            Some(reverse) => {
+                self.visit_expr(buf, reverse)?;
+            }
+            None => buf.write("false"),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `products|groupby("category")` groups by `&__item.category`.
+    fn _visit_groupby_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [iter, Expr::StrLit(path)] = args else {
+            return Err("`groupby` filter expects a field name or path as string literal".into());
+        };
+        let fields = split_field_path(path)
+            .ok_or_else(|| format!("invalid field path `{path}` in `groupby` filter"))?;
+
+        buf.write(&format!("{CRATE}::filters::groupby("));
+        self._visit_iterable(buf, iter)?;
+        buf.write(&format!(", |__item| &__item.{fields})?"));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_unique_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (iter, path) = match args {
+            [iter] => (iter, None),
+            [iter, Expr::StrLit(path)] => (iter, Some(*path)),
+            _ => {
+                let msg = "`unique` filter expects optionally a field name or path as string \
+                           literal";
+                return Err(msg.into());
+            }
+        };
+        match path {
+            Some(path) => {
+                let fields = split_field_path(path)
+                    .ok_or_else(|| format!("invalid field path `{path}` in `unique` filter"))?;
+                buf.write(&format!("{CRATE}::filters::unique_by("));
+                self._visit_iterable(buf, iter)?;
+                buf.write(&format!(", |__item| &__item.{fields})?"));
+            }
+            None => {
+                buf.write(&format!("{CRATE}::filters::unique("));
+                self._visit_iterable(buf, iter)?;
+                buf.write(")?");
+            }
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_iterable(&mut self, buf: &mut Buffer, iter: &Expr<'_>) -> Result<(), CompileError> {
+        let is_iterator = |expr: &Expr<'_>| matches!(expr, Expr::Filter(_) | Expr::Range(..));
+        match iter {
+            Expr::Group(inner) if is_iterator(inner) => {
+                self.visit_expr(buf, iter)?;
+            }
+            _ if is_iterator(iter) => {
+                self.visit_expr(buf, iter)?;
+            }
+            _ => self._visit_args(buf, std::slice::from_ref(iter))?,
+        }
+        Ok(())
+    }
+
+    // `first` and `last` also work on strings, which are not iterable: the method call syntax
+    // dereferences the argument until it implements one of the two traits.
+    fn _visit_first_last_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [arg] = args else {
+            return Err(format!("unexpected argument(s) in `{name}` filter").into());
+        };
+        buf.write(&format!(
+            "{{ use {CRATE}::filters::{{FirstLastIter as _, FirstLastStr as _}}; ("
+        ));
+        self._visit_iterable(buf, arg)?;
+        buf.write(&format!(").filter_{name}()? }}"));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_batch_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (items, size, fill) = match args {
+            [items, size] => (items, size, None),
+            [items, size, fill] => (items, size, Some(fill)),
+            _ => {
+                return Err(
+                    r#"use filter batch like `items|batch(3)` or `items|batch(3, "-")`"#.into(),
+                )
+            }
+        };
+        if matches!(size, Expr::NumLit(size) if size.trim_start_matches('0').is_empty()) {
+            return Err("the size of the rows of filter `batch` must not be zero".into());
+        }
+
+        match fill {
+            Some(_) => buf.write(&format!("{CRATE}::filters::batch_filled(")),
+            None => buf.write(&format!("{CRATE}::filters::batch(")),
+        }
+        self._visit_iterable(buf, items)?;
+        buf.write(", ");
+        self.visit_expr(buf, size)?;
+        if let Some(fill) = fill {
+            buf.write(", ");
+            self._visit_args(buf, std::slice::from_ref(fill))?;
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_as_ref_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let arg = match args {
+            [arg] => arg,
+            _ => return Err("unexpected argument(s) in `as_ref` filter".into()),
+        };
+        buf.write("&");
+        self.visit_expr(buf, arg)?;
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_deref_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let arg = match args {
+            [arg] => arg,
+            _ => return Err("unexpected argument(s) in `deref` filter".into()),
+        };
+        buf.write("*");
+        self.visit_expr(buf, arg)?;
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_date_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if cfg!(not(feature = "time")) {
+            return Err("the `date` filter requires the `time` feature to be enabled".into());
+        }
+        if args.len() != 2 {
+            return Err("filter `date` expects a format, e.g. `value|date(\"%Y-%m-%d\")`".into());
+        }
+
+        buf.write(&format!("{CRATE}::filters::date("));
+        self._visit_args(buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_json_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if cfg!(not(feature = "serde-json")) {
+            return Err("the `json` filter requires the `serde-json` feature to be enabled".into());
+        }
+
+        match args {
+            [_] => buf.write(&format!("{CRATE}::filters::json(")),
+            [_, _] => buf.write(&format!("{CRATE}::filters::json_pretty(")),
+            _ => return Err("unexpected argument(s) in `json` filter".into()),
+        }
+        self._visit_args(buf, &args[..1])?;
+        if let Some(indent) = args.get(1) {
+            buf.write(", ");
+            self.visit_expr(buf, indent)?;
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_safe_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if args.len() != 1 {
+            return Err("unexpected argument(s) in `safe` filter".into());
+        }
+        buf.write(CRATE);
+        buf.write("::filters::safe(&_escaper, ");
+        self._visit_args(buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Wrapped)
+    }
+
+    // The text is escaped before the line breaks are inserted, unless it is already safe.
+    fn _visit_linebreaksbr_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [text] = args else {
+            return Err(format!("unexpected argument(s) in `{name}` filter").into());
+        };
+        let mut text_buf = Buffer::new(0);
+        let wrap = self.visit_expr(&mut text_buf, text)?;
+
+        buf.write(&format!(
+            "{CRATE}::filters::safe(&_escaper, {CRATE}::filters::{name}("
+        ));
+        match wrap {
+            DisplayWrap::Wrapped => buf.write("&("),
+            DisplayWrap::Unwrapped => buf.write(&format!("{CRATE}::filters::escape(&_escaper, &(")),
+        }
+        buf.write(&text_buf.buf);
+        match wrap {
+            DisplayWrap::Wrapped => buf.write(")"),
+            DisplayWrap::Unwrapped => buf.write("))?"),
+        }
+        buf.write(")?)?");
+        Ok(DisplayWrap::Wrapped)
+    }
+
+    fn _visit_escape_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if args.len() > 2 {
+            return Err("only two arguments allowed to escape filter".into());
+        }
+        let opt_escaper = match args.get(1) {
+            Some(Expr::StrLit(name)) => Some(*name),
+            Some(_) => return Err("invalid escaper type for escape filter".into()),
+            None => None,
+        };
+        let escapers = &self.input.config.escapers;
+        let escaper = match opt_escaper {
+            Some(name) => escapers
+                .iter()
+                .find_map(|(escapers, escaper)| escapers.contains(name).then_some(escaper))
+                .ok_or_else(|| {
+                    let mut names = escapers
+                        .iter()
+                        .flat_map(|(names, _)| names)
+                        .filter(|name| !name.is_empty())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    names.dedup();
+                    let names = names
+                        .iter()
+                        .map(|name| format!("`{name}`"))
+                        .collect::<Vec<_>>();
+                    CompileError::from(format!(
+                        "invalid escaper `{name}` for escape filter, the configured escapers \
+                         are {}",
+                        names.join(", "),
+                    ))
+                })?,
+            None => "&_escaper",
+        };
+        buf.write(CRATE);
+        buf.write("::filters::escape(");
+        buf.write(escaper);
+        buf.write(", ");
+        self._visit_args(buf, &args[..1])?;
+        buf.write(")?");
+        Ok(DisplayWrap::Wrapped)
+    }
+
+    fn _visit_format_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let fmt = match args {
+            [Expr::StrLit(fmt), ..] => fmt,
+            _ => return Err(r#"use filter format like `"a={} b={}"|format(a, b)`"#.into()),
+        };
+        buf.write("::std::format!(");
+        self.visit_str_lit(buf, fmt);
+        if args.len() > 1 {
+            buf.write(", ");
+            self._visit_args(buf, &args[1..])?;
+        }
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_default_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value_and_fallback, optional) = match args {
+            [_, _, optional @ ..] if optional.len() <= 2 => args.split_at(2),
+            _ => {
+                return Err("use filter default like `value|default(\"N/A\")`, \
+                     `value|default(\"N/A\", strip=true)` or `value|default(0, boolean=true)`"
+                    .into())
+            }
+        };
+        let (mut strip, mut boolean) = (None, None);
+        for (i, arg) in optional.iter().enumerate() {
+            match (i, arg) {
+                (_, Expr::NamedArgument("strip", arg)) => strip = Some(&**arg),
+                (_, Expr::NamedArgument("boolean", arg)) => boolean = Some(&**arg),
+                (_, Expr::NamedArgument(name, _)) => {
+                    return Err(format!("filter `default` has no argument named `{name}`").into());
+                }
+                (0, arg) => strip = Some(arg),
+                (_, arg) => boolean = Some(arg),
+            }
+        }
+
+        buf.write(&format!("{CRATE}::filters::default("));
+        self._visit_args(buf, value_and_fallback)?;
+        for flag in [strip, boolean] {
+            buf.write(", ");
+            match flag {
+                Some(flag) => {
+                    self.visit_expr(buf, flag)?;
+                }
+                None => buf.write("false"),
+            }
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_numberformat_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [number, optional @ ..] = args else {
+            return Err("filter `numberformat` expects a number".into());
+        };
+        if optional.len() > 3 {
+            let msg = "filter `numberformat` expects optionally a number of decimals, a decimal \
+                       separator and a thousands separator";
+            return Err(msg.into());
+        }
+
+        buf.write(&format!("{CRATE}::filters::numberformat("));
+        self._visit_args(buf, std::slice::from_ref(number))?;
+        match optional.first() {
+            Some(decimals) => {
+                buf.write(", ::std::option::Option::Some(");
+                self.visit_expr(buf, decimals)?;
+                buf.write(")");
+            }
+            None => buf.write(", ::std::option::Option::None"),
+        }
+        for (i, default) in [(1, "\".\""), (2, "\",\"")] {
+            buf.write(", ");
+            match optional.get(i) {
+                Some(sep) => self._visit_args(buf, std::slice::from_ref(sep))?,
+                None => buf.write(default),
+            }
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_pluralize_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (count, suffixes) = match args {
+            [count] => (count, None),
+            [count, plural] => (count, Some((None, plural))),
+            [count, singular, plural] => (count, Some((Some(singular), plural))),
+            _ => {
+                return Err("use filter pluralize like `count|pluralize`, \
+                     `count|pluralize(\"es\")` or `count|pluralize(\"y\", \"ies\")`"
+                    .into())
+            }
+        };
+        buf.write(&format!("{CRATE}::filters::pluralize("));
+        self._visit_args(buf, std::slice::from_ref(count))?;
+        match suffixes {
+            Some((singular, plural)) => {
+                buf.write(", ");
+                match singular {
+                    Some(singular) => self._visit_args(buf, std::slice::from_ref(singular))?,
+                    None => buf.write("\"\""),
+                }
+                buf.write(", ");
+                self._visit_args(buf, std::slice::from_ref(plural))?;
+            }
+            None => buf.write(", \"\", \"s\""),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_format_float_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let trim = match args {
+            [_, _] => None,
+            [_, _, Expr::NamedArgument("trim", trim)] => Some(&**trim),
+            [_, _, trim] if !matches!(trim, Expr::NamedArgument(..)) => Some(trim),
+            _ => {
+                return Err("use filter format_float like `value|format_float(2)` or \
+                     `value|format_float(2, trim=true)`"
+                    .into())
+            }
+        };
+        buf.write(&format!("{CRATE}::filters::format_float("));
+        self._visit_args(buf, &args[..2])?;
+        buf.write(", ");
+        match trim {
+            Some(trim) => self._visit_args(buf, std::slice::from_ref(trim))?,
+            None => buf.write("false"),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_hex_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let upper = match args {
+            [_] => None,
+            [_, Expr::NamedArgument("upper", upper)] => Some(&**upper),
+            [_, upper] if !matches!(upper, Expr::NamedArgument(..)) => Some(upper),
+            _ => return Err("use filter hex like `value|hex` or `value|hex(upper=true)`".into()),
+        };
+        buf.write(&format!("{CRATE}::filters::hex("));
+        self._visit_args(buf, &args[..1])?;
+        buf.write(", ");
+        match upper {
+            Some(upper) => self._visit_args(buf, std::slice::from_ref(upper))?,
+            None => buf.write("false"),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_replace_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let count = match args {
+            [_, _, _] => None,
+            [_, _, _, Expr::NamedArgument("count", count)] => Some(&**count),
+            [_, _, _, count] if !matches!(count, Expr::NamedArgument(..)) => Some(count),
+            _ => {
+                let msg = "filter `replace` expects the text to replace, the replacement and \
+                           optionally a maximum count";
+                return Err(msg.into());
+            }
+        };
+        buf.write(&format!("{CRATE}::filters::replace("));
+        self._visit_args(buf, &args[..3])?;
+        match count {
+            Some(count) => {
+                buf.write(", ::std::option::Option::Some(");
+                self.visit_expr(buf, count)?;
+                buf.write(")");
+            }
+            None => buf.write(", ::std::option::Option::None"),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_round_filter(
+        &mut self,
+        buf: &mut Buffer,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let [number, optional @ ..] = args else {
+            return Err("filter `round` expects a number".into());
+        };
+        if optional.len() > 2 {
+            let msg = "filter `round` expects optionally a `precision` and a `method`";
+            return Err(msg.into());
+        }
+        let (mut precision, mut method) = (None, None);
+        for (i, arg) in optional.iter().enumerate() {
+            match (i, arg) {
+                (_, Expr::NamedArgument("precision", arg)) => precision = Some(&**arg),
+                (_, Expr::NamedArgument("method", arg)) => method = Some(&**arg),
+                (_, Expr::NamedArgument(name, _)) => {
+                    return Err(format!("filter `round` has no argument named `{name}`").into());
+                }
+                (0, arg) => precision = Some(arg),
+                (_, arg) => method = Some(arg),
+            }
+        }
+        if let Some(Expr::StrLit(method)) = method {
+            if !matches!(*method, "common" | "ceil" | "floor") {
+                let msg = format!(
+                    "unknown method `{method}` for filter `round`, \
+                     expected `common`, `ceil` or `floor`"
+                );
+                return Err(msg.into());
+            }
+        }
+
+        buf.write(&format!("{CRATE}::filters::round("));
+        self._visit_args(buf, std::slice::from_ref(number))?;
+        buf.write(", ");
+        match precision {
+            Some(precision) => {
+                self.visit_expr(buf, precision)?;
+            }
+            None => buf.write("0"),
+        }
+        buf.write(", ");
+        match method {
+            Some(method) => self._visit_args(buf, std::slice::from_ref(method))?,
+            None => buf.write("\"common\""),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `value|trim` has its own implementation, which strips whitespace while formatting.
+    fn _visit_trim_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        let (value, chars) = match args {
+            [value] => (value, None),
+            [value, chars] => (value, Some(chars)),
+            _ => {
+                let msg = format!(
+                    "use filter {name} like `value|{name}` or `value|{name}(\"/\")` to strip \
+                     the given characters"
+                );
+                return Err(msg.into());
+            }
+        };
+        match name {
+            "trim" => buf.write(&format!("{CRATE}::filters::trim_chars(")),
+            _ => buf.write(&format!("{CRATE}::filters::{name}(")),
+        }
+        self._visit_args(buf, std::slice::from_ref(value))?;
+        buf.write(", ");
+        match chars {
+            Some(chars) => self._visit_args(buf, std::slice::from_ref(chars))?,
+            None => buf.write("\"\""),
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_truncate_filter(
+        &mut self,
+        buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        match name {
-            "as_ref" => return self._visit_as_ref_filter(buf, args),
-            "deref" => return self._visit_deref_filter(buf, args),
-            "escape" | "e" => return self._visit_escape_filter(buf, args),
-            "fmt" => return self._visit_fmt_filter(buf, args),
-            "format" => return self._visit_format_filter(buf, args),
-            "join" => return self._visit_join_filter(buf, args),
-            "json" | "tojson" => return self._visit_json_filter(buf, args),
-            "safe" => return self._visit_safe_filter(buf, args),
-            _ => {}
+        let (value_and_length, optional) = match args {
+            [_, _, optional @ ..] if optional.len() <= 2 => args.split_at(2),
+            _ => {
+                let msg = "filter `truncate` expects a length, and optionally `killwords` and \
+                           an `ellipsis`";
+                return Err(msg.into());
+            }
+        };
+        let (mut killwords, mut ellipsis) = (None, None);
+        for (i, arg) in optional.iter().enumerate() {
+            match (i, arg) {
+                (_, Expr::NamedArgument("killwords", arg)) => killwords = Some(&**arg),
+                (_, Expr::NamedArgument("ellipsis", arg)) => ellipsis = Some(&**arg),
+                (_, Expr::NamedArgument(name, _)) => {
+                    return Err(format!("filter `truncate` has no argument named `{name}`").into());
+                }
+                (0, arg) => killwords = Some(arg),
+                (_, arg) => ellipsis = Some(arg),
+            }
         }
 
-        if crate::BUILT_IN_FILTERS.contains(&name) {
-            buf.write(&format!("{CRATE}::filters::{name}("));
-        } else {
-            buf.write(&format!("filters::{name}("));
+        buf.write(&format!("{CRATE}::filters::truncate_words("));
+        self._visit_args(buf, value_and_length)?;
+        buf.write(", ");
+        match killwords {
+            Some(killwords) => {
+                self.visit_expr(buf, killwords)?;
+            }
+            None => buf.write("false"),
+        }
+        buf.write(", ");
+        match ellipsis {
+            Some(ellipsis) => self._visit_args(buf, std::slice::from_ref(ellipsis))?,
+            None => buf.write("\"…\""),
         }
-        self._visit_args(buf, args)?;
         buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_as_ref_filter(
+    // `urlencode` without the argument uses a static set of characters.
+    fn _visit_urlencode_filter(
         &mut self,
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        let arg = match args {
-            [arg] => arg,
-            _ => return Err("unexpected argument(s) in `as_ref` filter".into()),
-        };
-        buf.write("&");
-        self.visit_expr(buf, arg)?;
+        if let [_, Expr::StrLit(safe)] = args {
+            if !safe.is_ascii() {
+                let msg = format!("the safe characters of `urlencode` must be ASCII, got `{safe}`");
+                return Err(msg.into());
+            }
+        }
+        buf.write(&format!("{CRATE}::filters::urlencode_with_safe("));
+        self._visit_args(buf, args)?;
+        buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_deref_filter(
+    fn _visit_wordwrap_filter(
         &mut self,
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        let arg = match args {
-            [arg] => arg,
-            _ => return Err("unexpected argument(s) in `deref` filter".into()),
+        let [text, optional @ ..] = args else {
+            return Err("filter `wordwrap` expects a text".into());
         };
-        buf.write("*");
-        self.visit_expr(buf, arg)?;
+        if optional.len() > 2 {
+            let msg = "filter `wordwrap` expects optionally a `width` and `break_long_words`";
+            return Err(msg.into());
+        }
+        let (mut width, mut break_long_words) = (None, None);
+        for (i, arg) in optional.iter().enumerate() {
+            match (i, arg) {
+                (_, Expr::NamedArgument("width", arg)) => width = Some(&**arg),
+                (_, Expr::NamedArgument("break_long_words", arg)) => {
+                    break_long_words = Some(&**arg);
+                }
+                (_, Expr::NamedArgument(name, _)) => {
+                    return Err(format!("filter `wordwrap` has no argument named `{name}`").into());
+                }
+                (0, arg) => width = Some(arg),
+                (_, arg) => break_long_words = Some(arg),
+            }
+        }
+
+        buf.write(&format!("{CRATE}::filters::wordwrap("));
+        self._visit_args(buf, std::slice::from_ref(text))?;
+        for (arg, default) in [(width, "79"), (break_long_words, "false")] {
+            buf.write(", ");
+            match arg {
+                Some(arg) => {
+                    self.visit_expr(buf, arg)?;
+                }
+                None => buf.write(default),
+            }
+        }
+        buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_json_filter(
+    fn _visit_capitalize_filter(
         &mut self,
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        if cfg!(not(feature = "serde-json")) {
-            return Err("the `json` filter requires the `serde-json` feature to be enabled".into());
-        }
+        let lower_rest = match args {
+            [_] => None,
+            [_, Expr::NamedArgument("lower_rest", arg)] => Some(&**arg),
+            [_, Expr::NamedArgument(name, _)] => {
+                return Err(format!("filter `capitalize` has no argument named `{name}`").into());
+            }
+            [_, arg] => Some(arg),
+            _ => {
+                let msg = "filter `capitalize` expects optionally a `lower_rest` argument";
+                return Err(msg.into());
+            }
+        };
 
-        if args.len() != 1 {
-            return Err("unexpected argument(s) in `json` filter".into());
+        match lower_rest {
+            Some(arg) => {
+                buf.write(&format!("{CRATE}::filters::capitalize_with("));
+                self._visit_args(buf, &args[..1])?;
+                buf.write(", ");
+                self.visit_expr(buf, arg)?;
+            }
+            None => {
+                buf.write(&format!("{CRATE}::filters::capitalize("));
+                self._visit_args(buf, &args[..1])?;
+            }
         }
-        buf.write(CRATE);
-        buf.write("::filters::json(");
-        self._visit_args(buf, args)?;
         buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_safe_filter(
+    fn _visit_filesizeformat_filter(
         &mut self,
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        if args.len() != 1 {
-            return Err("unexpected argument(s) in `safe` filter".into());
+        let binary = match args {
+            [_] => None,
+            [_, Expr::NamedArgument("binary", arg)] => Some(&**arg),
+            [_, Expr::NamedArgument(name, _)] => {
+                let msg = format!("filter `filesizeformat` has no argument named `{name}`");
+                return Err(msg.into());
+            }
+            [_, arg] => Some(arg),
+            _ => {
+                return Err("filter `filesizeformat` expects optionally a `binary` argument".into())
+            }
+        };
+
+        match binary {
+            Some(arg) => {
+                buf.write(&format!("{CRATE}::filters::filesizeformat_with("));
+                self._visit_args(buf, &args[..1])?;
+                buf.write(", ");
+                self.visit_expr(buf, arg)?;
+            }
+            None => {
+                buf.write(&format!("{CRATE}::filters::filesizeformat("));
+                self._visit_args(buf, &args[..1])?;
+            }
         }
-        buf.write(CRATE);
-        buf.write("::filters::safe(");
-        buf.write(self.input.escaper);
-        buf.write(", ");
-        self._visit_args(buf, args)?;
         buf.write(")?");
-        Ok(DisplayWrap::Wrapped)
+        Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_escape_filter(
+    fn _visit_indent_filter(
         &mut self,
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        if args.len() > 2 {
-            return Err("only two arguments allowed to escape filter".into());
-        }
-        let opt_escaper = match args.get(1) {
-            Some(Expr::StrLit(name)) => Some(*name),
-            Some(_) => return Err("invalid escaper type for escape filter".into()),
-            None => None,
-        };
-        let escaper = match opt_escaper {
-            Some(name) => self
-                .input
-                .config
-                .escapers
-                .iter()
-                .find_map(|(escapers, escaper)| escapers.contains(name).then_some(escaper))
-                .ok_or_else(|| CompileError::from("invalid escaper for escape filter"))?,
-            None => self.input.escaper,
+        let [_, indent, optional @ ..] = args else {
+            return Err("filter `indent` expects a width or an indentation string".into());
         };
-        buf.write(CRATE);
-        buf.write("::filters::escape(");
-        buf.write(escaper);
-        buf.write(", ");
-        self._visit_args(buf, &args[..1])?;
+        if matches!(indent, Expr::NamedArgument(..)) || optional.len() > 2 {
+            let msg = "use filter indent like `text|indent(4)` or \
+                       `text|indent(\"\\t\", first=true, blank=true)`";
+            return Err(msg.into());
+        }
+        let (mut first, mut blank) = (None, None);
+        for (i, arg) in optional.iter().enumerate() {
+            match (i, arg) {
+                (_, Expr::NamedArgument("first", arg)) => first = Some(&**arg),
+                (_, Expr::NamedArgument("blank", arg)) => blank = Some(&**arg),
+                (_, Expr::NamedArgument(name, _)) => {
+                    return Err(format!("filter `indent` has no argument named `{name}`").into());
+                }
+                (0, arg) => first = Some(arg),
+                (_, arg) => blank = Some(arg),
+            }
+        }
+
+        buf.write(&format!("{CRATE}::filters::indent_with("));
+        self._visit_args(buf, &args[..2])?;
+        for arg in [first, blank] {
+            buf.write(", ");
+            match arg {
+                Some(arg) => {
+                    self.visit_expr(buf, arg)?;
+                }
+                None => buf.write("false"),
+            }
+        }
         buf.write(")?");
-        Ok(DisplayWrap::Wrapped)
+        Ok(DisplayWrap::Unwrapped)
     }
 
-    fn _visit_format_filter(
+    // Locale-dependent filters are implemented by the configured `LocaleFormatter`.
+    fn _visit_locale_filter(
         &mut self,
         buf: &mut Buffer,
+        name: &str,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
-        let fmt = match args {
-            [Expr::StrLit(fmt), ..] => fmt,
-            _ => return Err(r#"use filter format like `"a={} b={}"|format(a, b)`"#.into()),
-        };
-        buf.write("::std::format!(");
-        self.visit_str_lit(buf, fmt);
-        if args.len() > 1 {
-            buf.write(", ");
-            self._visit_args(buf, &args[1..])?;
+        match (name, args.len()) {
+            ("thousands", 1) | ("currency", 2) => {}
+            ("thousands", _) => return Err("use filter thousands like `value|thousands`".into()),
+            _ => return Err(r#"use filter currency like `value|currency("EUR")`"#.into()),
         }
-        buf.write(")");
+        let formatter = &self.input.config.formatter;
+        buf.write(&format!(
+            "<{formatter} as {CRATE}::LocaleFormatter>::{name}("
+        ));
+        self._visit_args(buf, args)?;
+        buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
     }
 
@@ -1407,16 +3229,90 @@ impl<'a> Generator<'a> {
         buf: &mut Buffer,
         args: &[Expr<'_>],
     ) -> Result<DisplayWrap, CompileError> {
+        if let [iter, separator, attribute] = args {
+            buf.write(&format!("{CRATE}::filters::join("));
+            self._visit_attribute_iterable(buf, "join", iter, attribute)?;
+            buf.write(", &");
+            self.visit_expr(buf, separator)?;
+            buf.write(")?");
+            return Ok(DisplayWrap::Unwrapped);
+        }
+
+        if let Some(Expr::NamedArgument(name, _)) = args.last() {
+            return Err(format!("filter `join` has no argument named `{name}`").into());
+        }
         buf.write(CRATE);
-        buf.write("::filters::join((&");
+        buf.write("::filters::join(");
         for (i, arg) in args.iter().enumerate() {
-            if i > 0 {
-                buf.write(", &");
+            match i {
+                // Iterators, e.g. `users|map("name")`, are passed by value.
+                0 if matches!(arg, Expr::Filter(_)) => {
+                    self.visit_expr(buf, arg)?;
+                }
+                0 => {
+                    buf.write("(&");
+                    self.visit_expr(buf, arg)?;
+                    buf.write(").into_iter()");
+                }
+                _ => {
+                    buf.write(", &");
+                    self.visit_expr(buf, arg)?;
+                }
             }
-            self.visit_expr(buf, arg)?;
-            if i == 0 {
-                buf.write(").into_iter()");
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // Writes an iterator over `&__item.PATH` of the elements of `iter`, for filters with an
+    // attribute argument, e.g. `users|join(", ", "name")` or `products|sum("price")`.
+    fn _visit_attribute_iterable(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        iter: &Expr<'_>,
+        attribute: &Expr<'_>,
+    ) -> Result<(), CompileError> {
+        let path = match attribute {
+            Expr::StrLit(path) => Some(*path),
+            Expr::NamedArgument("attribute", path) => match **path {
+                Expr::StrLit(path) => Some(path),
+                _ => None,
+            },
+            Expr::NamedArgument(arg, _) => {
+                return Err(format!("filter `{name}` has no argument named `{arg}`").into());
             }
+            _ => None,
+        };
+        let path = path.ok_or_else(|| {
+            format!(
+                "the attribute of `{name}` filter must be a field name or path as string literal"
+            )
+        })?;
+        let fields = split_field_path(path)
+            .ok_or_else(|| format!("invalid field path `{path}` in `{name}` filter"))?;
+
+        buf.write("::std::iter::Iterator::map(::std::iter::IntoIterator::into_iter(");
+        self._visit_iterable(buf, iter)?;
+        buf.write(&format!("), |__item| &__item.{fields})"));
+        Ok(())
+    }
+
+    // `prices|sum` or, with an attribute, `products|sum("price")`.
+    fn _visit_aggregate_filter(
+        &mut self,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[Expr<'_>],
+    ) -> Result<DisplayWrap, CompileError> {
+        if args.len() > 2 {
+            let msg = format!("filter `{name}` expects optionally a field name or path");
+            return Err(msg.into());
+        }
+        buf.write(&format!("{CRATE}::filters::{name}("));
+        match args {
+            [iter, attribute] => self._visit_attribute_iterable(buf, name, iter, attribute)?,
+            _ => self._visit_iterable(buf, &args[0])?,
         }
         buf.write(")?");
         Ok(DisplayWrap::Unwrapped)
@@ -1475,6 +3371,10 @@ impl<'a> Generator<'a> {
                 } else if attr == "last" {
                     buf.write("_loop_item.last");
                     return Ok(DisplayWrap::Unwrapped);
+                } else if attr == "length" {
+                    self.uses_loop_length = true;
+                    buf.write("_loop_length");
+                    return Ok(DisplayWrap::Unwrapped);
                 } else {
                     return Err("unknown loop variable".into());
                 }
@@ -1533,7 +3433,11 @@ impl<'a> Generator<'a> {
                 match left {
                     Expr::Var(name) => match self.locals.resolve(name) {
                         Some(resolved) => buf.write(&resolved),
-                        None => buf.write(&format!("(&self.{})", normalize_identifier(name))),
+                        None if self.isolated => return Err(self.not_passed_error(name)),
+                        None => {
+                            self.check_not_hidden(name)?;
+                            buf.write(&format!("(&self.{})", normalize_identifier(name)));
+                        }
                     },
                     left => {
                         self.visit_expr(buf, left)?;
@@ -1583,12 +3487,67 @@ impl<'a> Generator<'a> {
         left: &Expr<'_>,
         right: &Expr<'_>,
     ) -> Result<DisplayWrap, CompileError> {
+        if op == "~" {
+            return self.visit_concat(buf, left, right);
+        }
         self.visit_expr(buf, left)?;
         buf.write(&format!(" {op} "));
         self.visit_expr(buf, right)?;
         Ok(DisplayWrap::Unwrapped)
     }
 
+    // Formats all operands of a chain of `~` operators into one `String`, which gets escaped
+    // as a whole.
+    fn visit_concat(
+        &mut self,
+        buf: &mut Buffer,
+        left: &Expr<'_>,
+        right: &Expr<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let operands = concat_operands(left, right);
+        buf.write("::std::format!(\"");
+        buf.write(&"{}".repeat(operands.len()));
+        buf.write("\"");
+        for operand in operands {
+            buf.write(", &(");
+            self.visit_expr(buf, operand)?;
+            buf.write(")");
+        }
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // A concatenation which is written to the output escapes each of its operands on its own,
+    // so that `x|safe ~ y` does not escape `x`, and `x|e ~ y` does not escape `x` twice.
+    fn visit_concat_escaped(
+        &mut self,
+        buf: &mut Buffer,
+        left: &Expr<'_>,
+        right: &Expr<'_>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let operands = concat_operands(left, right);
+        buf.write("::std::format!(\"");
+        buf.write(&"{}".repeat(operands.len()));
+        buf.write("\"");
+        for operand in operands {
+            let mut operand_buf = Buffer::new(0);
+            match self.visit_expr(&mut operand_buf, operand)? {
+                DisplayWrap::Wrapped => {
+                    buf.write(", &(");
+                    buf.write(&operand_buf.buf);
+                    buf.write(")");
+                }
+                DisplayWrap::Unwrapped => {
+                    buf.write(&format!(", &{CRATE}::filters::escape(&_escaper, &("));
+                    buf.write(&operand_buf.buf);
+                    buf.write("))?");
+                }
+            }
+        }
+        buf.write(")");
+        Ok(DisplayWrap::Wrapped)
+    }
+
     fn visit_group(
         &mut self,
         buf: &mut Buffer,
@@ -1652,14 +3611,64 @@ impl<'a> Generator<'a> {
         DisplayWrap::Unwrapped
     }
 
-    fn visit_var(&mut self, buf: &mut Buffer, s: &str) -> DisplayWrap {
+    fn visit_var(&mut self, buf: &mut Buffer, s: &str) -> Result<DisplayWrap, CompileError> {
         if s == "self" {
+            if self.isolated {
+                return Err(self.not_passed_error(s));
+            }
             buf.write(s);
-            return DisplayWrap::Unwrapped;
+            return Ok(DisplayWrap::Unwrapped);
         }
 
-        buf.write(normalize_identifier(&self.locals.resolve_or_self(s)));
-        DisplayWrap::Unwrapped
+        buf.write(normalize_identifier(&self.resolve_or_self(s)?));
+        match self.locals.get(&Cow::Borrowed(normalize_identifier(s))) {
+            Some(meta) if meta.captured => Ok(DisplayWrap::Wrapped),
+            _ => Ok(DisplayWrap::Unwrapped),
+        }
+    }
+
+    // Resolves a variable, which is a field of the template struct if it is not a local one.
+    fn resolve_or_self(&self, name: &str) -> Result<String, CompileError> {
+        match self.isolated {
+            true => self
+                .locals
+                .resolve(name)
+                .ok_or_else(|| self.not_passed_error(name)),
+            false => {
+                self.check_not_hidden(name)?;
+                Ok(self.locals.resolve_or_self(name))
+            }
+        }
+    }
+
+    // In strict mode, the error about the unknown `name` also tells where it is in the templates.
+    fn name_error(&self, msg: String, name: &str) -> CompileError {
+        if self.input.config.strict {
+            error_at(self.contexts, msg, name)
+        } else {
+            msg.into()
+        }
+    }
+
+    // Returns an error if `name` is a variable of the scope calling a macro with `uses`, which
+    // the macro did not declare.
+    fn check_not_hidden(&self, name: &str) -> Result<(), CompileError> {
+        let name = normalize_identifier(name);
+        match self.hidden_locals.get(name) {
+            Some(macro_name) if self.locals.resolve(name).is_none() => Err(format!(
+                "`{name}` is a variable of the scope calling macro {macro_name:?}, add it to \
+                 the `uses` of the macro to read it"
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn not_passed_error(&self, name: &str) -> CompileError {
+        CompileError::from(format!(
+            "`{name}` is not available in a template included with `with`, only the variables \
+             passed to it are"
+        ))
     }
 
     fn visit_generated(&mut self, buf: &mut Buffer, s: &str) -> DisplayWrap {
@@ -1882,7 +3891,17 @@ impl Buffer {
         self.indent += 1;
     }
 
+    // Appends the code generated in `other`, which must end at the current indentation.
+    fn append(&mut self, other: Buffer) {
+        debug_assert!(self.start);
+        self.buf.push_str(&other.buf);
+        self.indent = other.indent;
+    }
+
     fn dedent(&mut self) -> Result<(), CompileError> {
+        if self.discard {
+            return Ok(());
+        }
         if self.indent == 0 {
             return Err("dedent() called while indentation == 0".into());
         }
@@ -1895,6 +3914,9 @@ impl Buffer {
 pub(crate) struct LocalMeta {
     refs: Option<String>,
     initialized: bool,
+    /// The variable holds the output of a `{% set var %}...{% endset %}` block, which was
+    /// already escaped when it was rendered.
+    captured: bool,
 }
 
 impl LocalMeta {
@@ -1902,6 +3924,7 @@ impl LocalMeta {
         Self {
             refs: None,
             initialized: true,
+            captured: false,
         }
     }
 
@@ -1909,6 +3932,15 @@ impl LocalMeta {
         Self {
             refs: Some(refs),
             initialized: true,
+            captured: false,
+        }
+    }
+
+    fn captured() -> Self {
+        Self {
+            refs: None,
+            initialized: true,
+            captured: true,
         }
     }
 }
@@ -1984,6 +4016,25 @@ impl MapChain<'_, Cow<'_, str>, LocalMeta> {
         })
     }
 
+    /// Returns the names of all initialized variables in scope and the code to access them,
+    /// sorted by name.
+    fn visible(&self) -> Vec<(String, String)> {
+        let mut vars = BTreeMap::new();
+        let mut chain = Some(self);
+        while let Some(locals) = chain {
+            for scope in locals.scopes.iter().rev() {
+                for (name, meta) in scope {
+                    if meta.initialized && !vars.contains_key(name.as_ref()) {
+                        let code = meta.refs.clone().unwrap_or_else(|| name.to_string());
+                        vars.insert(name.to_string(), code);
+                    }
+                }
+            }
+            chain = locals.parent;
+        }
+        vars.into_iter().collect()
+    }
+
     fn resolve_or_self(&self, name: &str) -> String {
         let name = normalize_identifier(name);
         self.resolve(name).unwrap_or_else(|| format!("self.{name}"))
@@ -2012,7 +4063,10 @@ fn is_copyable_within_op(expr: &Expr<'_>, within_op: bool) -> bool {
         Expr::BinOp(_, lhs, rhs) => {
             is_copyable_within_op(lhs, true) && is_copyable_within_op(rhs, true)
         }
-        Expr::Range(..) => true,
+        Expr::Range(..) | Expr::Closure(..) | Expr::OptionalChain(..) => true,
+        Expr::IfElse(_, then, otherwise) => {
+            is_copyable_within_op(then, within_op) && is_copyable_within_op(otherwise, within_op)
+        }
         // The result of a call likely doesn't need to be borrowed,
         // as in that case the call is more likely to return a
         // reference in the first place then.
@@ -2039,7 +4093,10 @@ pub(crate) fn is_attr_self(expr: &Expr<'_>) -> bool {
 /// Returns `true` if the outcome of this expression may be used multiple times in the same
 /// `write!()` call, without evaluating the expression again, i.e. the expression should be
 /// side-effect free.
-pub(crate) fn is_cacheable(expr: &Expr<'_>) -> bool {
+///
+/// Calls are only considered pure with `pure_calls`, i.e. with the `dedup_exprs` option.
+pub(crate) fn is_cacheable(expr: &Expr<'_>, pure_calls: bool) -> bool {
+    let is_cacheable = |expr: &Expr<'_>| is_cacheable(expr, pure_calls);
     match expr {
         // Literals are the definition of pure:
         Expr::BoolLit(_) => true,
@@ -2061,16 +4118,99 @@ pub(crate) fn is_cacheable(expr: &Expr<'_>) -> bool {
                 && rhs.as_ref().map_or(true, |v| is_cacheable(v))
         }
         Expr::Group(arg) => is_cacheable(arg),
+        Expr::OptionalChain(obj, rest) => is_cacheable(obj) && is_cacheable(rest),
+        Expr::OptionalValue => true,
+        Expr::IfElse(cond, then, otherwise) => {
+            is_cacheable(cond) && is_cacheable(then) && is_cacheable(otherwise)
+        }
         Expr::Tuple(args) => args.iter().all(is_cacheable),
         Expr::NamedArgument(_, expr) => is_cacheable(expr),
+        Expr::Call(callee, args) if pure_calls => {
+            is_cacheable(callee) && args.iter().all(is_cacheable)
+        }
         // We have too little information to tell if the expression is pure:
         Expr::Call(_, _) => false,
         Expr::RustMacro(_, _) => false,
         Expr::Try(_) => false,
+        Expr::Closure(..) => false,
         Expr::Generated(_) => true,
     }
 }
 
+// Collects the calls on `self` with literal arguments written in `nodes` with `{{ }}`, with
+// whether they are written directly in `nodes`, and how many times they are written.
+fn collect_self_calls<'a>(
+    nodes: &'a [Node<'a>],
+    direct: bool,
+    calls: &mut Vec<(&'a Expr<'a>, bool, usize)>,
+) {
+    for node in nodes {
+        match node {
+            Node::Expr(_, expr @ Expr::Call(callee, args))
+                if is_attr_self(callee)
+                    && args.iter().all(|arg| {
+                        matches!(
+                            arg,
+                            Expr::BoolLit(_) | Expr::NumLit(_) | Expr::StrLit(_) | Expr::CharLit(_)
+                        ) || is_attr_self(arg)
+                    }) =>
+            {
+                match calls.iter_mut().find(|(call, ..)| *call == expr) {
+                    Some((_, found_direct, count)) => {
+                        *found_direct |= direct;
+                        *count += 1;
+                    }
+                    None => calls.push((expr, direct, 1)),
+                }
+            }
+            Node::If(i) => {
+                for cond in &i.branches {
+                    collect_self_calls(&cond.nodes, false, calls);
+                }
+            }
+            Node::Match(m) => {
+                for arm in &m.arms {
+                    collect_self_calls(&arm.nodes, false, calls);
+                }
+            }
+            Node::Loop(l) => {
+                collect_self_calls(&l.body, false, calls);
+                collect_self_calls(&l.else_nodes, false, calls);
+            }
+            Node::FilterBlock(f) => collect_self_calls(&f.nodes, false, calls),
+            Node::Autoescape(a) => collect_self_calls(&a.nodes, false, calls),
+            Node::Let(l) => {
+                if let Some(block) = &l.block {
+                    collect_self_calls(&block.nodes, false, calls);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Returns `true` if a `break` or `continue` in `nodes` belongs to the enclosing loop.
+fn has_loop_control(nodes: &[Node<'_>]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Break(_) | Node::Continue(_) => true,
+        Node::If(i) => i.branches.iter().any(|cond| has_loop_control(&cond.nodes)),
+        Node::Match(m) => m.arms.iter().any(|arm| has_loop_control(&arm.nodes)),
+        Node::BlockDef(b) => has_loop_control(&b.nodes),
+        Node::FilterBlock(f) => has_loop_control(&f.nodes),
+        Node::Autoescape(a) => has_loop_control(&a.nodes),
+        Node::Include(i) => i
+            .empty
+            .as_ref()
+            .map_or(false, |e| has_loop_control(&e.nodes)),
+        Node::Let(l) => l
+            .block
+            .as_ref()
+            .map_or(false, |b| has_loop_control(&b.nodes)),
+        // The `break` and `continue` tags in a nested loop belong to that loop.
+        _ => false,
+    })
+}
+
 fn median(sizes: &mut [usize]) -> usize {
     sizes.sort_unstable();
     if sizes.len() % 2 == 1 {
@@ -2120,6 +4260,34 @@ fn insert_first_filter_argument(args: &mut Vec<Expr<'_>>, var_name: String) {
     }
 }
 
+/// Turns a path of fields like `"address.city"` into the Rust code accessing them, or returns
+/// `None` if a segment is not a valid field name or tuple index
+fn split_field_path(path: &str) -> Option<String> {
+    fn is_valid(field: &str) -> bool {
+        match field.as_bytes() {
+            [] | [b'_'] => false,
+            [b'0'] => true,
+            [b'1'..=b'9', ..] => field.bytes().all(|c| c.is_ascii_digit()),
+            [b'a'..=b'z' | b'A'..=b'Z' | b'_', ..] => field
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'_'),
+            _ => false,
+        }
+    }
+
+    let mut fields = String::with_capacity(path.len());
+    for (i, field) in path.split('.').enumerate() {
+        if !is_valid(field) {
+            return None;
+        }
+        if i > 0 {
+            fields.push('.');
+        }
+        fields.push_str(normalize_identifier(field));
+    }
+    Some(fields)
+}
+
 #[derive(Clone, Copy, PartialEq)]
 enum AstLevel {
     Top,
@@ -2155,10 +4323,24 @@ impl<'a> Deref for WritableBuffer<'a> {
     }
 }
 
+// Returns the operands of `left ~ right`, in order: `a ~ b ~ c` is parsed as `(a ~ b) ~ c`.
+fn concat_operands<'a, 'b>(left: &'b Expr<'a>, right: &'b Expr<'a>) -> Vec<&'b Expr<'a>> {
+    let mut operands = vec![right];
+    let mut left = left;
+    while let Expr::BinOp("~", l, r) = left {
+        operands.push(r);
+        left = l;
+    }
+    operands.push(left);
+    operands.reverse();
+    operands
+}
+
 #[derive(Debug)]
 enum Writable<'a> {
     Lit(&'a str),
-    Expr(&'a Expr<'a>),
+    /// An expression, and whether it gets escaped
+    Expr(&'a Expr<'a>, bool),
     Generated(String, DisplayWrap),
 }
 
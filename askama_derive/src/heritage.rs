@@ -5,7 +5,7 @@ use std::rc::Rc;
 use crate::config::Config;
 use crate::CompileError;
 use parser::node::{BlockDef, Macro, Match};
-use parser::Node;
+use parser::{Expr, Node, Parsed};
 
 pub(crate) struct Heritage<'a> {
     pub(crate) root: &'a Context<'a>,
@@ -14,7 +14,17 @@ pub(crate) struct Heritage<'a> {
 
 impl Heritage<'_> {
     pub(crate) fn new<'n>(
+        ctx: &'n Context<'n>,
+        contexts: &'n HashMap<&'n Rc<Path>, Context<'n>>,
+    ) -> Heritage<'n> {
+        Self::with_parent(ctx, ctx.extends.as_ref(), contexts)
+    }
+
+    /// Builds the heritage of `ctx` as if it extended `parent`, e.g. one of the candidates of
+    /// `{% extends name in [...] %}`
+    pub(crate) fn with_parent<'n>(
         mut ctx: &'n Context<'n>,
+        mut parent: Option<&'n Rc<Path>>,
         contexts: &'n HashMap<&'n Rc<Path>, Context<'n>>,
     ) -> Heritage<'n> {
         let mut blocks: BlockAncestry<'n> = ctx
@@ -23,23 +33,141 @@ impl Heritage<'_> {
             .map(|(name, def)| (*name, vec![(ctx, *def)]))
             .collect();
 
-        while let Some(path) = &ctx.extends {
+        while let Some(path) = parent {
             ctx = &contexts[path];
             for (name, def) in &ctx.blocks {
                 blocks.entry(name).or_default().push((ctx, def));
             }
+            parent = ctx.extends.as_ref();
         }
 
         Heritage { root: ctx, blocks }
     }
 }
 
+/// The candidate parent templates of `{% extends name in ["a.html", "b.html"] %}`, and the
+/// expression selecting one of them
+#[derive(Clone)]
+pub(crate) struct DynamicExtends<'a> {
+    pub(crate) selector: &'a Expr<'a>,
+    pub(crate) parents: Vec<(&'a str, Rc<Path>)>,
+}
+
+/// Checks that blocks which override a block keep its parameters.
+///
+/// If `strict`, also checks that every top-level block of a child template overrides a block
+/// defined in one of its parent templates. Otherwise the block would silently never be rendered.
+pub(crate) fn check_block_overrides<'a>(
+    path: &'a Rc<Path>,
+    contexts: &'a HashMap<&'a Rc<Path>, Context<'a>>,
+    strict: bool,
+) -> Result<(), CompileError> {
+    let ctx = &contexts[path];
+    match &ctx.dynamic_extends {
+        Some(dynamic) => dynamic.parents.iter().try_for_each(|(_, parent)| {
+            check_block_overrides_from(path, Some(parent), contexts, strict)
+        }),
+        None => check_block_overrides_from(path, ctx.extends.as_ref(), contexts, strict),
+    }
+}
+
+fn check_block_overrides_from<'a>(
+    mut path: &'a Rc<Path>,
+    mut extends: Option<&'a Rc<Path>>,
+    contexts: &'a HashMap<&'a Rc<Path>, Context<'a>>,
+    strict: bool,
+) -> Result<(), CompileError> {
+    let mut ctx = &contexts[path];
+    while let Some(parent) = extends {
+        for node in ctx.nodes {
+            let Node::BlockDef(block) = node else {
+                continue;
+            };
+            if strict && overridden_block(parent, block.name, contexts).is_none() {
+                let msg = format!(
+                    "block {:?} of template {:?} is not defined in any parent template",
+                    block.name,
+                    path.file_name().unwrap_or_default(),
+                );
+                return Err(error_at(contexts, msg, block.name));
+            }
+        }
+        for block in ctx.blocks.values() {
+            let Some((parent_path, overridden)) = overridden_block(parent, block.name, contexts)
+            else {
+                continue;
+            };
+            if !block
+                .args
+                .iter()
+                .map(|(name, _)| name)
+                .eq(overridden.args.iter().map(|(name, _)| name))
+            {
+                return Err(format!(
+                    "block `{}` of template {:?} must keep the parameters of the block it \
+                     overrides, `{}` of template {:?}",
+                    block_signature(block),
+                    path.file_name().unwrap_or_default(),
+                    block_signature(overridden),
+                    parent_path.file_name().unwrap_or_default(),
+                )
+                .into());
+            }
+        }
+        path = parent;
+        ctx = &contexts[parent];
+        extends = ctx.extends.as_ref();
+    }
+    Ok(())
+}
+
+/// Finds the block named `name` in the template at `path` or in its parent templates
+fn overridden_block<'a>(
+    mut path: &'a Rc<Path>,
+    name: &str,
+    contexts: &'a HashMap<&'a Rc<Path>, Context<'a>>,
+) -> Option<(&'a Rc<Path>, &'a BlockDef<'a>)> {
+    loop {
+        let ctx = &contexts[path];
+        if let Some(block) = ctx.blocks.get(name) {
+            return Some((path, block));
+        }
+        path = ctx.extends.as_ref()?;
+    }
+}
+
+/// Adds to `msg` where `at`, which must be a slice of the source of one of the templates, is found
+pub(crate) fn error_at(
+    contexts: &HashMap<&Rc<Path>, Context<'_>>,
+    msg: String,
+    at: &str,
+) -> CompileError {
+    contexts
+        .values()
+        .find_map(|ctx| ctx.parsed.located(&msg, at))
+        .unwrap_or(msg)
+        .into()
+}
+
+/// Formats the name of `block` with its parameters, e.g. `card(title, level)`
+fn block_signature(block: &BlockDef<'_>) -> String {
+    match block.args.is_empty() {
+        true => block.name.to_owned(),
+        false => {
+            let args = block.args.iter().map(|(name, _)| *name);
+            format!("{}({})", block.name, args.collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
 type BlockAncestry<'a> = HashMap<&'a str, Vec<(&'a Context<'a>, &'a BlockDef<'a>)>>;
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub(crate) struct Context<'a> {
+    pub(crate) parsed: &'a Parsed,
     pub(crate) nodes: &'a [Node<'a>],
     pub(crate) extends: Option<Rc<Path>>,
+    pub(crate) dynamic_extends: Option<DynamicExtends<'a>>,
     pub(crate) blocks: HashMap<&'a str, &'a BlockDef<'a>>,
     pub(crate) macros: HashMap<&'a str, &'a Macro<'a>>,
     pub(crate) imports: HashMap<&'a str, Rc<Path>>,
@@ -49,9 +177,11 @@ impl Context<'_> {
     pub(crate) fn new<'n>(
         config: &Config<'_>,
         path: &Path,
-        nodes: &'n [Node<'n>],
+        parsed: &'n Parsed,
     ) -> Result<Context<'n>, CompileError> {
+        let nodes = parsed.nodes();
         let mut extends = None;
+        let mut dynamic_extends = None;
         let mut blocks = HashMap::new();
         let mut macros = HashMap::new();
         let mut imports = HashMap::new();
@@ -61,11 +191,19 @@ impl Context<'_> {
         while let Some(nodes) = nested.pop() {
             for n in nodes {
                 match n {
-                    Node::Extends(e) if top => match extends {
-                        Some(_) => return Err("multiple extend blocks found".into()),
-                        None => {
-                            extends = Some(config.find_template(e.path, Some(path))?);
+                    Node::Extends(_) if extends.is_some() || dynamic_extends.is_some() => {
+                        return Err("multiple extend blocks found".into());
+                    }
+                    Node::Extends(e) if top => match &e.selector {
+                        Some(selector) => {
+                            let parents = e
+                                .paths
+                                .iter()
+                                .map(|p| Ok((*p, config.find_template(p, Some(path))?)))
+                                .collect::<Result<_, CompileError>>()?;
+                            dynamic_extends = Some(DynamicExtends { selector, parents });
                         }
+                        None => extends = Some(config.find_template(e.paths[0], Some(path))?),
                     },
                     Node::Macro(m) if top => {
                         macros.insert(m.name, m);
@@ -97,6 +235,7 @@ impl Context<'_> {
                             nested.push(&arm.nodes);
                         }
                     }
+                    Node::Autoescape(a) => nested.push(&a.nodes),
                     _ => {}
                 }
             }
@@ -104,8 +243,10 @@ impl Context<'_> {
         }
 
         Ok(Context {
+            parsed,
             nodes,
             extends,
+            dynamic_extends,
             blocks,
             macros,
             imports,
@@ -140,17 +140,20 @@ impl TemplateInput<'_> {
 
                     match n {
                         Node::Extends(extends) if top => {
-                            let extends = self.config.find_template(extends.path, Some(&path))?;
-                            let dependency_path = (path.clone(), extends.clone());
-                            if path == extends {
-                                // We add the path into the graph to have a better looking error.
+                            for extends in &extends.paths {
+                                let extends = self.config.find_template(extends, Some(&path))?;
+                                let dependency_path = (path.clone(), extends.clone());
+                                if path == extends {
+                                    // We add the path into the graph to have a better looking
+                                    // error.
+                                    dependency_graph.push(dependency_path);
+                                    return cyclic_graph_error(&dependency_graph);
+                                } else if dependency_graph.contains(&dependency_path) {
+                                    return cyclic_graph_error(&dependency_graph);
+                                }
                                 dependency_graph.push(dependency_path);
-                                return cyclic_graph_error(&dependency_graph);
-                            } else if dependency_graph.contains(&dependency_path) {
-                                return cyclic_graph_error(&dependency_graph);
+                                add_to_check(extends)?;
                             }
-                            dependency_graph.push(dependency_path);
-                            add_to_check(extends)?;
                         }
                         Node::Macro(m) if top => {
                             nested.push(&m.nodes);
@@ -162,7 +165,18 @@ impl TemplateInput<'_> {
                         Node::FilterBlock(f) => {
                             nested.push(&f.nodes);
                         }
+                        Node::Autoescape(a) => {
+                            nested.push(&a.nodes);
+                        }
+                        Node::Let(l) => {
+                            if let Some(block) = &l.block {
+                                nested.push(&block.nodes);
+                            }
+                        }
                         Node::Include(include) => {
+                            if let Some(empty) = &include.empty {
+                                nested.push(&empty.nodes);
+                            }
                             let include = self.config.find_template(include.path, Some(&path))?;
                             add_to_check(include)?;
                         }
@@ -187,13 +201,14 @@ impl TemplateInput<'_> {
                         | Node::Comment(_)
                         | Node::Expr(_, _)
                         | Node::Call(_)
+                        | Node::Embed(_)
                         | Node::Extends(_)
-                        | Node::Let(_)
                         | Node::Import(_)
                         | Node::Macro(_)
                         | Node::Raw(_)
                         | Node::Continue(_)
-                        | Node::Break(_) => {}
+                        | Node::Break(_)
+                        | Node::Debug(_) => {}
                     }
                 }
                 top = false;
@@ -219,6 +234,7 @@ pub(crate) struct TemplateArgs {
     syntax: Option<String>,
     config: Option<String>,
     pub(crate) whitespace: Option<String>,
+    pub(crate) lint_a11y: Option<bool>,
 }
 
 impl TemplateArgs {
@@ -333,6 +349,12 @@ impl TemplateArgs {
                 } else {
                     return Err("whitespace value must be string literal".into());
                 }
+            } else if ident == "lint_a11y" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.lint_a11y = Some(b.value);
+                } else {
+                    return Err("lint_a11y value must be boolean literal".into());
+                }
             } else {
                 return Err(format!("unsupported attribute key {ident:?} found").into());
             }
@@ -385,6 +407,7 @@ pub(crate) enum Print {
     Ast,
     Code,
     None,
+    Stats,
 }
 
 impl FromStr for Print {
@@ -396,11 +419,13 @@ impl FromStr for Print {
             "ast" => Print::Ast,
             "code" => Print::Code,
             "none" => Print::None,
+            "stats" => Print::Stats,
             v => return Err(format!("invalid value for print option: {v}",).into()),
         })
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Print {
     fn default() -> Self {
         Self::None
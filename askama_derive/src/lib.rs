@@ -7,24 +7,34 @@ use std::{borrow::Cow, collections::HashMap};
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 
-use parser::ParseError;
+use parser::{ParseError, Parsed};
 
 mod config;
 use config::Config;
 mod generator;
 use generator::{Generator, MapChain};
 mod heritage;
-use heritage::{Context, Heritage};
+use heritage::{check_block_overrides, Context, Heritage};
 mod input;
 use input::{Print, TemplateArgs, TemplateInput};
+#[cfg(feature = "lint-a11y")]
+mod lint;
+mod stats;
 #[cfg(test)]
 mod tests;
 
 #[proc_macro_derive(Template, attributes(template))]
 pub fn derive_template(input: TokenStream) -> TokenStream {
     let ast = syn::parse::<syn::DeriveInput>(input).unwrap();
-    match build_template(&ast) {
-        Ok(source) => source.parse().unwrap(),
+    let mut warnings = Vec::new();
+    match build_template(&ast, &mut warnings) {
+        Ok(source) => {
+            let mut code: proc_macro2::TokenStream = source.parse().unwrap();
+            for warning in &warnings {
+                code.extend(compile_warning(&ast.ident, warning));
+            }
+            code.into()
+        }
         Err(e) => {
             let mut e = e.into_compile_error();
             if let Ok(source) = build_skeleton(&ast) {
@@ -40,8 +50,9 @@ fn build_skeleton(ast: &syn::DeriveInput) -> Result<String, CompileError> {
     let template_args = TemplateArgs::fallback();
     let config = Config::new("", None)?;
     let input = TemplateInput::new(ast, &config, &template_args)?;
+    let parsed = Parsed::default();
     let mut contexts = HashMap::new();
-    contexts.insert(&input.path, Context::default());
+    contexts.insert(&input.path, Context::new(&config, &input.path, &parsed)?);
     Generator::new(&input, &contexts, None, MapChain::default()).build(&contexts[&input.path])
 }
 
@@ -52,7 +63,10 @@ fn build_skeleton(ast: &syn::DeriveInput) -> Result<String, CompileError> {
 /// parsed, and the parse tree is fed to the code generator. Will print
 /// the parse tree and/or generated source according to the `print` key's
 /// value as passed to the `template()` attribute.
-pub(crate) fn build_template(ast: &syn::DeriveInput) -> Result<String, CompileError> {
+pub(crate) fn build_template(
+    ast: &syn::DeriveInput,
+    warnings: &mut Vec<String>,
+) -> Result<String, CompileError> {
     let template_args = TemplateArgs::new(ast)?;
     let toml = template_args.config()?;
     let config = Config::new(&toml, template_args.whitespace.as_deref())?;
@@ -63,16 +77,63 @@ pub(crate) fn build_template(ast: &syn::DeriveInput) -> Result<String, CompileEr
 
     let mut contexts = HashMap::new();
     for (path, parsed) in &templates {
-        contexts.insert(path, Context::new(input.config, path, parsed.nodes())?);
+        contexts.insert(path, Context::new(input.config, path, parsed)?);
+    }
+
+    check_block_overrides(&input.path, &contexts, input.config.strict)?;
+
+    for ctx in contexts.values() {
+        let dynamic = ctx.dynamic_extends.iter().flat_map(|d| d.parents.iter());
+        for parent in ctx.extends.iter().chain(dynamic.map(|(_, parent)| parent)) {
+            if contexts[parent].dynamic_extends.is_some() {
+                return Err(format!(
+                    "template {:?} selects its parent template at render time, so it cannot be \
+                     extended",
+                    parent.file_name().unwrap_or_default(),
+                )
+                .into());
+            }
+        }
     }
 
     let ctx = &contexts[&input.path];
-    let heritage = if !ctx.blocks.is_empty() || ctx.extends.is_some() {
+    if let (Some(dynamic), Some(block_name)) = (&ctx.dynamic_extends, input.block) {
+        for (name, parent) in &dynamic.parents {
+            let heritage = Heritage::with_parent(ctx, Some(parent), &contexts);
+            match heritage.blocks.get(&block_name) {
+                None => {
+                    return Err(
+                        format!("cannot find block {block_name} with parent {name:?}").into(),
+                    );
+                }
+                Some(defs) if !defs[0].1.args.is_empty() => {
+                    return Err(format!(
+                        "block {block_name:?} has parameters, so it cannot be rendered as a \
+                         block fragment"
+                    )
+                    .into());
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    let heritage = if ctx.dynamic_extends.is_some() {
+        // One heritage is built for each candidate parent by the generator.
+        None
+    } else if !ctx.blocks.is_empty() || ctx.extends.is_some() {
         let heritage = Heritage::new(ctx, &contexts);
 
         if let Some(block_name) = input.block {
-            if !heritage.blocks.contains_key(&block_name) {
-                return Err(format!("cannot find block {}", block_name).into());
+            match heritage.blocks.get(&block_name) {
+                None => return Err(format!("cannot find block {}", block_name).into()),
+                Some(defs) if !defs[0].1.args.is_empty() => {
+                    return Err(format!(
+                        "block {block_name:?} has parameters, so it cannot be rendered as a \
+                         block fragment"
+                    )
+                    .into());
+                }
+                Some(_) => {}
             }
         }
 
@@ -87,12 +148,34 @@ pub(crate) fn build_template(ast: &syn::DeriveInput) -> Result<String, CompileEr
 
     let code = Generator::new(&input, &contexts, heritage.as_ref(), MapChain::default())
         .build(&contexts[&input.path])?;
+    warnings.extend(input.config.warnings.take());
+    #[cfg(feature = "lint-a11y")]
+    if template_args.lint_a11y != Some(false) && input.mime_type.starts_with("text/html") {
+        warnings.extend(lint::check_templates(&templates));
+    }
     if input.print == Print::Code || input.print == Print::All {
         eprintln!("{code}");
     }
+    if input.print == Print::Stats {
+        stats::print_stats(&templates);
+    }
     Ok(code)
 }
 
+/// Makes the compiler emit `msg` as a warning pointing at `ident`.
+///
+/// Procedural macros cannot emit warnings on stable Rust, so the warning is the use of a
+/// deprecated item, with `msg` as the deprecation note.
+fn compile_warning(ident: &syn::Ident, msg: &str) -> proc_macro2::TokenStream {
+    quote::quote_spanned! {ident.span()=>
+        const _: () = {
+            #[deprecated(note = #msg)]
+            struct TemplateWarning;
+            let _ = TemplateWarning;
+        };
+    }
+}
+
 #[derive(Debug, Clone)]
 struct CompileError {
     msg: Cow<'static, str>,
@@ -150,32 +233,81 @@ impl From<String> for CompileError {
 // in the const vector based on features seems impossible right now).
 const BUILT_IN_FILTERS: &[&str] = &[
     "abs",
+    "batch",
+    "camel",
     "capitalize",
     "center",
+    "columns",
+    "currency",
+    "default",
     "e",
+    "enumerate",
     "escape",
     "filesizeformat",
+    "first",
     "fmt",
     "format",
+    "format_float",
+    "groupby",
+    "hang_indent",
+    "hex",
     "indent",
     "into_f64",
     "into_isize",
     "join",
+    "kebab",
+    "last",
     "linebreaks",
     "linebreaksbr",
     "paragraphbreaks",
+    "pascal",
+    "pluralize",
+    "prefix",
+    "reject",
+    "replace",
+    "round",
     "lower",
     "lowercase",
+    "map",
+    "max",
+    "min",
+    "nl2br",
+    "numberformat",
     "safe",
+    "select",
+    "skip",
+    "slugify",
+    "snake",
+    "sort",
+    "sort_by",
+    "spaceless",
+    "split",
+    "striptags",
+    "suffix",
+    "sum",
+    "take",
+    "thousands",
     "title",
+    "title_segments",
     "trim",
+    "trim_end",
+    "trim_start",
     "truncate",
+    "truncate_middle",
+    "unhex",
+    "unique",
     "upper",
     "uppercase",
     "urlencode",
     "urlencode_strict",
+    "windows",
     "wordcount",
+    "wordwrap",
+    "wrap",
+    "yesno",
+    "zip",
     // optional features, reserve the names anyway:
+    "date",
     "json",
 ];
 
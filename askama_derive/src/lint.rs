@@ -0,0 +1,293 @@
+//! Accessibility lints for the static HTML of templates, enabled with the `lint-a11y` feature.
+//!
+//! Only the literal parts of a template are inspected: everything generated at runtime is
+//! replaced by a placeholder, and any tag that contains one outside of a quoted attribute
+//! value is skipped, because its attributes cannot be known at compile time.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use parser::{Node, Parsed};
+
+/// Stands in for any content that is only known at runtime.
+const DYNAMIC: char = '\0';
+
+/// Returns the accessibility warnings for all `templates`, mentioning the file of each issue.
+pub(crate) fn check_templates(templates: &HashMap<Rc<Path>, Parsed>) -> Vec<String> {
+    let mut templates = templates.iter().collect::<Vec<_>>();
+    templates.sort_by_key(|(path, _)| *path);
+
+    let mut warnings = Vec::new();
+    for (path, parsed) in templates {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        for warning in check_a11y(parsed.nodes()) {
+            warnings.push(format!("accessibility: {warning} in template {name:?}"));
+        }
+    }
+    warnings
+}
+
+/// Returns the accessibility warnings for the literal HTML in `nodes`.
+pub(crate) fn check_a11y(nodes: &[Node<'_>]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    check_nodes(nodes, &mut warnings);
+    warnings
+}
+
+fn check_nodes<'a>(nodes: &'a [Node<'a>], warnings: &mut Vec<String>) {
+    let mut html = String::new();
+    let mut macros = Vec::new();
+    flatten(nodes, &mut html, &mut macros);
+    // The body of a macro is rendered where it is called, so it is checked on its own.
+    for body in macros {
+        check_nodes(body, warnings);
+    }
+
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some((name, attrs, after)) = tag(rest) else {
+            continue;
+        };
+        rest = after;
+        let Some(attrs) = attrs else {
+            continue;
+        };
+
+        if name.eq_ignore_ascii_case("img") {
+            if !has_attr(attrs, "alt") {
+                warnings.push("`<img>` without an `alt` attribute".to_owned());
+            }
+        } else if name.eq_ignore_ascii_case("a") {
+            let labelled = ["aria-label", "aria-labelledby", "title"]
+                .iter()
+                .any(|attr| has_attr(attrs, attr));
+            if !labelled && !has_text(link_content(after)) {
+                warnings.push("`<a>` without text or an `aria-label` attribute".to_owned());
+            }
+        }
+    }
+}
+
+/// Collects the literal text of `nodes` in source order, using [`DYNAMIC`] for everything else.
+fn flatten<'a>(nodes: &'a [Node<'a>], buf: &mut String, macros: &mut Vec<&'a [Node<'a>]>) {
+    for node in nodes {
+        match node {
+            Node::Lit(lit) => {
+                buf.push_str(lit.lws);
+                buf.push_str(lit.val);
+                buf.push_str(lit.rws);
+            }
+            Node::Raw(raw) => {
+                buf.push_str(raw.lit.lws);
+                buf.push_str(raw.lit.val);
+                buf.push_str(raw.lit.rws);
+            }
+            Node::If(i) => {
+                for cond in &i.branches {
+                    buf.push(DYNAMIC);
+                    flatten(&cond.nodes, buf, macros);
+                }
+                buf.push(DYNAMIC);
+            }
+            Node::Match(m) => {
+                for arm in &m.arms {
+                    buf.push(DYNAMIC);
+                    flatten(&arm.nodes, buf, macros);
+                }
+                buf.push(DYNAMIC);
+            }
+            Node::Loop(l) => {
+                buf.push(DYNAMIC);
+                flatten(&l.body, buf, macros);
+                buf.push(DYNAMIC);
+                flatten(&l.else_nodes, buf, macros);
+                buf.push(DYNAMIC);
+            }
+            Node::BlockDef(b) => {
+                buf.push(DYNAMIC);
+                flatten(&b.nodes, buf, macros);
+                buf.push(DYNAMIC);
+            }
+            Node::FilterBlock(f) => {
+                buf.push(DYNAMIC);
+                flatten(&f.nodes, buf, macros);
+                buf.push(DYNAMIC);
+            }
+            Node::Autoescape(a) => flatten(&a.nodes, buf, macros),
+            Node::Macro(m) => macros.push(&m.nodes),
+            Node::Comment(_)
+            | Node::Let(_)
+            | Node::Extends(_)
+            | Node::Import(_)
+            | Node::Break(_)
+            | Node::Continue(_)
+            | Node::Debug(_) => {}
+            Node::Expr(..) | Node::Call(_) | Node::Include(_) | Node::Embed(_) => buf.push(DYNAMIC),
+        }
+    }
+}
+
+/// Parses the tag starting after a `<`, returning its name, its attributes (or `None` if they
+/// contain dynamic content) and the remaining input after the closing `>`.
+fn tag(s: &str) -> Option<(&str, Option<&str>, &str)> {
+    let name_len = s
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(s.len());
+    if name_len == 0 {
+        return None;
+    }
+    let (name, s) = s.split_at(name_len);
+
+    let mut quote = None;
+    let mut dynamic = false;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => {
+                let attrs = (!dynamic).then_some(&s[..i]);
+                return Some((name, attrs, &s[i + 1..]));
+            }
+            (None, DYNAMIC) => dynamic = true,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns `true` if the attribute list contains an attribute called `name`.
+fn has_attr(attrs: &str, name: &str) -> bool {
+    let mut rest = attrs;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return false;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        if rest[..end].eq_ignore_ascii_case(name) {
+            return true;
+        }
+        rest = rest[end..].trim_start();
+
+        // Skip the attribute value, if any.
+        if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            rest = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => match value[1..].find(q) {
+                    Some(end) => &value[end + 2..],
+                    None => return false,
+                },
+                _ => {
+                    let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                    &value[end..]
+                }
+            };
+        }
+    }
+}
+
+/// Returns the content of a link up to its closing `</a>`.
+fn link_content(s: &str) -> &str {
+    let mut rest = s;
+    while let Some(start) = rest.find("</") {
+        let tail = &rest[start + 2..];
+        if let Some(tail) = tail.strip_prefix(|c| c == 'a' || c == 'A') {
+            if tail.trim_start().starts_with('>') {
+                return &s[..s.len() - rest.len() + start];
+            }
+        }
+        rest = tail;
+    }
+    s
+}
+
+/// Returns `true` if the content contains text (outside of tags), dynamic content, or an image
+/// with an `alt` text.
+fn has_text(content: &str) -> bool {
+    let mut rest = content;
+    loop {
+        let (text, tail) = match rest.find('<') {
+            Some(start) => (&rest[..start], Some(&rest[start + 1..])),
+            None => (rest, None),
+        };
+        if !text.trim().is_empty() {
+            return true;
+        }
+        let Some(tail) = tail else {
+            return false;
+        };
+        rest = match tag(tail) {
+            Some((name, attrs, after)) => {
+                let Some(attrs) = attrs else {
+                    return true;
+                };
+                if name.eq_ignore_ascii_case("img") && has_attr(attrs, "alt") {
+                    return true;
+                }
+                after
+            }
+            // A closing tag or comment.
+            None => match tail.find('>') {
+                Some(end) => &tail[end + 1..],
+                None => return false,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_a11y;
+    use parser::{Ast, Syntax};
+
+    fn check(src: &str) -> Vec<String> {
+        let syntax = Syntax::default();
+        let ast = Ast::from_str(src, None, &syntax).unwrap();
+        check_a11y(ast.nodes())
+    }
+
+    #[test]
+    fn test_img_alt() {
+        assert_eq!(
+            check(r#"<p><img src="logo.png"></p>"#),
+            ["`<img>` without an `alt` attribute"]
+        );
+        assert_eq!(
+            check(r#"<IMG SRC="{{ logo }}" />"#),
+            ["`<img>` without an `alt` attribute"]
+        );
+        assert!(check(r#"<img src="logo.png" alt="Logo">"#).is_empty());
+        assert!(check(r#"<img alt src="spacer.png">"#).is_empty());
+        assert!(check(r#"<img title="x > y" alt="">"#).is_empty());
+        assert!(check(r#"<img data-alt="missing" alt="">"#).is_empty());
+        // The attributes are not known at compile time.
+        assert!(check(r#"<img src="logo.png" {{ attrs }}>"#).is_empty());
+    }
+
+    #[test]
+    fn test_link_text() {
+        assert_eq!(
+            check(r#"<a href="/"></a>"#),
+            ["`<a>` without text or an `aria-label` attribute"]
+        );
+        assert_eq!(
+            check(r#"<a href="/"> <span class="icon"></span> </a>"#),
+            ["`<a>` without text or an `aria-label` attribute"]
+        );
+        assert!(check(r#"<a href="/">Home</a>"#).is_empty());
+        assert!(check(r#"<a href="/">{{ title }}</a>"#).is_empty());
+        assert!(check(r#"<a href="/" aria-label="Home"></a>"#).is_empty());
+        assert!(check(r#"<a href="/"><img src="home.png" alt="Home"></a>"#).is_empty());
+        assert!(
+            check(r#"<abbr title="x"></abbr><a href="/">{% if x %}Home{% endif %}</a>"#).is_empty()
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Size statistics of templates, printed with `print = "stats"`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use parser::{Node, Parsed};
+
+/// Counts of the nodes of a template that affect its rendering cost and binary size.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Stats {
+    /// Bytes of literal text, which end up in the binary.
+    pub(crate) static_bytes: usize,
+    /// Number of `{{ expr }}` expressions.
+    pub(crate) expressions: usize,
+    /// Number of `{% for %}` loops.
+    pub(crate) loops: usize,
+    /// Maximum nesting depth of blocks, `0` if the template contains no block.
+    pub(crate) max_depth: usize,
+}
+
+impl Stats {
+    pub(crate) fn new(nodes: &[Node<'_>]) -> Self {
+        let mut stats = Self::default();
+        stats.visit(nodes, 0);
+        stats
+    }
+
+    fn visit(&mut self, nodes: &[Node<'_>], depth: usize) {
+        for node in nodes {
+            let children: Vec<&[Node<'_>]> = match node {
+                Node::Lit(lit) => {
+                    self.static_bytes += lit.lws.len() + lit.val.len() + lit.rws.len();
+                    continue;
+                }
+                Node::Raw(raw) => {
+                    self.static_bytes += raw.lit.lws.len() + raw.lit.val.len() + raw.lit.rws.len();
+                    continue;
+                }
+                Node::Expr(..) => {
+                    self.expressions += 1;
+                    continue;
+                }
+                Node::Loop(l) => {
+                    self.loops += 1;
+                    vec![&l.body, &l.else_nodes]
+                }
+                Node::If(i) => i.branches.iter().map(|b| b.nodes.as_slice()).collect(),
+                Node::Match(m) => m.arms.iter().map(|a| a.nodes.as_slice()).collect(),
+                Node::BlockDef(b) => vec![&b.nodes],
+                Node::FilterBlock(f) => vec![&f.nodes],
+                Node::Autoescape(a) => vec![&a.nodes],
+                Node::Macro(m) => vec![&m.nodes],
+                Node::Let(l) => match &l.block {
+                    Some(block) => vec![&block.nodes],
+                    None => continue,
+                },
+                Node::Include(i) => match &i.empty {
+                    Some(empty) => vec![&empty.nodes],
+                    None => continue,
+                },
+                Node::Comment(_)
+                | Node::Call(_)
+                | Node::Extends(_)
+                | Node::Embed(_)
+                | Node::Import(_)
+                | Node::Break(_)
+                | Node::Continue(_)
+                | Node::Debug(_) => continue,
+            };
+            self.max_depth = self.max_depth.max(depth + 1);
+            for nodes in children {
+                self.visit(nodes, depth + 1);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "static_bytes={} expressions={} loops={} max_depth={}",
+            self.static_bytes, self.expressions, self.loops, self.max_depth,
+        )
+    }
+}
+
+/// Prints one line of statistics per template to stderr, in the order of their paths.
+pub(crate) fn print_stats(templates: &HashMap<Rc<Path>, Parsed>) {
+    let mut templates = templates.iter().collect::<Vec<_>>();
+    templates.sort_by_key(|(path, _)| *path);
+
+    for (path, parsed) in templates {
+        let stats = Stats::new(parsed.nodes());
+        eprintln!("askama-stats: template={path:?} {stats}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stats;
+    use parser::{Ast, Syntax};
+
+    fn stats(src: &str) -> Stats {
+        let syntax = Syntax::default();
+        let ast = Ast::from_str(src, None, &syntax).unwrap();
+        Stats::new(ast.nodes())
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(stats(""), Stats::default());
+        assert_eq!(
+            stats("Hello {{ name }}!{# comment #}"),
+            Stats {
+                static_bytes: 7,
+                expressions: 1,
+                loops: 0,
+                max_depth: 0,
+            }
+        );
+        assert_eq!(
+            stats(
+                "{% for row in rows %}{% if row.ok %}{% for c in row.cells %}{{ c }},\
+                {% endfor %}{% else %}-{% endif %}{% endfor %}{{ footer }}"
+            ),
+            Stats {
+                static_bytes: 2,
+                expressions: 2,
+                loops: 2,
+                max_depth: 3,
+            }
+        );
+        assert_eq!(
+            stats("{% raw %}{{ x }}{% endraw %}{% macro m() %}<p>{% endmacro %}"),
+            Stats {
+                static_bytes: 10,
+                expressions: 0,
+                loops: 0,
+                max_depth: 1,
+            }
+        );
+    }
+}
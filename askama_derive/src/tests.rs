@@ -12,8 +12,11 @@ fn check_if_let() {
             r##"#[template(source = r#"{jinja}"#, ext = "txt")]
 struct Foo;"##
         );
-        let generated =
-            build_template(&syn::parse_str::<syn::DeriveInput>(&jinja).unwrap()).unwrap();
+        let generated = build_template(
+            &syn::parse_str::<syn::DeriveInput>(&jinja).unwrap(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         let generated_s = syn::parse_str::<proc_macro2::TokenStream>(&generated)
             .unwrap()
@@ -25,7 +28,24 @@ struct Foo;"##
         let expected = format!(
             r#"impl ::askama::Template for Foo {{
     fn render_into(&self, writer: &mut (impl ::std::fmt::Write + ?Sized)) -> ::askama::Result<()> {{
-        {new_expected}
+        ::askama::Template::render_into_with_escaper(self, writer, ::askama::Text)
+    }}
+    fn render_into_with_escaper(
+        &self,
+        writer: &mut (impl ::std::fmt::Write + ?Sized),
+        _escaper: impl ::askama::Escaper,
+    ) -> ::askama::Result<()> {{
+        let mut _output = ::askama::helpers::OutputWriter::new(
+            writer,
+            &_escaper,
+            <Self as ::askama::Template>::SIZE_HINT
+        );
+        {{
+            use ::std::fmt::Write as _;
+            let writer = &mut _output;
+            {new_expected}
+        }}
+        _output.finish(&_escaper)?;
         ::askama::Result::Ok(())
     }}
     const EXTENSION: ::std::option::Option<&'static ::std::primitive::str> = Some("txt");
@@ -56,7 +76,7 @@ impl ::std::fmt::Display for Foo {{
     ::std::write!(
         writer,
         "{expr0}",
-        expr0 = &::askama::MarkupDisplay::new_unsafe(&(query), ::askama::Text),
+        expr0 = &::askama::MarkupDisplay::new_unsafe(&(query), &_escaper),
     )?;
 }"#,
     );
@@ -69,7 +89,7 @@ impl ::std::fmt::Display for Foo {{
     ::std::write!(
         writer,
         "{expr0}",
-        expr0 = &::askama::MarkupDisplay::new_unsafe(&(s), ::askama::Text),
+        expr0 = &::askama::MarkupDisplay::new_unsafe(&(s), &_escaper),
     )?;
 }"#,
     );
@@ -82,7 +102,7 @@ impl ::std::fmt::Display for Foo {{
     ::std::write!(
         writer,
         "{expr0}",
-        expr0 = &::askama::MarkupDisplay::new_unsafe(&(s), ::askama::Text),
+        expr0 = &::askama::MarkupDisplay::new_unsafe(&(s), &_escaper),
     )?;
 }"#,
     );
@@ -2,6 +2,11 @@
 #![deny(elided_lifetimes_in_paths)]
 #![deny(unreachable_pub)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
 use core::fmt::{self, Display, Formatter, Write};
 use core::str;
 
@@ -163,6 +168,43 @@ pub trait Escaper {
     fn write_escaped<W>(&self, fmt: W, string: &str) -> fmt::Result
     where
         W: Write;
+
+    /// Whether this escaper implements [`finalize()`](Escaper::finalize)
+    ///
+    /// Only then is the output of a template collected in a `String` before it is written to
+    /// the writer, so other escapers still write directly into the writer. Requires the `alloc`
+    /// feature.
+    #[cfg(feature = "alloc")]
+    const FINALIZES: bool = false;
+
+    /// Post-processes the whole rendered output of a template using this escaper.
+    ///
+    /// This is called once at the end of `askama::Template::render_into()`, after all content
+    /// was written, so every way of rendering a template, like `render()` or `to_string()`,
+    /// gives the same output. It is only called if [`FINALIZES`](Escaper::FINALIZES) is `true`.
+    /// The default implementation does nothing.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn finalize(&self, _output: &mut String) {}
+}
+
+impl<E: Escaper + ?Sized> Escaper for &E {
+    #[inline]
+    fn write_escaped<W>(&self, fmt: W, string: &str) -> fmt::Result
+    where
+        W: Write,
+    {
+        E::write_escaped(self, fmt, string)
+    }
+
+    #[cfg(feature = "alloc")]
+    const FINALIZES: bool = E::FINALIZES;
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn finalize(&self, output: &mut String) {
+        E::finalize(self, output)
+    }
 }
 
 #[cfg(test)]
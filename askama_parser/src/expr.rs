@@ -8,10 +8,10 @@ use nom::combinator::{cut, map, not, opt, peek, recognize};
 use nom::error::ErrorKind;
 use nom::error_position;
 use nom::multi::{fold_many0, many0, separated_list0};
-use nom::sequence::{pair, preceded, terminated, tuple};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 
 use super::{
-    char_lit, filter, identifier, not_ws, num_lit, path_or_identifier, str_lit, ws, Level,
+    char_lit, filter, identifier, keyword, not_ws, num_lit, path_or_identifier, str_lit, ws, Level,
     PathOrIdentifier,
 };
 use crate::{ErrorContext, ParseResult};
@@ -72,6 +72,15 @@ pub enum Expr<'a> {
     Call(Box<Expr<'a>>, Vec<Expr<'a>>),
     RustMacro(Vec<&'a str>, &'a str),
     Try(Box<Expr<'a>>),
+    /// A closure with an expression body, only allowed as argument of a call: `|x| x.active`.
+    Closure(Vec<&'a str>, Box<Expr<'a>>),
+    /// `obj?.attr`: `rest` is evaluated if `obj` is `Some`, its value being referenced by
+    /// [`Expr::OptionalValue`]. Stored as `(obj, rest)`, `rest` being e.g. `attr` or `attr.x`.
+    OptionalChain(Box<Expr<'a>>, Box<Expr<'a>>),
+    /// The value inside the `Option` of the enclosing [`Expr::OptionalChain`].
+    OptionalValue,
+    /// An inline conditional: `then if cond else otherwise`, stored as `(cond, then, otherwise)`.
+    IfElse(Box<Expr<'a>>, Box<Expr<'a>>, Box<Expr<'a>>),
     /// This variant should never be used directly. It is created when generating filter blocks.
     Generated(String),
 }
@@ -80,7 +89,7 @@ impl<'a> Expr<'a> {
     pub(super) fn arguments(
         i: &'a str,
         level: Level,
-        is_template_macro: bool,
+        allow_named_arguments: bool,
     ) -> ParseResult<'a, Vec<Self>> {
         let (_, level) = level.nest(i)?;
         let mut named_arguments = HashSet::new();
@@ -104,9 +113,10 @@ impl<'a> Expr<'a> {
                                     level,
                                     named_arguments,
                                     start,
-                                    is_template_macro,
+                                    allow_named_arguments,
                                 )
                             },
+                            move |i| Self::closure(i, level),
                             move |i| Self::parse(i, level),
                         ))(i)?;
                         if has_named_arguments && !matches!(expr, Self::NamedArgument(_, _)) {
@@ -124,16 +134,30 @@ impl<'a> Expr<'a> {
         )(i)
     }
 
+    fn closure(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        let (_, level) = level.nest(i)?;
+        let params = alt((
+            map(tag("||"), |_| Vec::new()),
+            delimited(
+                char('|'),
+                separated_list0(char(','), ws(identifier)),
+                char('|'),
+            ),
+        ));
+        let (i, (params, body)) = pair(params, cut(ws(|i| Self::parse(i, level))))(i)?;
+        Ok((i, Self::Closure(params, Box::new(body))))
+    }
+
     fn named_argument(
         i: &'a str,
         level: Level,
         named_arguments: &mut HashSet<&'a str>,
         start: &'a str,
-        is_template_macro: bool,
+        allow_named_arguments: bool,
     ) -> ParseResult<'a, Self> {
-        if !is_template_macro {
-            // If this is not a template macro, we don't want to parse named arguments so
-            // we instead return an error which will allow to continue the parsing.
+        if !allow_named_arguments {
+            // If this is not a template macro or filter call, we don't want to parse named
+            // arguments so we instead return an error which will allow to continue the parsing.
             return Err(nom::Err::Error(error_position!(i, ErrorKind::Alt)));
         }
 
@@ -152,6 +176,26 @@ impl<'a> Expr<'a> {
 
     pub(super) fn parse(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         let (_, level) = level.nest(i)?;
+        let (i, then) = Self::range(i, level)?;
+        // Without an `else`, the `if` is not part of this expression, e.g. it is the condition of
+        // a `{% for %}` loop.
+        let (i, if_else) = opt(tuple((
+            ws(keyword("if")),
+            move |i| Self::or(i, level),
+            ws(keyword("else")),
+            cut(move |i| Self::parse(i, level)),
+        )))(i)?;
+        Ok(match if_else {
+            Some((_, cond, _, otherwise)) => (
+                i,
+                Self::IfElse(Box::new(cond), Box::new(then), Box::new(otherwise)),
+            ),
+            None => (i, then),
+        })
+    }
+
+    // Only called by `parse()`, which already increased the nesting level.
+    fn range(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         let range_right = move |i| {
             pair(
                 ws(alt((tag("..="), tag("..")))),
@@ -175,7 +219,7 @@ impl<'a> Expr<'a> {
     // Keep in sync with `TWO_PLUS_CHAR_OPS`, below
     expr_prec_layer!(or, and, "||");
     expr_prec_layer!(and, compare, "&&");
-    expr_prec_layer!(compare, bor, "==", "!=", ">=", ">", "<=", "<");
+    expr_prec_layer!(compare, concat, "==", "!=", ">=", ">", "<=", "<");
     expr_prec_layer!(bor, bxor, "|");
     expr_prec_layer!(bxor, band, "^");
     expr_prec_layer!(band, shifts, "&");
@@ -183,6 +227,19 @@ impl<'a> Expr<'a> {
     expr_prec_layer!(addsub, muldivmod, "+", "-");
     expr_prec_layer!(muldivmod, filtered, "*", "/", "%");
 
+    // Unlike the other layers, `~` does not count as a level of nesting, so that it does not lower
+    // the nesting limit of the templates. Its operands are nested by `bor` anyway.
+    fn concat(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        let (i, left) = Self::bor(i, level)?;
+        let (i, right) = many0(preceded(ws(tag("~")), |i| Self::bor(i, level)))(i)?;
+        Ok((
+            i,
+            right.into_iter().fold(left, |left, right| {
+                Self::BinOp("~", Box::new(left), Box::new(right))
+            }),
+        ))
+    }
+
     fn filtered(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         let (_, level) = level.nest(i)?;
         let (i, (obj, filters)) =
@@ -310,22 +367,34 @@ enum Suffix<'a> {
     // The value is the arguments of the macro call.
     MacroCall(&'a str),
     Try,
+    OptionalAttr(&'a str),
 }
 
 impl<'a> Suffix<'a> {
     fn parse(i: &'a str, level: Level) -> ParseResult<'a, Expr<'a>> {
         let (_, level) = level.nest(i)?;
         let (mut i, mut expr) = Expr::single(i, level)?;
+        // The object of the last `?.` operator, the following suffixes are applied to its value.
+        let mut optional_obj = None;
         loop {
             let (j, suffix) = opt(alt((
                 Self::attr,
                 |i| Self::index(i, level),
                 |i| Self::call(i, level),
+                Self::optional_attr,
                 Self::r#try,
                 Self::r#macro,
             )))(i)?;
 
             match suffix {
+                Some(Self::OptionalAttr(attr)) => {
+                    let obj = match optional_obj.take() {
+                        Some(obj) => Expr::OptionalChain(Box::new(obj), Box::new(expr)),
+                        None => expr,
+                    };
+                    optional_obj = Some(obj);
+                    expr = Expr::Attr(Expr::OptionalValue.into(), attr);
+                }
                 Some(Self::Attr(attr)) => expr = Expr::Attr(expr.into(), attr),
                 Some(Self::Index(index)) => expr = Expr::Index(expr.into(), index.into()),
                 Some(Self::Call(args)) => expr = Expr::Call(expr.into(), args),
@@ -340,6 +409,9 @@ impl<'a> Suffix<'a> {
 
             i = j;
         }
+        if let Some(obj) = optional_obj {
+            expr = Expr::OptionalChain(Box::new(obj), Box::new(expr));
+        }
         Ok((i, expr))
     }
 
@@ -427,6 +499,16 @@ impl<'a> Suffix<'a> {
         map(move |i| Expr::arguments(i, level, false), Self::Call)(i)
     }
 
+    fn optional_attr(i: &'a str) -> ParseResult<'a, Self> {
+        map(
+            preceded(
+                tuple((take_till(not_ws), char('?'), char('.'), not(char('.')))),
+                cut(alt((num_lit, identifier))),
+            ),
+            Self::OptionalAttr,
+        )(i)
+    }
+
     fn r#try(i: &'a str) -> ParseResult<'a, Self> {
         map(preceded(take_till(not_ws), char('?')), |_| Self::Try)(i)
     }
@@ -23,6 +23,7 @@ pub mod node;
 pub use node::Node;
 #[cfg(test)]
 mod tests;
+pub mod validate;
 
 mod _parsed {
     use std::path::Path;
@@ -30,14 +31,14 @@ mod _parsed {
     use std::{fmt, mem};
 
     use super::node::Node;
-    use super::{Ast, ParseError, Syntax};
+    use super::{current_dir, strip_common, Ast, ParseError, Syntax};
 
     #[derive(Default)]
     pub struct Parsed {
         // `source` must outlive `ast`, so `ast` must be declared before `source`
         ast: Ast<'static>,
-        #[allow(dead_code)]
         source: String,
+        file_path: Option<Rc<Path>>,
     }
 
     impl Parsed {
@@ -52,14 +53,38 @@ mod _parsed {
             // internally we will transmute it to `&'static str` to satisfy the compiler.
             // However, we only expose the nodes with a lifetime limited to `self`.
             let src = unsafe { mem::transmute::<&str, &'static str>(source.as_str()) };
-            let ast = Ast::from_str(src, file_path, syntax)?;
-            Ok(Self { ast, source })
+            let ast = Ast::from_str(src, file_path.clone(), syntax)?;
+            Ok(Self {
+                ast,
+                source,
+                file_path,
+            })
         }
 
         // The return value's lifetime must be limited to `self` to uphold the unsafe invariant.
         pub fn nodes(&self) -> &[Node<'_>] {
             &self.ast.nodes
         }
+
+        /// Appends to `message` where `at` is found in the template, like the errors of the
+        /// parser do. Returns `None` if `at` is not a slice of the source of this template.
+        pub fn located(&self, message: &str, at: &str) -> Option<String> {
+            let offset = (at.as_ptr() as usize).checked_sub(self.source.as_ptr() as usize)?;
+            let source_before = self.source.get(..offset)?;
+            let row = source_before.matches('\n').count() + 1;
+            let column = match source_before.rfind('\n') {
+                Some(start) => source_before[start + 1..].chars().count(),
+                None => source_before.chars().count(),
+            };
+            let path = self
+                .file_path
+                .as_ref()
+                .and_then(|path| Some(strip_common(&current_dir().ok()?, path)));
+            Some(match path {
+                Some(path) => format!("{message}\n  --> {path}:{row}:{column}"),
+                None => format!("{message} at row {row}, column {column}"),
+            })
+        }
     }
 
     impl fmt::Debug for Parsed {
@@ -569,14 +594,6 @@ impl<'a> State<'a> {
         tag(self.syntax.comment_end)(i)
     }
 
-    fn tag_expr_start<'i>(&self, i: &'i str) -> ParseResult<'i> {
-        tag(self.syntax.expr_start)(i)
-    }
-
-    fn tag_expr_end<'i>(&self, i: &'i str) -> ParseResult<'i> {
-        tag(self.syntax.expr_end)(i)
-    }
-
     fn enter_loop(&self) {
         self.loop_depth.set(self.loop_depth.get() + 1);
     }
@@ -598,6 +615,9 @@ pub struct Syntax<'a> {
     pub expr_end: &'a str,
     pub comment_start: &'a str,
     pub comment_end: &'a str,
+    /// Optional delimiters for expressions that are never escaped, e.g. `{{{ x }}}`.
+    pub raw_expr_start: Option<&'a str>,
+    pub raw_expr_end: Option<&'a str>,
 }
 
 impl Default for Syntax<'static> {
@@ -609,6 +629,8 @@ impl Default for Syntax<'static> {
             expr_end: "}}",
             comment_start: "{#",
             comment_end: "#}",
+            raw_expr_start: None,
+            raw_expr_end: None,
         }
     }
 }
@@ -640,7 +662,7 @@ fn filter(i: &str, level: Level) -> ParseResult<'_, (&str, Option<Vec<Expr<'_>>>
     let (i, (_, fname, args)) = tuple((
         char('|'),
         ws(identifier),
-        opt(|i| Expr::arguments(i, level, false)),
+        opt(|i| Expr::arguments(i, level, true)),
     ))(i)?;
     Ok((i, (fname, args)))
 }
@@ -685,7 +707,7 @@ fn strip_common(base: &Path, path: &Path) -> String {
 #[cfg(not(windows))]
 #[cfg(test)]
 mod test {
-    use super::{char_lit, num_lit, strip_common};
+    use super::{char_lit, num_lit, strip_common, Expr, Filter, Node, Parsed, Syntax};
     use std::path::Path;
 
     #[test]
@@ -718,6 +740,19 @@ mod test {
         assert_eq!(strip_common(&cwd, Path::new("/a/b/c")), "/a/b/c");
     }
 
+    #[test]
+    fn test_located() {
+        let parsed = Parsed::new("Hi\n  {{ a|uper }}".into(), None, &Syntax::default()).unwrap();
+        let Node::Expr(_, Expr::Filter(Filter { name, .. })) = &parsed.nodes()[1] else {
+            panic!("expected a filter");
+        };
+        assert_eq!(
+            parsed.located("unknown filter", name).as_deref(),
+            Some("unknown filter at row 2, column 7"),
+        );
+        assert_eq!(parsed.located("unknown filter", "uper"), None);
+    }
+
     #[test]
     fn test_num_lit() {
         // Should fail.
@@ -3,15 +3,13 @@ use std::str;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_till};
 use nom::character::complete::char;
-use nom::combinator::{
-    complete, consumed, cut, eof, map, map_res, not, opt, peek, recognize, value,
-};
+use nom::combinator::{complete, cut, eof, map, map_res, not, opt, peek, recognize, value};
 use nom::error::ErrorKind;
 use nom::error_position;
 use nom::multi::{fold_many0, many0, many1, separated_list0, separated_list1};
 use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 
-use crate::{not_ws, ErrorContext, ParseResult};
+use crate::{not_ws, ErrorContext, ParseErr, ParseResult};
 
 use super::{
     bool_lit, char_lit, filter, identifier, is_ws, keyword, num_lit, path_or_identifier, skip_till,
@@ -31,12 +29,17 @@ pub enum Node<'a> {
     Extends(Extends<'a>),
     BlockDef(BlockDef<'a>),
     Include(Include<'a>),
+    Embed(Embed<'a>),
     Import(Import<'a>),
     Macro(Macro<'a>),
     Raw(Raw<'a>),
     Break(Ws),
     Continue(Ws),
     FilterBlock(FilterBlock<'a>),
+    /// `{% autoescape true|false %}...{% endautoescape %}`
+    Autoescape(Autoescape<'a>),
+    /// `{% debug %}`, which dumps the variables in scope.
+    Debug(Ws),
 }
 
 impl<'a> Node<'a> {
@@ -72,15 +75,18 @@ impl<'a> Node<'a> {
             "if" => |i, s| wrap(Self::If, If::parse(i, s)),
             "for" => |i, s| wrap(|n| Self::Loop(Box::new(n)), Loop::parse(i, s)),
             "match" => |i, s| wrap(Self::Match, Match::parse(i, s)),
-            "extends" => |i, _s| wrap(Self::Extends, Extends::parse(i)),
-            "include" => |i, _s| wrap(Self::Include, Include::parse(i)),
+            "extends" => |i, s| wrap(Self::Extends, Extends::parse(i, s)),
+            "include" => |i, s| wrap(Self::Include, Include::parse(i, s)),
+            "embed" => |i, _s| wrap(Self::Embed, Embed::parse(i)),
             "import" => |i, _s| wrap(Self::Import, Import::parse(i)),
             "block" => |i, s| wrap(Self::BlockDef, BlockDef::parse(i, s)),
             "macro" => |i, s| wrap(Self::Macro, Macro::parse(i, s)),
             "raw" => |i, s| wrap(Self::Raw, Raw::parse(i, s)),
             "break" => |i, s| Self::r#break(i, s),
             "continue" => |i, s| Self::r#continue(i, s),
+            "debug" => |i, _s| Self::debug(i),
             "filter" => |i, s| wrap(Self::FilterBlock, FilterBlock::parse(i, s)),
+            "autoescape" => |i, s| wrap(Self::Autoescape, Autoescape::parse(i, s)),
             _ => {
                 return Err(ErrorContext::from_err(nom::Err::Error(error_position!(
                     i,
@@ -104,6 +110,16 @@ impl<'a> Node<'a> {
         }
     }
 
+    fn debug(i: &'a str) -> ParseResult<'a, Self> {
+        let mut p = tuple((
+            opt(Whitespace::parse),
+            ws(keyword("debug")),
+            opt(Whitespace::parse),
+        ));
+        let (i, (pws, _, nws)) = p(i)?;
+        Ok((i, Self::Debug(Ws(pws, nws))))
+    }
+
     fn r#break(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let mut p = tuple((
             opt(Whitespace::parse),
@@ -137,8 +153,17 @@ impl<'a> Node<'a> {
     }
 
     fn expr(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
+        // An expression between the `raw_expr_*` delimiters is never escaped, as if it was
+        // passed to the `safe` filter.
+        let raw = s
+            .syntax
+            .raw_expr_start
+            .zip(s.syntax.raw_expr_end)
+            .filter(|(start, _)| i.starts_with(start));
+        let (start, end) = raw.unwrap_or((s.syntax.expr_start, s.syntax.expr_end));
+
         let (i, (pws, expr)) = preceded(
-            |i| s.tag_expr_start(i),
+            tag(start),
             cut(pair(
                 opt(Whitespace::parse),
                 ws(|i| Expr::parse(i, s.level.get())),
@@ -147,12 +172,20 @@ impl<'a> Node<'a> {
 
         let (i, (nws, closed)) = cut(pair(
             opt(Whitespace::parse),
-            alt((value(true, |i| s.tag_expr_end(i)), value(false, ws(eof)))),
+            alt((value(true, tag(end)), value(false, ws(eof)))),
         ))(i)?;
-        match closed {
-            true => Ok((i, Self::Expr(Ws(pws, nws), expr))),
-            false => Err(ErrorContext::unclosed("expression", s.syntax.expr_end, i).into()),
+        if !closed {
+            return Err(ErrorContext::unclosed("expression", end, i).into());
         }
+
+        let expr = match raw {
+            Some(_) => Expr::Filter(Filter {
+                name: "safe",
+                arguments: vec![expr],
+            }),
+            None => expr,
+        };
+        Ok((i, Self::Expr(Ws(pws, nws), expr)))
     }
 }
 
@@ -309,6 +342,8 @@ impl<'a> Target<'a> {
 pub struct When<'a> {
     pub ws: Ws,
     pub target: Target<'a>,
+    /// The expression after `if` in `{% when Some(x) if x > 0 %}`.
+    pub guard: Option<Expr<'a>>,
     pub nodes: Vec<Node<'a>>,
 }
 
@@ -330,6 +365,7 @@ impl<'a> When<'a> {
             Self {
                 ws: Ws(pws, nws),
                 target: Target::Name("_"),
+                guard: None,
                 nodes,
             },
         ))
@@ -343,17 +379,22 @@ impl<'a> When<'a> {
             ws(keyword("when")),
             cut(tuple((
                 ws(|i| Target::parse(i, s)),
+                opt(preceded(
+                    ws(keyword("if")),
+                    cut(ws(|i| Expr::parse(i, s.level.get()))),
+                )),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
                 cut(|i| Node::many(i, s)),
             ))),
         ));
-        let (i, (_, pws, _, (target, nws, _, nodes))) = p(i)?;
+        let (i, (_, pws, _, (target, guard, nws, _, nodes))) = p(i)?;
         Ok((
             i,
             Self {
                 ws: Ws(pws, nws),
                 target,
+                guard,
                 nodes,
             },
         ))
@@ -439,7 +480,12 @@ impl Whitespace {
 pub struct Loop<'a> {
     pub ws1: Ws,
     pub var: Target<'a>,
+    /// Whether the target is followed by `?` (`{% for item? in iter %}`), i.e. every item is
+    /// a `Result` which gets unwrapped, and errors are propagated.
+    pub try_item: bool,
     pub iter: Expr<'a>,
+    /// Whether the iterable is followed by `reversed` (`{% for item in iter reversed %}`).
+    pub reversed: bool,
     pub cond: Option<Expr<'a>>,
     pub body: Vec<Node<'a>>,
     pub ws2: Ws,
@@ -483,9 +529,11 @@ impl<'a> Loop<'a> {
             ws(keyword("for")),
             cut(tuple((
                 ws(|i| Target::parse(i, s)),
+                opt(ws(char('?'))),
                 ws(keyword("in")),
                 cut(tuple((
                     ws(|i| Expr::parse(i, s.level.get())),
+                    opt(ws(keyword("reversed"))),
                     opt(if_cond),
                     opt(Whitespace::parse),
                     |i| s.tag_block_end(i),
@@ -502,15 +550,28 @@ impl<'a> Loop<'a> {
                 ))),
             ))),
         ));
-        let (i, (pws1, _, (var, _, (iter, cond, nws1, _, (body, (_, pws2, else_block, _, nws2)))))) =
-            p(i)?;
+        let (
+            i,
+            (
+                pws1,
+                _,
+                (
+                    var,
+                    try_item,
+                    _,
+                    (iter, reversed, cond, nws1, _, (body, (_, pws2, else_block, _, nws2))),
+                ),
+            ),
+        ) = p(i)?;
         let (nws3, else_block, pws3) = else_block.unwrap_or_default();
         Ok((
             i,
             Self {
                 ws1: Ws(pws1, nws1),
                 var,
+                try_item: try_item.is_some(),
                 iter,
+                reversed: reversed.is_some(),
                 cond,
                 body,
                 ws2: Ws(pws2, nws3),
@@ -525,38 +586,40 @@ impl<'a> Loop<'a> {
 pub struct Macro<'a> {
     pub ws1: Ws,
     pub name: &'a str,
-    pub args: Vec<&'a str>,
+    /// The names of the parameters, with their default values
+    pub args: Vec<(&'a str, Option<Expr<'a>>)>,
+    /// The variables of the calling scope declared with `uses`. If there are any, these are the
+    /// only variables of the calling scope which the macro can read.
+    pub uses: Vec<&'a str>,
     pub nodes: Vec<Node<'a>>,
     pub ws2: Ws,
 }
 
 impl<'a> Macro<'a> {
     fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
-        fn parameters(i: &str) -> ParseResult<'_, Vec<&str>> {
-            delimited(
-                ws(char('(')),
-                separated_list0(char(','), ws(identifier)),
-                tuple((opt(ws(char(','))), char(')'))),
-            )(i)
-        }
-
         let mut start = tuple((
             opt(Whitespace::parse),
             ws(keyword("macro")),
             cut(tuple((
                 ws(identifier),
-                opt(ws(parameters)),
+                opt(ws(|i| parameters(i, s))),
+                opt(preceded(
+                    ws(keyword("uses")),
+                    cut(separated_list1(ws(char(',')), ws(identifier))),
+                )),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
             ))),
         ));
-        let (j, (pws1, _, (name, params, nws1, _))) = start(i)?;
+        let (j, (pws1, _, (name, params, uses, nws1, _))) = start(i)?;
         if name == "super" {
             return Err(nom::Err::Failure(ErrorContext::new(
                 "'super' is not a valid name for a macro",
                 i,
             )));
         }
+        let params = params.unwrap_or_default();
+        check_parameters(i, "macro", name, &params)?;
 
         let mut end = cut(tuple((
             |i| Node::many(i, s),
@@ -580,7 +643,8 @@ impl<'a> Macro<'a> {
             Self {
                 ws1: Ws(pws1, nws1),
                 name,
-                args: params.unwrap_or_default(),
+                args: params,
+                uses: uses.unwrap_or_default(),
                 nodes: contents,
                 ws2: Ws(pws2, nws2),
             },
@@ -650,6 +714,52 @@ impl<'a> FilterBlock<'a> {
     }
 }
 
+/// The nodes between `{% autoescape %}` and `{% endautoescape %}`
+///
+/// With `false`, the expressions in the block are written without being escaped. With `true`,
+/// they are escaped again with the escaper of the template, e.g. in a nested block.
+#[derive(Debug, PartialEq)]
+pub struct Autoescape<'a> {
+    pub ws1: Ws,
+    pub enabled: bool,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> Autoescape<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            ws(keyword("autoescape")),
+            cut(tuple((ws(bool_lit), opt(Whitespace::parse), |i| {
+                s.tag_block_end(i)
+            }))),
+        ));
+        let (i, (pws1, _, (enabled, nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                ws(keyword("endautoescape")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            Self {
+                ws1: Ws(pws1, nws1),
+                enabled: enabled == "true",
+                nodes,
+                ws2: Ws(pws2, nws2),
+            },
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Import<'a> {
     pub ws: Ws,
@@ -770,6 +880,13 @@ impl<'a> Match<'a> {
 pub struct BlockDef<'a> {
     pub ws1: Ws,
     pub name: &'a str,
+    /// The names of the parameters of `{% block card(title) %}`, with their default values.
+    /// A block with parameters is not rendered where it is defined, but where it is called with
+    /// `{% call card("Title") %}`.
+    pub args: Vec<(&'a str, Option<Expr<'a>>)>,
+    /// `true` if the block was declared as `{% block name scoped %}`, in which case the
+    /// variables in scope where the block is placed are available to its overrides.
+    pub scoped: bool,
     pub nodes: Vec<Node<'a>>,
     pub ws2: Ws,
 }
@@ -779,11 +896,26 @@ impl<'a> BlockDef<'a> {
         let mut start = tuple((
             opt(Whitespace::parse),
             ws(keyword("block")),
-            cut(tuple((ws(identifier), opt(Whitespace::parse), |i| {
-                s.tag_block_end(i)
-            }))),
+            cut(tuple((
+                ws(identifier),
+                opt(ws(|i| parameters(i, s))),
+                opt(ws(keyword("scoped"))),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
         ));
-        let (i, (pws1, _, (name, nws1, _))) = start(i)?;
+        let (j, (pws1, _, (name, params, scoped, nws1, _))) = start(i)?;
+        let params = params.unwrap_or_default();
+        check_parameters(i, "block", name, &params)?;
+        if !params.is_empty() && scoped.is_some() {
+            return Err(nom::Err::Failure(ErrorContext::new(
+                format!(
+                    "block `{name}` has parameters, so it cannot be `scoped`: it only sees the \
+                     arguments it is called with"
+                ),
+                i,
+            )));
+        }
 
         let mut end = cut(tuple((
             |i| Node::many(i, s),
@@ -800,13 +932,15 @@ impl<'a> BlockDef<'a> {
                 ))),
             ))),
         )));
-        let (i, (nodes, (_, pws2, _, (_, nws2)))) = end(i)?;
+        let (i, (nodes, (_, pws2, _, (_, nws2)))) = end(j)?;
 
         Ok((
             i,
             BlockDef {
                 ws1: Ws(pws1, nws1),
                 name,
+                args: params,
+                scoped: scoped.is_some(),
                 nodes,
                 ws2: Ws(pws2, nws2),
             },
@@ -814,6 +948,46 @@ impl<'a> BlockDef<'a> {
     }
 }
 
+/// Parses the parameters of a macro or of a block, e.g. `(title, level = 1)`
+fn parameters<'a>(i: &'a str, s: &State<'_>) -> ParseResult<'a, Vec<(&'a str, Option<Expr<'a>>)>> {
+    delimited(
+        ws(char('(')),
+        separated_list0(
+            char(','),
+            ws(pair(
+                identifier,
+                opt(preceded(
+                    ws(char('=')),
+                    ws(|i| Expr::parse(i, s.level.get())),
+                )),
+            )),
+        ),
+        tuple((opt(ws(char(','))), char(')'))),
+    )(i)
+}
+
+fn check_parameters<'a>(
+    i: &'a str,
+    kind: &str,
+    name: &str,
+    params: &[(&'a str, Option<Expr<'a>>)],
+) -> Result<(), ParseErr<'a>> {
+    match params
+        .windows(2)
+        .find(|window| window[0].1.is_some() && window[1].1.is_none())
+    {
+        Some(window) => Err(nom::Err::Failure(ErrorContext::new(
+            format!(
+                "parameter `{}` of {kind} `{name}` needs a default value, because it follows \
+                 a parameter with a default value",
+                window[1].0,
+            ),
+            i,
+        ))),
+        None => Ok(()),
+    }
+}
+
 fn check_end_name<'a>(
     before: &'a str,
     after: &'a str,
@@ -847,6 +1021,10 @@ impl<'a> Lit<'a> {
             tag(s.syntax.block_start),
             tag(s.syntax.comment_start),
             tag(s.syntax.expr_start),
+            |i| match s.syntax.raw_expr_start {
+                Some(raw_expr_start) => tag(raw_expr_start)(i),
+                None => Err(nom::Err::Error(error_position!(i, ErrorKind::Tag))),
+            },
         ));
 
         let (i, _) = not(eof)(i)?;
@@ -891,17 +1069,44 @@ impl<'a> Raw<'a> {
             peek(|i| s.tag_block_end(i)),
         ));
 
+        let raw = tuple((
+            |i| s.tag_block_start(i),
+            opt(Whitespace::parse),
+            ws(keyword("raw")),
+            opt(Whitespace::parse),
+            |i| s.tag_block_end(i),
+        ));
+        // Nested `{% raw %}...{% endraw %}` pairs are part of the content, so they are kept as
+        // they are, e.g. to document templates.
+        let mut next = skip_till(alt((map(raw, |_| None), map(endraw, Some))));
+        let contents = move |start: &'a str| {
+            let mut i = start;
+            let mut depth = 0_usize;
+            loop {
+                let (end, (j, tag)) = next(i)?;
+                match tag {
+                    None => depth += 1,
+                    Some(_) if depth > 0 => depth -= 1,
+                    Some(tag) => {
+                        let contents = &start[..start.len() - end.len()];
+                        return Ok((j, (contents, tag)));
+                    }
+                }
+                i = j;
+            }
+        };
+
         let mut p = tuple((
             opt(Whitespace::parse),
             ws(keyword("raw")),
             cut(tuple((
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
-                consumed(skip_till(endraw)),
+                contents,
             ))),
         ));
 
-        let (_, (pws1, _, (nws1, _, (contents, (i, (_, pws2, _, nws2, _)))))) = p(i)?;
+        let (i, (pws1, _, (nws1, _, (contents, (_, pws2, _, nws2, _))))) = p(i)?;
         let lit = Lit::split_ws_parts(contents);
         let ws1 = Ws(pws1, nws1);
         let ws2 = Ws(pws2, nws2);
@@ -914,6 +1119,17 @@ pub struct Let<'a> {
     pub ws: Ws,
     pub var: Target<'a>,
     pub val: Option<Expr<'a>>,
+    /// The content of a block assignment, `{% set var %}...{% endset %}`.
+    pub block: Option<LetBlock<'a>>,
+}
+
+/// The nodes between `{% set var %}` and `{% endset %}`, whose rendered output gets assigned
+/// to the variable.
+#[derive(Debug, PartialEq)]
+pub struct LetBlock<'a> {
+    pub nodes: Vec<Node<'a>>,
+    /// The whitespace handling of the `{% endset %}` tag.
+    pub ws: Ws,
 }
 
 impl<'a> Let<'a> {
@@ -930,7 +1146,28 @@ impl<'a> Let<'a> {
                 opt(Whitespace::parse),
             ))),
         ));
-        let (i, (pws, _, (var, val, nws))) = p(i)?;
+        let (i, (pws, tag, (var, val, nws))) = p(i)?;
+
+        // Without a value, `{% let %}` only declares the variable, while `{% set %}` starts a
+        // block assignment that ends with `{% endset %}`.
+        let (i, block) = match (tag, &val) {
+            ("set", None) => {
+                let (i, block) = cut(tuple((
+                    |i| s.tag_block_end(i),
+                    |i| Node::many(i, s),
+                    |i| s.tag_block_start(i),
+                    opt(Whitespace::parse),
+                    ws(keyword("endset")),
+                    opt(Whitespace::parse),
+                )))(i)?;
+                (i, Some(block))
+            }
+            _ => (i, None),
+        };
+        let block = block.map(|(_, nodes, _, pws, _, nws)| LetBlock {
+            nodes,
+            ws: Ws(pws, nws),
+        });
 
         Ok((
             i,
@@ -938,6 +1175,7 @@ impl<'a> Let<'a> {
                 ws: Ws(pws, nws),
                 var,
                 val,
+                block,
             },
         ))
     }
@@ -992,13 +1230,93 @@ impl<'a> If<'a> {
 pub struct Include<'a> {
     pub ws: Ws,
     pub path: &'a str,
+    /// The variables of `{% include "path" with name = value, ... %}`, which are the only ones
+    /// the included template can use.
+    pub with: Option<Vec<(&'a str, Expr<'a>)>>,
+    /// The fallback content of `{% include "path" %}{% empty %}...{% endinclude %}`.
+    pub empty: Option<IncludeEmpty<'a>>,
+}
+
+/// The nodes between `{% empty %}` and `{% endinclude %}`, which are rendered instead of the
+/// included template if its output is empty or contains only whitespace.
+#[derive(Debug, PartialEq)]
+pub struct IncludeEmpty<'a> {
+    pub ws1: Ws,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
 }
 
 impl<'a> Include<'a> {
-    fn parse(i: &'a str) -> ParseResult<'a, Self> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let mut p = tuple((
             opt(Whitespace::parse),
             ws(keyword("include")),
+            cut(tuple((
+                ws(str_lit),
+                opt(preceded(
+                    ws(keyword("with")),
+                    cut(separated_list1(
+                        char(','),
+                        ws(pair(
+                            identifier,
+                            preceded(ws(char('=')), ws(|i| Expr::parse(i, s.level.get()))),
+                        )),
+                    )),
+                )),
+                opt(Whitespace::parse),
+            ))),
+        ));
+        let (i, (pws, _, (path, with, nws))) = p(i)?;
+
+        // Only whitespace may separate the `{% include %}` and `{% empty %}` tags.
+        let mut empty = opt(tuple((
+            |i| s.tag_block_end(i),
+            ws(|i| s.tag_block_start(i)),
+            opt(Whitespace::parse),
+            ws(keyword("empty")),
+            cut(tuple((
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+                |i| Node::many(i, s),
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                ws(keyword("endinclude")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, empty) = empty(i)?;
+        let empty = empty.map(
+            |(_, _, pws1, _, (nws1, _, nodes, _, pws2, _, nws2))| IncludeEmpty {
+                ws1: Ws(pws1, nws1),
+                nodes,
+                ws2: Ws(pws2, nws2),
+            },
+        );
+
+        Ok((
+            i,
+            Self {
+                ws: Ws(pws, nws),
+                path,
+                with,
+                empty,
+            },
+        ))
+    }
+}
+
+/// `{% embed "path" %}`: inserts the raw content of a file, without parsing or escaping it.
+#[derive(Debug, PartialEq)]
+pub struct Embed<'a> {
+    pub ws: Ws,
+    pub path: &'a str,
+}
+
+impl<'a> Embed<'a> {
+    fn parse(i: &'a str) -> ParseResult<'a, Self> {
+        let mut p = tuple((
+            opt(Whitespace::parse),
+            ws(keyword("embed")),
             cut(pair(ws(str_lit), opt(Whitespace::parse))),
         ));
         let (i, (pws, _, (path, nws))) = p(i)?;
@@ -1014,20 +1332,36 @@ impl<'a> Include<'a> {
 
 #[derive(Debug, PartialEq)]
 pub struct Extends<'a> {
-    pub path: &'a str,
+    /// The path of the parent template, or the paths of the candidates of
+    /// `{% extends name in ["a.html", "b.html"] %}`.
+    pub paths: Vec<&'a str>,
+    /// The expression selecting the parent template among `paths` at render time.
+    pub selector: Option<Expr<'a>>,
 }
 
 impl<'a> Extends<'a> {
-    fn parse(i: &'a str) -> ParseResult<'a, Self> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let start = i;
 
-        let (i, (pws, _, (path, nws))) = tuple((
+        let dynamic = pair(
+            terminated(ws(|i| Expr::parse(i, s.level.get())), ws(keyword("in"))),
+            cut(delimited(
+                ws(char('[')),
+                separated_list1(char(','), ws(str_lit)),
+                pair(opt(ws(char(','))), char(']')),
+            )),
+        );
+        let parent = alt((
+            map(dynamic, |(selector, paths)| (paths, Some(selector))),
+            map(str_lit, |path| (vec![path], None)),
+        ));
+        let (i, (pws, _, ((paths, selector), nws))) = tuple((
             opt(Whitespace::parse),
             ws(keyword("extends")),
-            cut(pair(ws(str_lit), opt(Whitespace::parse))),
+            cut(pair(ws(parent), opt(Whitespace::parse))),
         ))(i)?;
         match (pws, nws) {
-            (None, None) => Ok((i, Self { path })),
+            (None, None) => Ok((i, Self { paths, selector })),
             (_, _) => Err(nom::Err::Failure(ErrorContext::new(
                 "whitespace control is not allowed on `extends`",
                 start,
@@ -1040,6 +1374,9 @@ impl<'a> Extends<'a> {
 pub struct Comment<'a> {
     pub ws: Ws,
     pub content: &'a str,
+    /// The trimmed text following the `debug:` marker, if the comment starts with it
+    /// (e.g. `{# debug: rendered by user/profile.html #}`).
+    pub debug: Option<&'a str>,
 }
 
 impl<'a> Comment<'a> {
@@ -1089,13 +1426,19 @@ impl<'a> Comment<'a> {
         )(i)?;
 
         let mut nws = None;
+        let mut debug = None;
         if let Some(content) = content.strip_suffix(s.syntax.comment_end) {
             nws = match content.chars().last() {
                 Some('-') => Some(Whitespace::Suppress),
                 Some('+') => Some(Whitespace::Preserve),
                 Some('~') => Some(Whitespace::Minimize),
                 _ => None,
-            }
+            };
+            let text = match nws {
+                Some(_) => &content[..content.len() - 1],
+                None => content,
+            };
+            debug = text.trim_start().strip_prefix("debug:").map(str::trim);
         };
 
         Ok((
@@ -1103,6 +1446,7 @@ impl<'a> Comment<'a> {
             Self {
                 ws: Ws(pws, nws),
                 content,
+                debug,
             },
         ))
     }
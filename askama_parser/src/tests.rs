@@ -1,4 +1,4 @@
-use super::node::{Lit, Whitespace, Ws};
+use super::node::{Autoescape, Include, IncludeEmpty, Lit, Whitespace, Ws};
 use super::{Ast, Expr, Filter, Node, Syntax};
 
 fn check_ws_split(s: &str, res: &(&str, &str, &str)) {
@@ -303,6 +303,85 @@ fn change_delimiters_parse_filter() {
     Ast::from_str("{= strvar|e =}", None, &syntax).unwrap();
 }
 
+#[test]
+fn change_delimiters_parse_raw_expr() {
+    let syntax = Syntax {
+        raw_expr_start: Some("{{{"),
+        raw_expr_end: Some("}}}"),
+        ..Syntax::default()
+    };
+
+    assert_eq!(
+        Ast::from_str("{{ a }}{{{- b -}}}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![
+            Node::Expr(Ws(None, None), Expr::Var("a")),
+            Node::Expr(
+                Ws(Some(Whitespace::Suppress), Some(Whitespace::Suppress)),
+                Expr::Filter(Filter {
+                    name: "safe",
+                    arguments: vec![Expr::Var("b")],
+                }),
+            ),
+        ],
+    );
+    assert_eq!(
+        &*Ast::from_str("{{{ a }}", None, &syntax)
+            .unwrap_err()
+            .to_string(),
+        "failed to parse template source at row 1, column 6 near:\n\"}}\"",
+    );
+}
+
+#[test]
+fn test_parse_closure() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ a.filter(|x| x.b).c(|| d, e || f) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::Call(
+                Box::new(Expr::Attr(
+                    Box::new(Expr::Call(
+                        Box::new(Expr::Attr(Box::new(Expr::Var("a")), "filter")),
+                        vec![Expr::Closure(
+                            vec!["x"],
+                            Box::new(Expr::Attr(Box::new(Expr::Var("x")), "b")),
+                        )],
+                    )),
+                    "c",
+                )),
+                vec![
+                    Expr::Closure(vec![], Box::new(Expr::Var("d"))),
+                    Expr::BinOp("||", Box::new(Expr::Var("e")), Box::new(Expr::Var("f"))),
+                ],
+            ),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ a(|x, y| x + y) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::Call(
+                Box::new(Expr::Var("a")),
+                vec![Expr::Closure(
+                    vec!["x", "y"],
+                    Box::new(Expr::BinOp(
+                        "+",
+                        Box::new(Expr::Var("x")),
+                        Box::new(Expr::Var("y")),
+                    )),
+                )],
+            ),
+        )],
+    );
+}
+
 #[test]
 fn test_precedence() {
     let syntax = Syntax::default();
@@ -337,6 +416,24 @@ fn test_precedence() {
             )
         )],
     );
+    assert_eq!(
+        Ast::from_str("{{ a ~ b + c == d ~ e }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::BinOp(
+                "==",
+                Expr::BinOp(
+                    "~",
+                    Expr::Var("a").into(),
+                    Expr::BinOp("+", Expr::Var("b").into(), Expr::Var("c").into()).into(),
+                )
+                .into(),
+                Expr::BinOp("~", Expr::Var("d").into(), Expr::Var("e").into()).into(),
+            )
+        )],
+    );
     assert_eq!(
         Ast::from_str("{{ a * (b + c) / -d }}", None, &syntax)
             .unwrap()
@@ -378,6 +475,186 @@ fn test_precedence() {
     );
 }
 
+#[test]
+fn test_parse_if_else_expr() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ a ~ b if c || d else e if f else g }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::IfElse(
+                Expr::BinOp("||", Expr::Var("c").into(), Expr::Var("d").into()).into(),
+                Expr::BinOp("~", Expr::Var("a").into(), Expr::Var("b").into()).into(),
+                Expr::IfElse(
+                    Expr::Var("f").into(),
+                    Expr::Var("e").into(),
+                    Expr::Var("g").into(),
+                )
+                .into(),
+            )
+        )],
+    );
+
+    // Without `else`, the `if` is the condition of the loop.
+    let nodes = Ast::from_str("{% for x in xs if x %}{% endfor %}", None, &syntax)
+        .unwrap()
+        .nodes;
+    let Node::Loop(l) = &nodes[0] else {
+        panic!("expected a loop, got {nodes:?}");
+    };
+    assert_eq!(l.iter, Expr::Var("xs"));
+    assert_eq!(l.cond, Some(Expr::Var("x")));
+
+    assert!(Ast::from_str("{{ a if b }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ a if b else }}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_optional_chain() {
+    let syntax = Syntax::default();
+    let value = || Box::new(Expr::OptionalValue);
+    assert_eq!(
+        Ast::from_str("{{ a?.b.c()?.d }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::OptionalChain(
+                Expr::OptionalChain(
+                    Expr::Var("a").into(),
+                    Expr::Call(
+                        Expr::Attr(Expr::Attr(value(), "b").into(), "c").into(),
+                        vec![]
+                    )
+                    .into(),
+                )
+                .into(),
+                Expr::Attr(value(), "d").into(),
+            )
+        )],
+    );
+    // `?` followed by anything else than a field is still the `?` operator.
+    assert_eq!(
+        Ast::from_str("{{ a?[0] }}", None, &syntax).unwrap().nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            Expr::Index(
+                Expr::Try(Expr::Var("a").into()).into(),
+                Expr::NumLit("0").into()
+            )
+        )],
+    );
+}
+
+#[test]
+fn test_parse_match_guard() {
+    let syntax = Syntax::default();
+    let nodes = Ast::from_str(
+        "{% match a %}{% when Some(b) if b > 0 %}{% when _ %}{% endmatch %}",
+        None,
+        &syntax,
+    )
+    .unwrap()
+    .nodes;
+    let Node::Match(m) = &nodes[0] else {
+        panic!("expected a match, got {nodes:?}");
+    };
+    assert_eq!(
+        m.arms[0].guard,
+        Some(Expr::BinOp(
+            ">",
+            Expr::Var("b").into(),
+            Expr::NumLit("0").into()
+        ))
+    );
+    assert_eq!(m.arms[1].guard, None);
+    assert!(Ast::from_str(
+        "{% match a %}{% when Some(b) if %}{% endmatch %}",
+        None,
+        &syntax
+    )
+    .is_err());
+}
+
+#[test]
+fn test_parse_block_params() {
+    let syntax = Syntax::default();
+    let nodes = Ast::from_str(
+        "{% block card(title, level = 1) %}{% endblock %}{% block body scoped %}{% endblock %}",
+        None,
+        &syntax,
+    )
+    .unwrap()
+    .nodes;
+    let [Node::BlockDef(card), Node::BlockDef(body)] = &*nodes else {
+        panic!("expected two blocks, got {nodes:?}");
+    };
+    assert_eq!(
+        card.args,
+        [("title", None), ("level", Some(Expr::NumLit("1")))]
+    );
+    assert!(body.args.is_empty() && body.scoped);
+    assert!(Ast::from_str(
+        "{% block card(level = 1, title) %}{% endblock %}",
+        None,
+        &syntax
+    )
+    .is_err());
+}
+
+#[test]
+fn test_parse_nested_raw() {
+    let syntax = Syntax::default();
+    let nodes = Ast::from_str(
+        "{% raw %}a{% raw %}{{ b }}{% endraw %}c{%- endraw %}",
+        None,
+        &syntax,
+    )
+    .unwrap()
+    .nodes;
+    let [Node::Raw(raw)] = &*nodes else {
+        panic!("expected a raw block, got {nodes:?}");
+    };
+    assert_eq!(raw.lit.val, "a{% raw %}{{ b }}{% endraw %}c");
+    assert!(Ast::from_str("{% raw %}{% raw %}{% endraw %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_dynamic_extends() {
+    let syntax = Syntax::default();
+    let nodes = Ast::from_str(r#"{% extends "base.html" %}"#, None, &syntax)
+        .unwrap()
+        .nodes;
+    let [Node::Extends(extends)] = &*nodes else {
+        panic!("expected extends, got {nodes:?}");
+    };
+    assert_eq!(extends.paths, ["base.html"]);
+    assert_eq!(extends.selector, None);
+
+    let nodes = Ast::from_str(
+        r#"{% extends self.base() in ["a.html", "b.html",] %}"#,
+        None,
+        &syntax,
+    )
+    .unwrap()
+    .nodes;
+    let [Node::Extends(extends)] = &*nodes else {
+        panic!("expected extends, got {nodes:?}");
+    };
+    assert_eq!(extends.paths, ["a.html", "b.html"]);
+    assert_eq!(
+        extends.selector,
+        Some(Expr::Call(
+            Expr::Attr(Expr::Var("self").into(), "base").into(),
+            vec![]
+        ))
+    );
+
+    assert!(Ast::from_str(r#"{% extends base in "a.html" %}"#, None, &syntax).is_err());
+}
+
 #[test]
 fn test_associativity() {
     let syntax = Syntax::default();
@@ -628,6 +905,24 @@ fn test_parse_comments() {
     one_comment_ws("{# foo {# bar #} {# {# baz #} qux #} #}", Ws(None, None));
 }
 
+#[test]
+fn test_parse_debug_comments() {
+    fn debug_text(source: &str) -> Option<&str> {
+        let s = &Syntax::default();
+        match Ast::from_str(source, None, s).unwrap().nodes.pop().unwrap() {
+            Node::Comment(comment) => comment.debug,
+            node => panic!("expected a comment not, but parsed {:?}", node),
+        }
+    }
+
+    assert_eq!(debug_text("{# foo #}"), None);
+    assert_eq!(debug_text("{# foo debug: bar #}"), None);
+    assert_eq!(debug_text("{#debug:#}"), Some(""));
+    assert_eq!(debug_text("{# debug: foo.html #}"), Some("foo.html"));
+    assert_eq!(debug_text("{#- debug: foo\n bar -#}"), Some("foo\n bar"));
+    assert_eq!(debug_text("{#~\n  debug: foo ~#}"), Some("foo"));
+}
+
 #[test]
 fn test_parse_tuple() {
     let syntax = Syntax::default();
@@ -795,6 +1090,148 @@ fn test_parse_tuple() {
     );
 }
 
+#[test]
+fn test_parse_include_empty() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% include \"a.html\" %}\n{%- empty %}none{% endinclude -%}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Include(Include {
+            ws: Ws(None, None),
+            path: "a.html",
+            with: None,
+            empty: Some(IncludeEmpty {
+                ws1: Ws(Some(Whitespace::Suppress), None),
+                nodes: vec![Node::Lit(Lit {
+                    lws: "",
+                    val: "none",
+                    rws: "",
+                })],
+                ws2: Ws(None, Some(Whitespace::Suppress)),
+            }),
+        })],
+    );
+
+    // Without `{% empty %}`, the following nodes are not part of the include.
+    let nodes = Ast::from_str("{% include \"a.html\" %} {{ b }}", None, &syntax)
+        .unwrap()
+        .nodes;
+    assert!(matches!(
+        &nodes[0],
+        Node::Include(Include { empty: None, .. })
+    ));
+    assert_eq!(nodes.len(), 3);
+}
+
+#[test]
+fn test_parse_autoescape() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% autoescape false %}{{ a }}{%- autoescape true -%}{{ b }}{% endautoescape %}\
+             {% endautoescape %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Autoescape(Autoescape {
+            ws1: Ws(None, None),
+            enabled: false,
+            nodes: vec![
+                Node::Expr(Ws(None, None), Expr::Var("a")),
+                Node::Autoescape(Autoescape {
+                    ws1: Ws(Some(Whitespace::Suppress), Some(Whitespace::Suppress)),
+                    enabled: true,
+                    nodes: vec![Node::Expr(Ws(None, None), Expr::Var("b"))],
+                    ws2: Ws(None, None),
+                }),
+            ],
+            ws2: Ws(None, None),
+        })],
+    );
+
+    assert!(Ast::from_str("{% autoescape %}{% endautoescape %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% autoescape html %}{% endautoescape %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% autoescape true %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_macro_defaults() {
+    let syntax = Syntax::default();
+    let nodes = Ast::from_str(
+        "{% macro m(a, b = \"x\", c=b) %}{% endmacro %}",
+        None,
+        &syntax,
+    )
+    .unwrap()
+    .nodes;
+    let Node::Macro(m) = &nodes[0] else {
+        panic!("expected a macro, found {nodes:?}");
+    };
+    assert_eq!(
+        m.args,
+        [
+            ("a", None),
+            ("b", Some(Expr::StrLit("x"))),
+            ("c", Some(Expr::Var("b"))),
+        ]
+    );
+
+    let err = Ast::from_str("{% macro m(a = 1, b) %}{% endmacro %}", None, &syntax).unwrap_err();
+    assert!(err.to_string().starts_with(
+        "parameter `b` of macro `m` needs a default value, because it follows a parameter \
+         with a default value"
+    ));
+}
+
+#[test]
+fn test_parse_macro_uses() {
+    let syntax = Syntax::default();
+    for src in [
+        "{% macro m(a) uses b, c %}{% endmacro %}",
+        "{% macro m uses b , c %}{% endmacro %}",
+    ] {
+        let nodes = Ast::from_str(src, None, &syntax).unwrap().nodes;
+        let Node::Macro(m) = &nodes[0] else {
+            panic!("expected a macro, found {nodes:?}");
+        };
+        assert_eq!(m.uses, ["b", "c"]);
+    }
+    assert!(Ast::from_str("{% macro m() uses %}{% endmacro %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% macro m() uses b, %}{% endmacro %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_include_with() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% include \"a.html\" with user = u.name, b=true -%}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Include(Include {
+            ws: Ws(None, Some(Whitespace::Suppress)),
+            path: "a.html",
+            with: Some(vec![
+                ("user", Expr::Attr(Box::new(Expr::Var("u")), "name"),),
+                ("b", Expr::BoolLit("true")),
+            ]),
+            empty: None,
+        })],
+    );
+    assert!(Ast::from_str("{% include \"a.html\" with %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% include \"a.html\" with a %}", None, &syntax).is_err());
+}
+
 #[test]
 fn test_missing_space_after_kw() {
     let syntax = Syntax::default();
@@ -936,11 +1373,66 @@ fn fuzzed_comment_depth() {
 #[test]
 fn let_set() {
     assert_eq!(
-        Ast::from_str("{% let a %}", None, &Syntax::default())
+        Ast::from_str("{% let a = 1 %}", None, &Syntax::default())
             .unwrap()
             .nodes(),
-        Ast::from_str("{% set a %}", None, &Syntax::default())
+        Ast::from_str("{% set a = 1 %}", None, &Syntax::default())
             .unwrap()
             .nodes(),
     );
+    // Without a value, only `set` starts a block assignment.
+    assert!(Ast::from_str("{% let a %}", None, &Syntax::default()).is_ok());
+    assert!(Ast::from_str("{% set a %}", None, &Syntax::default()).is_err());
+    assert!(Ast::from_str("{% set a %}b{% endset %}", None, &Syntax::default()).is_ok());
+    assert!(Ast::from_str("{% let a %}b{% endlet %}", None, &Syntax::default()).is_err());
+}
+
+#[test]
+fn let_decl_linear() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let test = std::thread::spawn(move || {
+        let template = "{% let v %}{% let v = 1 %}x".repeat(30);
+        assert!(Ast::from_str(&template, None, &Syntax::default()).is_ok());
+        sender.send(()).unwrap();
+    });
+    receiver
+        .recv_timeout(std::time::Duration::from_secs(3))
+        .expect("timeout");
+    test.join().unwrap();
+}
+
+#[test]
+fn test_validate() {
+    use super::validate::{validate, Diagnostic};
+
+    let check = |src: &str, fields: &[&str]| validate(src, None, fields, &Syntax::default());
+    let unknown = |name: &str| Diagnostic::UnknownName(name.to_owned());
+
+    assert_eq!(check("{{ a }}{{ self.b }}{{ c.d }}", &["a", "b", "c"]), []);
+    assert_eq!(
+        check("{{ a }}{{ self.b }}{{ a }}{{ self.c() }}", &[]),
+        [unknown("a"), unknown("b")]
+    );
+    assert_eq!(
+        check(
+            "{% let x = a %}{% for (i, y) in x %}{{ i }}{{ y }}{{ loop.index }}{% endfor %}\
+             {{ i }}{% if let Some(z) = x %}{{ z }}{% endif %}{{ z }}",
+            &["a"]
+        ),
+        [unknown("i"), unknown("z")]
+    );
+    assert_eq!(
+        check(
+            "{% match a %}{% when Some with (b) if b > w %}{{ b }}{% when None %}{% endmatch %}\
+             {% macro m(c, d = c ~ y) %}{{ c }}{{ d }}{{ a }}{{ x }}{% endmacro %}\
+             {% set s %}{{ a }}{% endset %}{{ s }}\
+             {{ a.iter().filter(|v| v.ok)|count }}{{ v }}",
+            &["a"]
+        ),
+        [unknown("w"), unknown("y"), unknown("x"), unknown("v")]
+    );
+    assert!(matches!(
+        &*check("{% if a %}", &["a"]),
+        [Diagnostic::Parse(_)]
+    ));
 }
@@ -0,0 +1,289 @@
+//! Checks templates without generating their code, e.g. for linters, editors or pre-commit
+//! hooks.
+
+use std::path::Path;
+use std::rc::Rc;
+use std::{fmt, fs, io};
+
+use crate::node::{Target, When};
+use crate::{Ast, Expr, Node, ParseError, Syntax};
+
+/// A problem found by [`validate()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The template could not be parsed.
+    Parse(ParseError),
+    /// A variable is neither a field of the template struct nor defined in the template.
+    UnknownName(String),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Parse(err) => err.fmt(f),
+            Diagnostic::UnknownName(name) => write!(
+                f,
+                "unknown variable `{name}`: it is neither a field of the template struct nor \
+                 defined in the template"
+            ),
+        }
+    }
+}
+
+/// Parses `source` and checks that every variable it references is either one of the `fields`
+/// of the template struct, or defined in the template, e.g. by `{% let %}` or `{% for %}`
+///
+/// This is much faster than compiling the template, but it can only find a subset of the
+/// errors: the types of the fields are not known, so e.g. calls of missing methods or filters
+/// are not found. The templates which are included, extended or imported are not checked
+/// either, but they can be checked on their own. Within macro definitions, only the macro
+/// arguments and the `fields` are known.
+///
+/// If the template cannot be parsed, the parse error is the only returned diagnostic. Otherwise
+/// every unknown name is reported once, in the order of its first use.
+///
+/// ```
+/// use askama_parser::validate::{validate, Diagnostic};
+/// use askama_parser::Syntax;
+///
+/// let source = "{% for user in users %}{{ user.name }} {{ title }}{% endfor %}";
+/// let diagnostics = validate(source, None, &["users"], &Syntax::default());
+/// assert_eq!(diagnostics, [Diagnostic::UnknownName("title".into())]);
+/// ```
+pub fn validate(
+    source: &str,
+    file_path: Option<Rc<Path>>,
+    fields: &[&str],
+    syntax: &Syntax<'_>,
+) -> Vec<Diagnostic> {
+    let ast = match Ast::from_str(source, file_path, syntax) {
+        Ok(ast) => ast,
+        Err(err) => return vec![Diagnostic::Parse(err)],
+    };
+
+    let mut checker = Checker {
+        fields,
+        scopes: vec![Vec::new()],
+        diagnostics: Vec::new(),
+    };
+    checker.nodes(ast.nodes());
+    checker.diagnostics
+}
+
+/// Reads the template at `path` and checks it with [`validate()`]
+pub fn validate_file(
+    path: &Path,
+    fields: &[&str],
+    syntax: &Syntax<'_>,
+) -> io::Result<Vec<Diagnostic>> {
+    let source = fs::read_to_string(path)?;
+    Ok(validate(&source, Some(path.into()), fields, syntax))
+}
+
+struct Checker<'a, 'f> {
+    fields: &'f [&'f str],
+    scopes: Vec<Vec<&'a str>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a, '_> {
+    fn nodes(&mut self, nodes: &[Node<'a>]) {
+        for node in nodes {
+            match node {
+                Node::Expr(_, expr) => self.expr(expr),
+                Node::Call(call) => call.args.iter().for_each(|arg| self.expr(arg)),
+                Node::Let(l) => {
+                    if let Some(val) = &l.val {
+                        self.expr(val);
+                    }
+                    if let Some(block) = &l.block {
+                        self.scoped(|this| this.nodes(&block.nodes));
+                    }
+                    self.bind(&l.var);
+                }
+                Node::If(i) => {
+                    for branch in &i.branches {
+                        self.scoped(|this| {
+                            if let Some(cond) = &branch.cond {
+                                this.expr(&cond.expr);
+                                if let Some(target) = &cond.target {
+                                    this.bind(target);
+                                }
+                            }
+                            this.nodes(&branch.nodes);
+                        });
+                    }
+                }
+                Node::Match(m) => {
+                    self.expr(&m.expr);
+                    for When {
+                        target,
+                        guard,
+                        nodes,
+                        ..
+                    } in &m.arms
+                    {
+                        self.scoped(|this| {
+                            this.bind(target);
+                            if let Some(guard) = guard {
+                                this.expr(guard);
+                            }
+                            this.nodes(nodes);
+                        });
+                    }
+                }
+                Node::Loop(l) => {
+                    self.expr(&l.iter);
+                    self.scoped(|this| {
+                        this.bind(&l.var);
+                        this.scopes.last_mut().unwrap().push("loop");
+                        if let Some(cond) = &l.cond {
+                            this.expr(cond);
+                        }
+                        this.nodes(&l.body);
+                    });
+                    self.scoped(|this| this.nodes(&l.else_nodes));
+                }
+                Node::BlockDef(b) => self.scoped(|this| {
+                    for (arg, default) in &b.args {
+                        if let Some(default) = default {
+                            this.expr(default);
+                        }
+                        this.scopes.last_mut().unwrap().push(arg);
+                    }
+                    this.nodes(&b.nodes);
+                }),
+                Node::Include(i) => {
+                    for (_, value) in i.with.iter().flatten() {
+                        self.expr(value);
+                    }
+                    if let Some(empty) = &i.empty {
+                        self.scoped(|this| this.nodes(&empty.nodes));
+                    }
+                }
+                Node::Macro(m) => {
+                    let scopes = std::mem::replace(&mut self.scopes, vec![Vec::new()]);
+                    for (arg, default) in &m.args {
+                        if let Some(default) = default {
+                            self.expr(default);
+                        }
+                        self.scopes[0].push(arg);
+                    }
+                    self.nodes(&m.nodes);
+                    self.scopes = scopes;
+                }
+                Node::FilterBlock(f) => {
+                    f.filters.arguments.iter().for_each(|arg| self.expr(arg));
+                    self.scoped(|this| this.nodes(&f.nodes));
+                }
+                Node::Autoescape(a) => self.scoped(|this| this.nodes(&a.nodes)),
+                Node::Extends(e) => {
+                    if let Some(selector) = &e.selector {
+                        self.expr(selector);
+                    }
+                }
+                Node::Lit(_)
+                | Node::Comment(_)
+                | Node::Embed(_)
+                | Node::Import(_)
+                | Node::Raw(_)
+                | Node::Break(_)
+                | Node::Continue(_)
+                | Node::Debug(_) => {}
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'a>) {
+        match expr {
+            Expr::Var("self") => {}
+            Expr::Var(name) => self.name(name),
+            Expr::Attr(obj, attr) => match &**obj {
+                Expr::Var("self") => self.name(attr),
+                obj => self.expr(obj),
+            },
+            // `self.method()` calls a method, which is not a field.
+            Expr::Call(callee, args) => {
+                match &**callee {
+                    Expr::Attr(obj, _) => self.expr(obj),
+                    callee => self.expr(callee),
+                }
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            Expr::Closure(params, body) => {
+                self.scoped(|this| {
+                    this.scopes.last_mut().unwrap().extend(params);
+                    this.expr(body);
+                });
+            }
+            Expr::Array(exprs) | Expr::Tuple(exprs) => exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Filter(filter) => filter.arguments.iter().for_each(|arg| self.expr(arg)),
+            Expr::Index(obj, index) => {
+                self.expr(obj);
+                self.expr(index);
+            }
+            Expr::OptionalChain(obj, rest) => {
+                self.expr(obj);
+                self.expr(rest);
+            }
+            Expr::IfElse(cond, then, otherwise) => {
+                self.expr(cond);
+                self.expr(then);
+                self.expr(otherwise);
+            }
+            Expr::BinOp(_, left, right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Range(_, left, right) => {
+                left.iter().chain(right).for_each(|e| self.expr(e));
+            }
+            Expr::NamedArgument(_, expr)
+            | Expr::Unary(_, expr)
+            | Expr::Group(expr)
+            | Expr::Try(expr) => self.expr(expr),
+            Expr::BoolLit(_)
+            | Expr::NumLit(_)
+            | Expr::StrLit(_)
+            | Expr::CharLit(_)
+            | Expr::Path(_)
+            | Expr::RustMacro(..)
+            | Expr::OptionalValue
+            | Expr::Generated(_) => {}
+        }
+    }
+
+    fn name(&mut self, name: &str) {
+        let known = self.fields.contains(&name)
+            || self.scopes.iter().any(|scope| scope.contains(&name))
+            || self.diagnostics.iter().any(|d| match d {
+                Diagnostic::UnknownName(unknown) => unknown == name,
+                Diagnostic::Parse(_) => false,
+            });
+        if !known {
+            self.diagnostics
+                .push(Diagnostic::UnknownName(name.to_owned()));
+        }
+    }
+
+    fn bind(&mut self, target: &Target<'a>) {
+        match target {
+            Target::Name(name) => self.scopes.last_mut().unwrap().push(name),
+            Target::Tuple(_, targets) | Target::OrChain(targets) => {
+                targets.iter().for_each(|target| self.bind(target));
+            }
+            Target::Struct(_, targets) => targets.iter().for_each(|(_, target)| self.bind(target)),
+            Target::NumLit(_)
+            | Target::StrLit(_)
+            | Target::CharLit(_)
+            | Target::BoolLit(_)
+            | Target::Path(_) => {}
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(Vec::new());
+        f(self);
+        self.scopes.pop();
+    }
+}
@@ -10,6 +10,38 @@ criterion_group!(benches, functions);
 fn functions(c: &mut Criterion) {
     c.bench_function("Big table", |b| big_table(b, 100));
     c.bench_function("Teams", teams);
+    c.bench_function("Small grid", |b| {
+        b.iter(|| {
+            SmallGrid {
+                cells: [[1, 2, 3]; 3],
+            }
+            .render()
+            .unwrap()
+        })
+    });
+    c.bench_function("Small grid (unrolled)", |b| {
+        b.iter(|| {
+            UnrolledSmallGrid {
+                cells: [[1, 2, 3]; 3],
+            }
+            .render()
+            .unwrap()
+        })
+    });
+    c.bench_function("Repeated calls", |b| {
+        let tmpl = RepeatedCalls {
+            user: "Ferris".into(),
+            year: 2015,
+        };
+        b.iter(|| tmpl.render().unwrap())
+    });
+    c.bench_function("Repeated calls (deduplicated)", |b| {
+        let tmpl = DedupCalls {
+            user: "Ferris".into(),
+            year: 2015,
+        };
+        b.iter(|| tmpl.render().unwrap())
+    });
 }
 
 fn big_table(b: &mut criterion::Bencher, size: usize) {
@@ -67,3 +99,49 @@ struct Team {
     name: String,
     score: u8,
 }
+
+#[derive(Template)]
+#[template(path = "small-grid.html")]
+struct SmallGrid {
+    cells: [[u8; 3]; 3],
+}
+
+#[derive(Template)]
+#[template(path = "small-grid.html", config = "test_unroll.toml")]
+struct UnrolledSmallGrid {
+    cells: [[u8; 3]; 3],
+}
+
+#[derive(Template)]
+#[template(path = "repeated-calls.html")]
+struct RepeatedCalls {
+    user: String,
+    year: u16,
+}
+
+impl RepeatedCalls {
+    fn title(&self) -> String {
+        site_title(&self.user)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "repeated-calls.html", config = "test_dedup.toml")]
+struct DedupCalls {
+    user: String,
+    year: u16,
+}
+
+impl DedupCalls {
+    fn title(&self) -> String {
+        site_title(&self.user)
+    }
+}
+
+fn site_title(user: &str) -> String {
+    let mut title = String::from("The Askama Book Club");
+    for _ in 0..user.len() {
+        title = title.replace("  ", " ");
+    }
+    title
+}
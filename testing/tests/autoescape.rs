@@ -0,0 +1,74 @@
+use std::fmt::Write;
+
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{{ s }} {% autoescape false %}{{ s }} {{ s|e }} \
+              {% autoescape true %}{{ s }}{% endautoescape %} {{ s }}{% endautoescape %} {{ s }}",
+    ext = "html"
+)]
+struct Nested<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_autoescape_nested() {
+    let t = Nested { s: "<b>" };
+    assert_eq!(
+        t.render().unwrap(),
+        "&lt;b&gt; <b> &lt;b&gt; &lt;b&gt; <b> &lt;b&gt;"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% autoescape false -%}
+    {% filter upper %}{{ s }}{% endfilter %} {% include \"hello.html\" %}
+{%- endautoescape %}",
+    ext = "html"
+)]
+struct FilterAndInclude<'a> {
+    s: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_autoescape_filter_and_include() {
+    let t = FilterAndInclude {
+        s: "<b>",
+        name: "<i>",
+    };
+    assert_eq!(t.render().unwrap(), "<B> Hello, <i>!");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% autoescape false %}{{ s }}{% autoescape true %} {{ s }}{% endautoescape %}\
+              {% endautoescape %}",
+    ext = "js",
+    config = "test_js_escaper.toml"
+)]
+struct ConfiguredEscaper<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_autoescape_configured_escaper() {
+    let t = ConfiguredEscaper { s: "it's" };
+    assert_eq!(t.render().unwrap(), r"it's it\u0027s");
+}
+
+struct JsEscaper;
+
+impl askama::Escaper for JsEscaper {
+    fn write_escaped<W: Write>(&self, mut fmt: W, string: &str) -> std::fmt::Result {
+        for c in string.chars() {
+            match c {
+                '"' | '\'' | '\\' | '<' | '>' | '&' => write!(fmt, "\\u{:04x}", c as u32)?,
+                c => fmt.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
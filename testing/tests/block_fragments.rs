@@ -115,3 +115,26 @@ fn test_fragment_include() {
     let fragment_include = FragmentInclude { s: "world" };
     assert_eq!(fragment_include.render().unwrap(), "\nINCLUDED: world\n");
 }
+
+#[derive(Template)]
+#[template(path = "fragment-pruned.html", block = "content")]
+struct FragmentPrunedContent<'a> {
+    title: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "fragment-pruned.html", block = "nav")]
+struct FragmentPrunedNav<'a> {
+    nav_title: &'a str,
+}
+
+/// The fields used by the loops, conditions and variables of the template outside of the block
+/// are not required.
+#[test]
+fn test_fragment_pruned_fields() {
+    let content = FragmentPrunedContent { title: "Title" };
+    assert_eq!(content.render().unwrap(), "Title [Title]");
+
+    let nav = FragmentPrunedNav { nav_title: "Nav" };
+    assert_eq!(nav.render().unwrap(), "Nav");
+}
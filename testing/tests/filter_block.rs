@@ -118,6 +118,23 @@ fn filter_block_html_escape() {
     assert_eq!(template.render().unwrap(), r#"&lt;block&gt;"#);
 }
 
+// This test ensures that the expressions in the block are escaped when they are rendered, so
+// `safe` only prevents the result of the filter from being escaped again.
+#[derive(Template)]
+#[template(
+    source = r#"{% filter lower|safe %}<p>{{ s }}</p>{% endfilter %}"#,
+    ext = "html"
+)]
+struct SafeResult<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn filter_block_safe_result() {
+    let template = SafeResult { s: "<B>&" };
+    assert_eq!(template.render().unwrap(), "<p>&lt;b&gt;&amp;</p>");
+}
+
 // This test ensures that it is not escaped if it is not HTML.
 #[derive(Template)]
 #[template(source = r#"{% filter lower %}<block>{% endfilter %}"#, ext = "txt")]
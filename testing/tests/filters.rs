@@ -123,7 +123,7 @@ mod filters {
         Ok(s.replace("oo", "aa"))
     }
     // for test_nested_filter_ref
-    pub fn mytrim(s: &dyn (::std::fmt::Display)) -> ::askama::Result<String> {
+    pub fn mytrim(s: &dyn ::std::fmt::Display) -> ::askama::Result<String> {
         Ok(s.to_string().trim().to_owned())
     }
 }
@@ -134,6 +134,22 @@ fn test_my_filter() {
     assert_eq!(t.render().unwrap(), "faa");
 }
 
+#[derive(Template)]
+#[template(
+    source = "{{ s|myfilter|upper }}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct StrictFilterTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_strict_custom_filter() {
+    let t = StrictFilterTemplate { s: "foo" };
+    assert_eq!(t.render().unwrap(), "FAA");
+}
+
 #[derive(Template)]
 #[template(path = "filters_join.html")]
 struct JoinTemplate<'a> {
@@ -247,7 +263,31 @@ fn test_filter_truncate() {
     let t = TruncateFilter {
         foo: "alpha bar".into(),
     };
-    assert_eq!(t.render().unwrap(), "alpha baralpha...");
+    assert_eq!(t.render().unwrap(), "alpha baralpha…");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ s|truncate(8) }}|{{ s|truncate(8, true) }}|{{ s|truncate(8, false, "...") }}|{{ s|truncate(8, killwords=kill, ellipsis=end) }}|{{ s|truncate(0) }}|{{ s|truncate(100) }}"#,
+    ext = "txt"
+)]
+struct TruncateWordsFilter<'a> {
+    s: &'a str,
+    kill: bool,
+    end: &'a str,
+}
+
+#[test]
+fn test_filter_truncate_words() {
+    let t = TruncateWordsFilter {
+        s: "alpha bravo",
+        kill: true,
+        end: " [more]",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "alpha…|alpha br…|alpha...|alpha br [more]|…|alpha bravo"
+    );
 }
 
 #[cfg(feature = "serde-json")]
@@ -288,6 +328,37 @@ fn test_json_attribute2() {
     );
 }
 
+#[cfg(feature = "serde-json")]
+#[derive(Template)]
+#[template(
+    source = "<pre>{{ data|json(4)|safe }}</pre>{{ data|json(0) }}",
+    ext = "html"
+)]
+struct JsonPrettyTemplate<'a> {
+    data: &'a Value,
+}
+
+#[cfg(feature = "serde-json")]
+#[test]
+fn test_json_pretty() {
+    let data = json!({"a": ["<b>", 1]});
+    let t = JsonPrettyTemplate { data: &data };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<pre>{
+    "a": [
+        "\u003cb\u003e",
+        1
+    ]
+}</pre>{
+&quot;a&quot;: [
+&quot;\u003cb\u003e&quot;,
+1
+]
+}"#
+    );
+}
+
 #[cfg(feature = "serde-json")]
 #[derive(Template)]
 #[template(
@@ -328,3 +399,1187 @@ fn test_let_borrow() {
     };
     assert_eq!(template.render().unwrap(), "hello1")
 }
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{% for pair in items|windows(2) %}{{ pair[0] }}->{{ pair[1] }};{% endfor %}"#,
+    ext = "txt"
+)]
+struct WindowsTemplate {
+    items: Vec<&'static str>,
+}
+
+#[test]
+fn test_windows() {
+    let template = WindowsTemplate {
+        items: vec!["a", "b", "c"],
+    };
+    assert_eq!(template.render().unwrap(), "a->b;b->c;");
+
+    let template = WindowsTemplate { items: vec!["a"] };
+    assert_eq!(template.render().unwrap(), "");
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{% for (i, item) in items|enumerate %}{{ i + 1 }}. {{ item }}
+{% endfor %}"#,
+    ext = "txt"
+)]
+struct EnumerateTemplate {
+    items: Vec<&'static str>,
+}
+
+#[test]
+fn test_enumerate() {
+    let template = EnumerateTemplate {
+        items: vec!["foo", "bar", "baz"],
+    };
+    assert_eq!(template.render().unwrap(), "1. foo\n2. bar\n3. baz\n");
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = "{% for i in (0..10)|skip(2)|take(3) %}{{ i }} {% endfor %}\
+              {% for (i, item) in items|skip(1)|enumerate %}{{ i }}={{ item }} {% endfor %}\
+              {% for item in items|take(2) %}{{ item }} {% endfor %}",
+    ext = "txt"
+)]
+struct TakeSkipTemplate {
+    items: Vec<&'static str>,
+}
+
+#[test]
+fn test_take_skip() {
+    let template = TakeSkipTemplate {
+        items: vec!["foo", "bar", "baz"],
+    };
+    assert_eq!(template.render().unwrap(), "2 3 4 0=bar 1=baz foo bar ");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ price|format_float(2) }} {{ weight|format_float(3, trim=true) }} \
+              {{ 1e21|format_float(1, false) }} {{ -0.0001|format_float(2) }}",
+    ext = "txt"
+)]
+struct FormatFloatTemplate {
+    price: f64,
+    weight: f32,
+}
+
+#[test]
+fn test_format_float() {
+    let t = FormatFloatTemplate {
+        price: 19.5,
+        weight: 0.25,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "19.50 0.25 1000000000000000000000.0 0.00"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for item in items %}{{ item|hang_indent(\"- \", \"  \") }}\n{% endfor %}",
+    ext = "txt"
+)]
+struct HangIndentTemplate {
+    items: Vec<&'static str>,
+}
+
+#[test]
+fn test_hang_indent() {
+    let template = HangIndentTemplate {
+        items: vec!["first line\nsecond line\nthird line", "other"],
+    };
+    assert_eq!(
+        template.render().unwrap(),
+        "- first line\n  second line\n  third line\n- other\n"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ name|prefix(\"@\") }}|{{ name|suffix(\":\") }}|{{ label|wrap(\"(\", \")\") }}",
+    ext = "txt"
+)]
+struct AffixTemplate<'a> {
+    name: &'a str,
+    label: &'a str,
+}
+
+#[test]
+fn test_affixes() {
+    let template = AffixTemplate {
+        name: "alice",
+        label: "admin",
+    };
+    assert_eq!(template.render().unwrap(), "@alice|alice:|(admin)");
+    let template = AffixTemplate {
+        name: "",
+        label: "",
+    };
+    assert_eq!(template.render().unwrap(), "||");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for (label, value) in labels|zip(values) %}{{ label }}={{ value }};{% endfor %}
+{% for (i, value) in (0..)|zip(values|skip(1)) %}{{ i }}={{ value }};{% endfor %}",
+    ext = "txt"
+)]
+struct ZipTemplate<'a> {
+    labels: Vec<&'a str>,
+    values: Vec<u32>,
+}
+
+#[test]
+fn test_zip() {
+    let template = ZipTemplate {
+        labels: vec!["a", "b", "c"],
+        values: vec![1, 2],
+    };
+    assert_eq!(template.render().unwrap(), "a=1;b=2;\n0=2;");
+}
+
+#[derive(Template)]
+#[template(source = "{{ s|title }} {{ s|title_segments }}", ext = "txt")]
+struct TitleSegmentsTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_title_segments() {
+    let template = TitleSegmentsTemplate { s: "new-york/us" };
+    assert_eq!(template.render().unwrap(), "New-york/us New-York/Us");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ bytes|hex }} {{ bytes|hex(upper=true) }} {{ s|hex|unhex }}",
+    ext = "txt"
+)]
+struct HexFilter<'a> {
+    bytes: Vec<u8>,
+    s: &'a str,
+}
+
+#[test]
+fn test_hex_filter() {
+    let t = HexFilter {
+        bytes: vec![0xde, 0xad, 0x01],
+        s: "<round trip>",
+    };
+    assert_eq!(t.render().unwrap(), "dead01 DEAD01 <round trip>");
+}
+
+#[derive(Template)]
+#[template(source = "{{ s|unhex }}", ext = "txt")]
+struct UnhexFilter<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_unhex_filter() {
+    assert_eq!(UnhexFilter { s: "4f6b" }.render().unwrap(), "Ok");
+    assert!(UnhexFilter { s: "4f6" }.render().is_err());
+    assert!(UnhexFilter { s: "4g" }.render().is_err());
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ n|thousands }} {{ -1234.5|thousands }} {{ price|format_float(2)|currency(cur) }}"#,
+    ext = "txt"
+)]
+struct LocaleFilters<'a> {
+    n: u64,
+    price: f64,
+    cur: &'a str,
+}
+
+#[test]
+fn test_locale_filters() {
+    let t = LocaleFilters {
+        n: u64::MAX,
+        price: 1234.5,
+        cur: "EUR",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "18,446,744,073,709,551,615 -1,234.5 1,234.50 EUR"
+    );
+}
+
+struct Reversed;
+
+impl askama::LocaleFormatter for Reversed {
+    fn thousands(number: impl std::fmt::Display) -> askama::Result<String> {
+        Ok(number.to_string().chars().rev().collect())
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ 1234|thousands }} {{ 56|currency("CHF") }}"#,
+    ext = "txt",
+    config = "test_formatter.toml"
+)]
+struct CustomFormatter;
+
+#[test]
+fn test_custom_formatter() {
+    assert_eq!(CustomFormatter.render().unwrap(), "4321 65 CHF");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ hash|truncate_middle(9) }} {{ short|truncate_middle(9) }}",
+    ext = "html"
+)]
+struct TruncateMiddleFilter<'a> {
+    hash: &'a str,
+    short: &'a str,
+}
+
+#[test]
+fn test_truncate_middle_filter() {
+    let t = TruncateMiddleFilter {
+        hash: "0123456789abcdef",
+        short: "<short>",
+    };
+    assert_eq!(t.render().unwrap(), "0123…cdef &lt;short&gt;");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ nickname|default("Anonymous") }}|{{ city|default("N/A") }}|{{ city|default("N/A", strip=true) }}|{{ tabs|default("-", true) }}"#,
+    ext = "html"
+)]
+struct DefaultFilter<'a> {
+    nickname: Option<String>,
+    city: &'a str,
+    tabs: String,
+}
+
+#[test]
+fn test_default_filter() {
+    let t = DefaultFilter {
+        nickname: None,
+        city: "   ",
+        tabs: "\t\t".into(),
+    };
+    assert_eq!(t.render().unwrap(), "Anonymous|   |N/A|-");
+
+    let t = DefaultFilter {
+        nickname: Some("<b>".into()),
+        city: " Paris ",
+        tabs: "x".into(),
+    };
+    assert_eq!(t.render().unwrap(), "&lt;b&gt;| Paris | Paris |x");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ count|default(1, boolean=true) }} {{ count|default(1) }} {{ active|default("off", boolean=true) }} {{ name|default("?", strip=true, boolean=true) }} {{ name|default("?", false, true) }}"#,
+    ext = "txt"
+)]
+struct DefaultBooleanFilter<'a> {
+    count: u32,
+    active: bool,
+    name: Option<&'a str>,
+}
+
+#[test]
+fn test_default_filter_boolean() {
+    let t = DefaultBooleanFilter {
+        count: 0,
+        active: false,
+        name: Some(" "),
+    };
+    assert_eq!(t.render().unwrap(), "1 0 off ?  ");
+
+    let t = DefaultBooleanFilter {
+        count: 3,
+        active: true,
+        name: None,
+    };
+    assert_eq!(t.render().unwrap(), "3 3 true ? ?");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ active|yesno("<on>,off") }} {{ known|yesno(labels) }} {{ unknown|yesno(labels) }}"#,
+    ext = "html"
+)]
+struct YesNoFilter<'a> {
+    active: bool,
+    known: Option<bool>,
+    unknown: Option<bool>,
+    labels: &'a str,
+}
+
+#[test]
+fn test_yesno_filter() {
+    let t = YesNoFilter {
+        active: true,
+        known: Some(false),
+        unknown: None,
+        labels: "yes,no,maybe",
+    };
+    assert_eq!(t.render().unwrap(), "&lt;on&gt; no maybe");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% filter spaceless|safe %}<ul>\n  <li>{{ a }}</li>\n  <li> {{ b }} </li>\n</ul>{% endfilter %}",
+    ext = "html"
+)]
+struct SpacelessFilter<'a> {
+    a: &'a str,
+    b: &'a str,
+}
+
+#[test]
+fn test_spaceless_filter() {
+    let t = SpacelessFilter {
+        a: "x > y",
+        b: "<b>",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<ul><li>x &gt; y</li><li> &lt;b&gt; </li></ul>"
+    );
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{% for column in items|columns(3) %}[{% for item in column %}{{ item }}{% endfor %}]{% endfor %}"#,
+    ext = "txt"
+)]
+struct ColumnsTemplate {
+    items: Vec<&'static str>,
+}
+
+#[test]
+fn test_columns() {
+    let template = ColumnsTemplate {
+        items: vec!["a", "b", "c", "d", "e", "f", "g"],
+    };
+    assert_eq!(template.render().unwrap(), "[abc][de][fg]");
+
+    let template = ColumnsTemplate {
+        items: vec!["a", "b"],
+    };
+    assert_eq!(template.render().unwrap(), "[a][b][]");
+
+    let template = ColumnsTemplate { items: vec![] };
+    assert_eq!(template.render().unwrap(), "[][][]");
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = "{{ label|snake }} {{ label|kebab }} {{ label|camel }} {{ label|pascal }}",
+    ext = "txt"
+)]
+struct CaseConversions<'a> {
+    label: &'a str,
+}
+
+#[test]
+fn test_case_conversions() {
+    let t = CaseConversions { label: "user name" };
+    assert_eq!(t.render().unwrap(), "user_name user-name userName UserName");
+
+    let t = CaseConversions { label: "userName" };
+    assert_eq!(t.render().unwrap(), "user_name user-name userName UserName");
+}
+
+struct User {
+    name: &'static str,
+}
+
+impl User {
+    fn truncated_name(&self, len: usize) -> &str {
+        &self.name[..self.name.len().min(len)]
+    }
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{% for name in users|map("name") %}{{ name }};{% endfor %}
+{{ users|map("truncated_name", 3)|join(", ") }}
+{{ users|map("truncated_name", 1)|join(", ") }}"#,
+    ext = "txt"
+)]
+struct MapTemplate {
+    users: Vec<User>,
+}
+
+#[test]
+fn test_map() {
+    let template = MapTemplate {
+        users: vec![User { name: "Alice" }, User { name: "Bob" }],
+    };
+    assert_eq!(template.render().unwrap(), "Alice;Bob;\nAli, Bob\nA, B");
+}
+
+#[derive(askama::Template)]
+#[template(source = "/posts/{{ title|slugify }}", ext = "html")]
+struct SlugifyTemplate<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_slugify() {
+    let t = SlugifyTemplate {
+        title: "Hello, World! 2024",
+    };
+    assert_eq!(t.render().unwrap(), "/posts/hello-world-2024");
+
+    let t = SlugifyTemplate { title: "<&>" };
+    assert_eq!(t.render().unwrap(), "/posts/");
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{{ text|replace("a", "o") }} {{ text|replace(from, "-", 1) }} {{ text|replace("a", to, count=n) }}"#,
+    ext = "txt"
+)]
+struct ReplaceTemplate<'a> {
+    text: String,
+    from: &'a str,
+    to: &'a str,
+    n: usize,
+}
+
+#[test]
+fn test_replace() {
+    let t = ReplaceTemplate {
+        text: "banana".to_owned(),
+        from: "an",
+        to: "A",
+        n: 2,
+    };
+    assert_eq!(t.render().unwrap(), "bonono b-ana bAnAna");
+}
+
+#[derive(askama::Template)]
+#[template(
+    source = r#"{% for part in line|split(",") %}[{{ part }}]{% endfor %} {{ line|split(sep)|join("+") }}"#,
+    ext = "txt"
+)]
+struct SplitTemplate<'a> {
+    line: String,
+    sep: &'a str,
+}
+
+#[test]
+fn test_split() {
+    let t = SplitTemplate {
+        line: "a,b,,c".to_owned(),
+        sep: ",",
+    };
+    assert_eq!(t.render().unwrap(), "[a][b][][c] a+b++c");
+
+    let t = SplitTemplate {
+        line: "a".to_owned(),
+        sep: "",
+    };
+    assert!(t.render().is_err());
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for n in counts %}{{ n }} item{{ n|pluralize }}, box{{ n|pluralize("es") }}, cherr{{ n|pluralize("y", "ies") }}; {% endfor %}{{ names.len() }} name{{ names.len()|pluralize }}"#,
+    ext = "txt"
+)]
+struct PluralizeFilter<'a> {
+    counts: &'a [i32],
+    names: Vec<&'a str>,
+}
+
+#[test]
+fn test_pluralize_filter() {
+    let t = PluralizeFilter {
+        counts: &[0, 1, 2],
+        names: vec!["a"],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "0 items, boxes, cherries; 1 item, box, cherry; 2 items, boxes, cherries; 1 name"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ big|numberformat }} {{ price|numberformat(2) }} {{ price|numberformat(2, ",", ".") }} {{ neg|numberformat(1, ".", " ") }}"#,
+    ext = "txt"
+)]
+struct NumberformatFilter {
+    big: u64,
+    price: f64,
+    neg: i32,
+}
+
+#[test]
+fn test_numberformat_filter() {
+    let t = NumberformatFilter {
+        big: 9_007_199_254_740_993,
+        price: 1234.5,
+        neg: -1234567,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "9,007,199,254,740,993 1,234.50 1.234,50 -1 234 567.0"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ x|round }} {{ x|round(1) }} {{ y|round(0, "ceil") }} {{ x|round(precision=2, method="floor") }} {{ x|round(2, method) }}"#,
+    ext = "txt"
+)]
+struct RoundFilter<'a> {
+    x: f64,
+    y: f32,
+    method: &'a str,
+}
+
+#[test]
+fn test_round_filter() {
+    let t = RoundFilter {
+        x: 2.567,
+        y: 2.1,
+        method: "ceil",
+    };
+    assert_eq!(t.render().unwrap(), "3 2.6 3 2.56 2.57");
+
+    let t = RoundFilter {
+        x: f64::NAN,
+        y: f32::INFINITY,
+        method: "unknown",
+    };
+    assert!(t.render().is_err());
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ body|striptags }}|{{ body|striptags|safe }}",
+    ext = "html"
+)]
+struct StriptagsFilter<'a> {
+    body: &'a str,
+}
+
+#[test]
+fn test_striptags_filter() {
+    let t = StriptagsFilter {
+        body: "<h1>Title</h1>\n\n<p>Fish &amp; chips,\n  <em>1 < 2</em></p>",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Title Fish &amp;amp; chips, 1 &lt; 2|Title Fish &amp; chips, 1 < 2"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ text|wordwrap }}|{{ text|wordwrap(10) }}|{{ text|wordwrap(width=5, break_long_words=true) }}",
+    ext = "txt"
+)]
+struct WordwrapFilter<'a> {
+    text: &'a str,
+}
+
+#[test]
+fn test_wordwrap_filter() {
+    let t = WordwrapFilter {
+        text: "Dear Ferris,\nthanks for everything",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Dear Ferris,\nthanks for everything|Dear\nFerris,\nthanks for\neverything|Dear\nFerri\ns,\nthank\ns for\nevery\nthing"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ items|first|default(0) }} {{ items|last|default(0) }} {{ name|first|default('-') }}{{ name|last|default('-') }} {% for row in rows %}{% if let Some(x) = row|last %}{{ x }}{% else %}_{% endif %}{% endfor %} {{ (1..4)|last|default(0) }} {{ name|split(" ")|last|default("") }}"#,
+    ext = "txt"
+)]
+struct FirstLastFilter<'a> {
+    items: &'a [i32],
+    name: String,
+    rows: Vec<Vec<&'a str>>,
+}
+
+#[test]
+fn test_first_last_filter() {
+    let t = FirstLastFilter {
+        items: &[4, 5, 6],
+        name: "Ferris Crab".into(),
+        rows: vec![vec!["a", "b"], vec![], vec!["c"]],
+    };
+    assert_eq!(t.render().unwrap(), "4 6 Fb b_c 3 Crab");
+
+    let t = FirstLastFilter {
+        items: &[],
+        name: String::new(),
+        rows: vec![],
+    };
+    assert_eq!(t.render().unwrap(), "0 0 --  3 ");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for row in items|batch(size) %}[{% for cell in row %}{{ cell }}{% endfor %}]{% endfor %} {% for row in items|batch(3, fill) %}[{% for cell in row %}{{ cell }}{% endfor %}]{% endfor %} {% for row in (1..6)|batch(2, 0) %}{{ row.len() }}{% endfor %}"#,
+    ext = "html"
+)]
+struct BatchFilter<'a> {
+    items: Vec<&'a str>,
+    size: usize,
+    fill: &'a str,
+}
+
+#[test]
+fn test_batch_filter() {
+    let t = BatchFilter {
+        items: vec!["a", "b", "c", "d"],
+        size: 2,
+        fill: "<_>",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "[ab][cd] [abc][d&lt;_&gt;&lt;_&gt;] 222"
+    );
+
+    let t = BatchFilter {
+        items: vec![],
+        size: 0,
+        fill: "",
+    };
+    assert!(t.render().is_err());
+}
+
+struct Address {
+    city: &'static str,
+}
+
+struct Person {
+    name: &'static str,
+    age: u32,
+    address: Address,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ numbers|sort|join(",") }} {{ numbers|sort(reverse=true)|join(",") }} {% for p in people|sort_by("age") %}{{ p.name }}{% endfor %} {% for p in people|sort_by("age", true) %}{{ p.name }}{% endfor %} {% for p in people|sort_by("address.city", reverse=true) %}{{ p.name }}{% endfor %}"#,
+    ext = "txt"
+)]
+struct SortFilter {
+    numbers: Vec<i32>,
+    people: Vec<Person>,
+}
+
+#[test]
+fn test_sort_filter() {
+    let person = |name, age, city| Person {
+        name,
+        age,
+        address: Address { city },
+    };
+    let t = SortFilter {
+        numbers: vec![3, -1, 2],
+        people: vec![
+            person("A", 30, "Oslo"),
+            person("B", 20, "Bern"),
+            person("C", 30, "Rome"),
+            person("D", 20, "Oslo"),
+        ],
+    };
+    assert_eq!(t.render().unwrap(), "-1,2,3 3,2,-1 BDAC ACBD CADB");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for (name, _) in pairs|sort_by("1") %}{{ name }}{% endfor %}"#,
+    ext = "txt"
+)]
+struct SortByTupleIndex<'a> {
+    pairs: Vec<(&'a str, u32)>,
+}
+
+#[test]
+fn test_sort_by_tuple_index() {
+    let t = SortByTupleIndex {
+        pairs: vec![("a", 3), ("b", 1), ("c", 2)],
+    };
+    assert_eq!(t.render().unwrap(), "bca");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ tags|unique|join(", ") }} {% for user in users|unique("name") %}{{ user.name }};{% endfor %} {{ users|map("name")|unique|join("") }}"#,
+    ext = "txt"
+)]
+struct UniqueFilter<'a> {
+    tags: Vec<&'a str>,
+    users: Vec<User>,
+}
+
+#[test]
+fn test_unique_filter() {
+    let t = UniqueFilter {
+        tags: vec!["rust", "web", "rust", "async", "web"],
+        users: vec![
+            User { name: "Bob" },
+            User { name: "Alice" },
+            User { name: "Bob" },
+        ],
+    };
+    assert_eq!(t.render().unwrap(), "rust, web, async Bob;Alice; BobAlice");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<a href="{{ path|urlencode }}">{{ path|urlencode("") }}</a> {{ query|urlencode(safe) }}"#,
+    ext = "html"
+)]
+struct UrlencodeSafeFilter<'a> {
+    path: &'a str,
+    query: &'a str,
+    safe: &'a str,
+}
+
+#[test]
+fn test_urlencode_safe_filter() {
+    let t = UrlencodeSafeFilter {
+        path: "/städte/köln am rhein",
+        query: "a=1&b=x y",
+        safe: "=&",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<a href="/st%C3%A4dte/k%C3%B6ln%20am%20rhein">%2Fst%C3%A4dte%2Fk%C3%B6ln%20am%20rhein</a> a=1&amp;b=x%20y"#
+    );
+
+    let t = UrlencodeSafeFilter {
+        path: "",
+        query: "",
+        safe: "ü",
+    };
+    assert!(t.render().is_err());
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"[{{ path|trim("/") }}] [{{ path|trim_start("/") }}] [{{ path|trim_end(sep) }}] [{{ text|trim_start }}] [{{ text|trim_end }}] [{{ text|trim("") }}]"#,
+    ext = "txt"
+)]
+struct TrimCharsFilter<'a> {
+    path: &'a str,
+    sep: String,
+    text: &'a str,
+}
+
+#[test]
+fn test_trim_chars_filter() {
+    let t = TrimCharsFilter {
+        path: "//docs/intro/",
+        sep: "/".into(),
+        text: "\t some text \n",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "[docs/intro] [docs/intro/] [//docs/intro] [some text \n] [\t some text] [some text]"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ code|indent(4) }}|{{ code|indent("\t", true) }}|{{ code|indent(2, blank=true) }}|{{ code|indent(prefix, first=true) }}"#,
+    ext = "txt"
+)]
+struct IndentFilter<'a> {
+    code: &'a str,
+    prefix: &'a str,
+}
+
+#[test]
+fn test_indent_filter() {
+    let t = IndentFilter {
+        code: "fn main() {\n\n}",
+        prefix: "> ",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "fn main() {\n\n    }|\tfn main() {\n\n\t}|fn main() {\n  \n  }|> fn main() {\n\n> }"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ name|capitalize }} {{ name|capitalize(false) }} {{ name|capitalize(lower_rest=keep) }} {{ "ßig"|capitalize }}"#,
+    ext = "txt"
+)]
+struct CapitalizeFilter<'a> {
+    name: &'a str,
+    keep: bool,
+}
+
+#[test]
+fn test_capitalize_filter() {
+    let t = CapitalizeFilter {
+        name: "mcDonald",
+        keep: true,
+    };
+    assert_eq!(t.render().unwrap(), "Mcdonald McDonald Mcdonald SSig");
+}
+
+#[cfg(feature = "time")]
+#[derive(Template)]
+#[template(
+    source = r#"{{ created|date("%Y-%m-%d %H:%M %z") }} {{ day|date("<%d/%m>") }}"#,
+    ext = "html"
+)]
+struct DateFilter {
+    created: time::OffsetDateTime,
+    day: time::Date,
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_date_filter() {
+    use time::{Date, Month, Time, UtcOffset};
+
+    let day = Date::from_calendar_date(2024, Month::March, 9).unwrap();
+    let created = day
+        .with_time(Time::from_hms(18, 5, 0).unwrap())
+        .assume_offset(UtcOffset::from_hms(2, 0, 0).unwrap());
+    let t = DateFilter { created, day };
+    assert_eq!(t.render().unwrap(), "2024-03-09 18:05 +0200 &lt;09/03&gt;");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ size|filesizeformat }} {{ size|filesizeformat(true) }} {{ size|filesizeformat(binary=binary) }}"#,
+    ext = "txt"
+)]
+struct FilesizeFormatFilter {
+    size: u64,
+    binary: bool,
+}
+
+#[test]
+fn test_filesizeformat_filter() {
+    let t = FilesizeFormatFilter {
+        size: 1_536_000,
+        binary: false,
+    };
+    assert_eq!(t.render().unwrap(), "1.54 MB 1.46 MiB 1.54 MB");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ people|map("address.city")|join(", ") }}
+{{ people|map(attribute="age")|join(", ") }}
+{{ words|map(filter="upper")|join(" ") }}
+{% for word in words|map(filter="truncate", args=(2,)) %}{{ word }};{% endfor %}"#,
+    ext = "txt"
+)]
+struct MapPathAndFilter {
+    people: Vec<Person>,
+    words: Vec<&'static str>,
+}
+
+#[test]
+fn test_map_path_and_filter() {
+    let t = MapPathAndFilter {
+        people: vec![
+            Person {
+                name: "Ann",
+                age: 40,
+                address: Address { city: "Berlin" },
+            },
+            Person {
+                name: "Ben",
+                age: 30,
+                address: Address { city: "Paris" },
+            },
+        ],
+        words: vec!["hello", "world"],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Berlin, Paris\n40, 30\nHELLO WORLD\nhe…;wo…;"
+    );
+}
+
+struct Pair {
+    first: &'static str,
+    upper: &'static str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ pairs|map("first")|join(",") }} {{ pairs|map("upper")|join(",") }} {{ pairs|map("first")|map(filter="upper")|join(",") }}"#,
+    ext = "txt"
+)]
+struct MapFieldNamedLikeFilter {
+    pairs: Vec<Pair>,
+}
+
+#[test]
+fn test_map_field_named_like_filter() {
+    let t = MapFieldNamedLikeFilter {
+        pairs: vec![
+            Pair {
+                first: "a",
+                upper: "b",
+            },
+            Pair {
+                first: "c",
+                upper: "d",
+            },
+        ],
+    };
+    assert_eq!(t.render().unwrap(), "a,c b,d A,C");
+}
+
+struct Account {
+    name: &'static str,
+    active: bool,
+    roles: Vec<&'static str>,
+}
+
+impl Account {
+    fn is_admin(&self) -> bool {
+        self.has_role("admin")
+    }
+
+    fn has_role(&self, role: &str) -> bool {
+        self.roles.contains(&role)
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for a in accounts|select("active") %}{{ a.name }};{% endfor %}
+{% for a in accounts|reject("active") %}{{ a.name }};{% endfor %}
+{% for a in accounts|select("is_admin()") %}{{ a.name }};{% endfor %}
+{% for a in accounts|reject("has_role", role) %}{{ a.name }};{% endfor %}
+{{ accounts|select("active")|map("name")|join(", ") }}"#,
+    ext = "txt"
+)]
+struct SelectRejectFilter {
+    accounts: Vec<Account>,
+    role: &'static str,
+}
+
+#[test]
+fn test_select_reject_filter() {
+    let account = |name, active, roles| Account {
+        name,
+        active,
+        roles,
+    };
+    let t = SelectRejectFilter {
+        accounts: vec![
+            account("ann", true, vec!["admin"]),
+            account("ben", false, vec!["editor"]),
+            account("cid", true, vec!["editor", "admin"]),
+        ],
+        role: "editor",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "ann;cid;\nben;\nann;cid;\nann;\nann, cid"
+    );
+}
+
+struct Product {
+    name: &'static str,
+    category: &'static str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for (category, items) in products|groupby("category") -%}
+{{ category }}: {{ items|map("name")|join(", ") }} ({{ items.len() }})
+{% endfor %}"#,
+    ext = "txt"
+)]
+struct GroupbyFilter {
+    products: Vec<Product>,
+}
+
+#[test]
+fn test_groupby_filter() {
+    let product = |name, category| Product { name, category };
+    let t = GroupbyFilter {
+        products: vec![
+            product("pear", "fruit"),
+            product("leek", "vegetable"),
+            product("apple", "fruit"),
+            product("bread", "bakery"),
+        ],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "bakery: bread (1)\nfruit: pear, apple (2)\nvegetable: leek (1)\n"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ people|join(", ", "name") }}|{{ people|join(sep, attribute="address.city") }}|{{ people|sort_by("age")|join("&", "name") }}"#,
+    ext = "html"
+)]
+struct JoinAttributeFilter {
+    people: Vec<Person>,
+    sep: &'static str,
+}
+
+#[test]
+fn test_join_attribute_filter() {
+    let person = |name, age, city| Person {
+        name,
+        age,
+        address: Address { city },
+    };
+    let t = JoinAttributeFilter {
+        people: vec![person("Ann", 40, "Berlin"), person("<Ben>", 30, "Paris")],
+        sep: " & ",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Ann, &lt;Ben&gt;|Berlin &amp; Paris|&lt;Ben&gt;&amp;Ann"
+    );
+}
+
+struct LineItem {
+    price: f64,
+    quantity: u32,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ scores|sum }} {{ scores|min|default("-") }} {{ scores|max|default("-") }} {{ empty|sum }} {{ empty|max|default("-") }} {{ items|sum("price") }} {{ items|sum(attribute="quantity") }} {{ items|max("price")|default(0) }}"#,
+    ext = "txt"
+)]
+struct AggregateFilters {
+    scores: Vec<i32>,
+    empty: Vec<i32>,
+    items: Vec<LineItem>,
+}
+
+#[test]
+fn test_aggregate_filters() {
+    let t = AggregateFilters {
+        scores: vec![7, -2, 10],
+        empty: vec![],
+        items: vec![
+            LineItem {
+                price: 1.5,
+                quantity: 2,
+            },
+            LineItem {
+                price: 2.25,
+                quantity: 3,
+            },
+        ],
+    };
+    assert_eq!(t.render().unwrap(), "15 -2 10 0 - 3.75 5 2.25");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ int|abs }} {{ float|abs }} {{ unsigned|abs }} {{ values|map(filter="abs")|join(",") }}"#,
+    ext = "txt"
+)]
+struct AbsFilter {
+    int: i32,
+    float: f64,
+    unsigned: u64,
+    values: Vec<i64>,
+}
+
+#[test]
+fn test_abs_filter() {
+    let t = AbsFilter {
+        int: i32::MIN,
+        float: -1.25,
+        unsigned: 3,
+        values: vec![-1, 0, 2],
+    };
+    assert_eq!(t.render().unwrap(), "2147483647 1.25 3 1,0,2");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ text|linebreaksbr }}|{{ text|nl2br }}|{{ html|safe|linebreaksbr }}"#,
+    ext = "html"
+)]
+struct LinebreaksbrFilter<'a> {
+    text: &'a str,
+    html: &'a str,
+}
+
+#[test]
+fn test_linebreaksbr_filter() {
+    let t = LinebreaksbrFilter {
+        text: "a < b\r\nc\r\n",
+        html: "<b>x</b>\ny",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "a &lt; b<br/>c|a &lt; b<br/>c|<b>x</b><br/>y"
+    );
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ text|linebreaksbr }}"#, ext = "txt")]
+struct LinebreaksbrText<'a> {
+    text: &'a str,
+}
+
+#[test]
+fn test_linebreaksbr_text() {
+    let t = LinebreaksbrText { text: "a < b\nc" };
+    assert_eq!(t.render().unwrap(), "a < b<br/>c");
+}
+
+struct JsEscaper;
+
+impl askama::Escaper for JsEscaper {
+    fn write_escaped<W: std::fmt::Write>(&self, mut fmt: W, string: &str) -> std::fmt::Result {
+        for c in string.chars() {
+            match c {
+                '"' | '\'' | '\\' | '<' | '>' | '&' => write!(fmt, "\\u{:04x}", c as u32)?,
+                c => fmt.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<a title="{{ s }}" onclick="alert('{{ s|e("js") }}')">{{ s|escape("txt") }}</a>"#,
+    ext = "html",
+    config = "test_js_escaper.toml"
+)]
+struct NamedEscaperFilter<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_named_escaper_filter() {
+    let t = NamedEscaperFilter { s: "it's <b>" };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<a title="it&#x27;s &lt;b&gt;" onclick="alert('it\u0027s \u003cb\u003e')">it's <b></a>"#
+    );
+}
@@ -24,3 +24,40 @@ fn test_if() {
     assert_eq!(If { s: "c" }.render().unwrap(), "c");
     assert_eq!(If { s: "d" }.render().unwrap(), "else");
 }
+
+// The fields used in the dead branches don't exist: these branches must not be generated at all.
+#[derive(Template)]
+#[template(
+    source = r#"{%- if false -%}
+{{ missing }}
+{%- else if s == "a" -%}
+a
+{%- else if true -%}
+{% if false %}{{ missing }}{% else %}true{% endif %}
+{%- else -%}
+{{ missing }}
+{%- endif -%}
+{% if false %}{{ missing }}{% endif %}"#,
+    ext = "txt"
+)]
+struct IfConstant<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_if_constant() {
+    assert_eq!(IfConstant { s: "a" }.render().unwrap(), "a");
+    assert_eq!(IfConstant { s: "b" }.render().unwrap(), "true");
+}
+
+#[derive(Template)]
+#[template(
+    source = "[{% if true %}{% let x = 1 %}{{ x }}{% else if cond %}{{ missing }}{% endif %}]",
+    ext = "txt"
+)]
+struct IfTrue;
+
+#[test]
+fn test_if_true() {
+    assert_eq!(IfTrue.render().unwrap(), "[1]");
+}
@@ -53,3 +53,80 @@ fn test_include_macro() {
 
     assert_eq!(template.render().unwrap(), "Hello, Alice!\nHowdy, Bob!");
 }
+
+#[derive(Template)]
+#[template(
+    source = "<style>\n{%- embed \"embedded.css\" -%}\n</style>",
+    ext = "html"
+)]
+struct EmbedTemplate;
+
+#[test]
+fn test_embed() {
+    assert_eq!(
+        EmbedTemplate.render().unwrap(),
+        "<style>body > p { content: \"{{ not_a_variable }}\"; }\n\
+         /* <b>&</b> {% raw %} */\n</style>"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<ul>{% include "include-list.html" %}
+{% empty %}<li>Nothing here</li>{% endinclude %}</ul>"#,
+    ext = "html"
+)]
+struct IncludeEmptyTemplate<'a> {
+    strs: &'a [&'a str],
+}
+
+#[test]
+fn test_include_empty() {
+    let s = IncludeEmptyTemplate {
+        strs: &["foo", "bar"],
+    };
+    assert_eq!(
+        s.render().unwrap(),
+        "<ul>\n  <li>foo</li>\n\n  <li>bar</li>\n</ul>"
+    );
+
+    // The output of the included template is only whitespace, which counts as empty.
+    let s = IncludeEmptyTemplate { strs: &[] };
+    assert_eq!(s.render().unwrap(), "<ul><li>Nothing here</li></ul>");
+}
+
+// The included template only sees the variables passed with `with`, so it can be included by
+// templates with different fields.
+#[derive(Template)]
+#[template(
+    source = r#"{% include "hello.html" with name = user.name %}
+{% for (i, user) in others.iter().enumerate() -%}
+{% include "hello.html" with name = user ~ i %}
+{% endfor %}
+{%- include "hello.html" with name = "nobody" %}{% empty %}unused{% endinclude %}"#,
+    ext = "html"
+)]
+struct IncludeWith<'a> {
+    user: User<'a>,
+    others: Vec<&'a str>,
+    // Not visible to `hello.html`, which only sees the passed `name`.
+    #[allow(dead_code)]
+    name: &'a str,
+}
+
+struct User<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_include_with() {
+    let t = IncludeWith {
+        user: User { name: "Alice" },
+        others: vec!["<Bob>"],
+        name: "unused",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Hello, Alice!\nHello, &lt;Bob&gt;0!\nHello, nobody!"
+    );
+}
@@ -353,3 +353,174 @@ fn test_named_end() {
     let n = NamedBlocks { title: "title" };
     assert_eq!(n.render().unwrap(), "title\n\ntadam\nCopyright 2017");
 }
+
+#[derive(Template)]
+#[template(path = "scoped-base.html")]
+struct ScopedBase<'a> {
+    rows: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(path = "scoped-child.html")]
+struct ScopedChild<'a> {
+    rows: &'a [&'a str],
+}
+
+#[test]
+fn test_scoped_block() {
+    let t = ScopedBase { rows: &["a", "b"] };
+    assert_eq!(t.render().unwrap(), "[a]\n[b]\n");
+
+    let t = ScopedChild { rows: &["a", "b"] };
+    assert_eq!(t.render().unwrap(), "[1: A (a)]\n[2: B (b)]\n");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "base.html" %}{% block content %}{% block inner %}Hi{% endblock %}{% endblock %}"#,
+    ext = "html",
+    config = "test_strict.toml"
+)]
+struct StrictTemplate {
+    title: &'static str,
+}
+
+#[test]
+fn test_strict_block_overrides() {
+    let t = StrictTemplate { title: "T" };
+    assert_eq!(t.render().unwrap(), "T\nHi\nFoo\nCopyright 2017");
+}
+
+#[test]
+fn test_blocks() {
+    assert_eq!(BaseTemplate::BLOCKS, ["content", "foo"]);
+    assert_eq!(ChildTemplate::BLOCKS, ["content", "foo"]);
+    assert_eq!(DeepKidTemplate::BLOCKS, ["body", "content", "head"]);
+
+    let t: &dyn askama::DynTemplate = &EmptyChild { title: "" };
+    assert_eq!(t.blocks(), ["content", "foo"]);
+}
+
+#[derive(Template)]
+#[template(path = "dynamic-extends.html")]
+struct DynamicExtendsTemplate<'a> {
+    base: &'a str,
+    title: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_dynamic_extends() {
+    let mut t = DynamicExtendsTemplate {
+        base: "dynamic-email-base.html",
+        title: "News",
+        name: "<Alice>",
+    };
+    assert_eq!(t.render().unwrap(), "[email] News: Hello, &lt;Alice&gt;!");
+    t.base = "dynamic-web-base.html";
+    assert_eq!(
+        t.render().unwrap(),
+        "<h1>News</h1>Hello, &lt;Alice&gt;! <footer>web</footer>"
+    );
+    assert_eq!(
+        DynamicExtendsTemplate::BLOCKS,
+        ["content", "footer", "title"]
+    );
+
+    t.base = "base.html";
+    assert_eq!(
+        t.render().unwrap_err().to_string(),
+        r#""base.html" is not one of the parent templates "dynamic-email-base.html", "dynamic-web-base.html""#
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends ("dynamic-email-base.html" if email else "dynamic-web-base.html") in ["dynamic-email-base.html", "dynamic-web-base.html"] %}
+{%- block title %}{% include "dynamic-extends.html" %}{% endblock %}"#,
+    ext = "html"
+)]
+struct DynamicExtendsIncludeTemplate<'a> {
+    email: bool,
+    base: &'a str,
+    title: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_dynamic_extends_include() {
+    let t = DynamicExtendsIncludeTemplate {
+        email: false,
+        base: "dynamic-email-base.html",
+        title: "Hi",
+        name: "Bob",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<h1>[email] Hi: Hello, Bob!</h1> <footer>web</footer>"
+    );
+}
+
+#[derive(Template)]
+#[template(path = "dynamic-extends.html", block = "content")]
+struct DynamicExtendsBlockTemplate<'a> {
+    base: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_dynamic_extends_block() {
+    let t = DynamicExtendsBlockTemplate {
+        base: "dynamic-web-base.html",
+        name: "Carol",
+    };
+    assert_eq!(t.render().unwrap(), "Hello, Carol!");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "fragment-mid-super.html" %}
+{%- block body %}<b>{{ super() }}</b>{% endblock %}"#,
+    ext = "html"
+)]
+struct ExprSuperTemplate;
+
+// `{{ super() }}` renders the block of the parent, which in turn renders the block of its own
+// parent with `{% call super() %}`.
+#[test]
+fn test_expr_super() {
+    assert_eq!(
+        ExprSuperTemplate.render().unwrap(),
+        "<html>\n<head></head>\n<body>\n<b>\n[\n<p>Parent body content</p>\n]\n</b>\n\
+         \n()\n\n</body>\n</html>"
+    );
+}
+
+#[derive(Template)]
+#[template(path = "block-params-base.html")]
+struct BlockParamsBaseTemplate<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_block_params() {
+    let t = BlockParamsBaseTemplate { name: "<Bob>" };
+    assert_eq!(t.render().unwrap(), "<h1>Intro</h1> <h2>&lt;Bob&gt;</h2>");
+}
+
+#[derive(Template)]
+#[template(path = "block-params-child.html")]
+struct BlockParamsChildTemplate<'a> {
+    name: &'a str,
+}
+
+// The override is called with the arguments, uses its own default value, and passes them to
+// its `super()` block.
+#[test]
+fn test_block_params_override() {
+    let t = BlockParamsChildTemplate { name: "Bob" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<div><h3>Intro</h3></div> <div><h2>Bob</h2></div>"
+    );
+}
@@ -73,6 +73,27 @@ fn test_for_range() {
     );
 }
 
+#[derive(Template)]
+#[template(
+    source = "{% for s in strings reversed %}{{ loop.index }}:{{ s }} {% endfor %}
+{%- for i in 1..=5 reversed if i % 2 == 1 %}{{ i }}{% endfor %}
+{%- for reversed in reversed %}{{ reversed }}{% endfor %}",
+    ext = "txt"
+)]
+struct ForReversedTemplate<'a> {
+    strings: &'a [&'a str],
+    reversed: [u8; 2],
+}
+
+#[test]
+fn test_for_reversed() {
+    let t = ForReversedTemplate {
+        strings: &["a", "b", "c"],
+        reversed: [1, 2],
+    };
+    assert_eq!(t.render().unwrap(), "1:c 2:b 3:a 53112");
+}
+
 #[derive(Template)]
 #[template(source = "{% for i in [1, 2, 3] %}{{ i }}{% endfor %}", ext = "txt")]
 struct ForArrayTemplate;
@@ -359,6 +380,23 @@ fn test_loop_continue() {
     assert_eq!(t.render().unwrap(), "x1yx2yx4yx5y");
 }
 
+// A `break` in the first iteration does not count as an empty loop.
+#[derive(Template)]
+#[template(
+    source = "{% for v in values %}{% if loop.first %}{% break %}{% endif %}{{ v }}\
+    {% else %}empty{% endfor %}",
+    ext = "txt"
+)]
+struct BreakElse<'a> {
+    values: &'a [i32],
+}
+
+#[test]
+fn test_loop_break_else() {
+    assert_eq!(BreakElse { values: &[1, 2] }.render().unwrap(), "");
+    assert_eq!(BreakElse { values: &[] }.render().unwrap(), "empty");
+}
+
 #[derive(Template)]
 #[template(path = "for-break-continue.html")]
 struct BreakContinue<'a> {
@@ -445,3 +483,99 @@ fn test_for_in_if() {
     let t = ForInIf { limit: 1 };
     assert_eq!(t.render().unwrap(), ":(");
 }
+
+// The `loop` variables only count the items for which the condition holds.
+#[derive(Template)]
+#[template(
+    source = "{% for i in 0..limit if i % 3 == 0 %}{{ loop.index }}:{{ i }}\
+    {% if !loop.last %},{% endif %}{% endfor %}",
+    ext = "txt"
+)]
+struct ForInIfLoopVars {
+    limit: usize,
+}
+
+#[test]
+fn test_for_in_if_loop_vars() {
+    let t = ForInIfLoopVars { limit: 8 };
+    assert_eq!(t.render().unwrap(), "1:0,2:3,3:6");
+}
+
+macro_rules! unroll_templates {
+    ($unrolled:ident, $looped:ident, $source:literal) => {
+        #[derive(Template)]
+        #[template(source = $source, ext = "txt", config = "test_unroll.toml")]
+        struct $unrolled {
+            n: u32,
+        }
+
+        #[derive(Template)]
+        #[template(source = $source, ext = "txt")]
+        struct $looped {
+            n: u32,
+        }
+    };
+}
+
+unroll_templates!(
+    UnrolledGrid,
+    LoopedGrid,
+    "{% for row in 0..2 %}
+    {%- for col in 1..=3 -%}
+        {{ row * n + col }}{% if !loop.last %},{% endif %}
+    {%- endfor %}
+{% endfor %}"
+);
+
+unroll_templates!(
+    UnrolledLoopVars,
+    LoopedLoopVars,
+    "{% for i in 5..8 +%} {{ loop.index }}:{{ i }}:{{ loop.first }}:{{ loop.last }}:\
+    {{ loop.length }}:{{ loop.cycle([\"a\", \"b\"]) }} {%+ endfor %}|{% for i in 0..n %}{{ i }}{% endfor %}"
+);
+
+unroll_templates!(
+    UnrolledBreak,
+    LoopedBreak,
+    "{% for i in 0..3 %}{% if i == n %}{% break %}{% endif %}{{ i }}{% endfor %}\
+    {% for i in 0..10 %}{{ i }}{% endfor %}"
+);
+
+#[test]
+fn test_unrolled_loops() {
+    assert_eq!(
+        UnrolledGrid { n: 10 }.render().unwrap(),
+        "1,2,3\n11,12,13\n"
+    );
+    assert_eq!(
+        UnrolledGrid { n: 10 }.render().unwrap(),
+        LoopedGrid { n: 10 }.render().unwrap(),
+    );
+    assert_eq!(
+        UnrolledLoopVars { n: 2 }.render().unwrap(),
+        LoopedLoopVars { n: 2 }.render().unwrap(),
+    );
+    assert_eq!(
+        UnrolledBreak { n: 1 }.render().unwrap(),
+        LoopedBreak { n: 1 }.render().unwrap(),
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for row in rows %}{{ loop.index }}/{{ loop.length }}:\
+    {% for c in row.chars() if c.is_alphabetic() %}{{ c }}{{ loop.length }}{% endfor %}\
+    {% if !loop.last %} {% endif %}{% endfor %}",
+    ext = "txt"
+)]
+struct LoopLength<'a> {
+    rows: Vec<&'a str>,
+}
+
+#[test]
+fn test_loop_length() {
+    let t = LoopLength {
+        rows: vec!["a-b", "", "---c"],
+    };
+    assert_eq!(t.render().unwrap(), "1/3:a2b2 2/3: 3/3:c1");
+}
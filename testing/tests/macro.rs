@@ -34,6 +34,19 @@ fn test_import() {
     assert_eq!(t.render().unwrap(), "foo foo foo");
 }
 
+// The imported templates import each other, and only their macros are used.
+#[derive(Template)]
+#[template(
+    source = r#"{% import "import-cycle-a.html" as a %}{% call a::x() %}"#,
+    ext = "html"
+)]
+struct ImportCycleTemplate;
+
+#[test]
+fn test_import_cycle() {
+    assert_eq!(ImportCycleTemplate.render().unwrap(), "xy");
+}
+
 #[derive(Template)]
 #[template(path = "deep-nested-macro.html")]
 struct NestedTemplate;
@@ -170,3 +183,86 @@ struct TrailingComma;
 fn test_trailing_comma() {
     assert_eq!(TrailingComma.render().unwrap(), "hihihihihi");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro price(amount) %}{{ amount }} {{ currency }}{% endmacro -%}
+{% call price(1) %}
+{%- for currency in others %}, {% call price(2) %}{% endfor %}"#,
+    ext = "txt"
+)]
+struct CallerScope<'a> {
+    currency: &'a str,
+    others: Vec<&'a str>,
+}
+
+#[test]
+fn test_caller_scope() {
+    let t = CallerScope {
+        currency: "EUR",
+        others: vec!["USD", "GBP"],
+    };
+    assert_eq!(t.render().unwrap(), "1 EUR, 2 USD, 2 GBP");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% call greet(name) %}!
+{%- macro greet(who) %}Hello, {{ who }}{% endmacro %}"#,
+    ext = "txt"
+)]
+struct CalledBeforeDefinition<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_called_before_definition() {
+    let t = CalledBeforeDefinition { name: "world" };
+    assert_eq!(t.render().unwrap(), "Hello, world!");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro input(name, value = "", kind = "text", id = "id-" ~ name) -%}
+<input id="{{ id }}" name="{{ name }}" type="{{ kind }}" value="{{ value }}">
+{%- endmacro -%}
+{% call input("a") %}
+{% call input("b", v) %}
+{% call input(v, kind = "number") %}
+{% call input("d", id = "x", value = v, kind = "hidden") %}"#,
+    ext = "txt"
+)]
+struct DefaultArguments<'a> {
+    v: &'a str,
+}
+
+#[test]
+fn test_default_arguments() {
+    let t = DefaultArguments { v: "1" };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<input id="id-a" name="a" type="text" value="">
+<input id="id-b" name="b" type="text" value="1">
+<input id="id-1" name="1" type="number" value="">
+<input id="x" name="d" type="hidden" value="1">"#
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro price(amount) uses currency %}{{ amount }} {{ currency }}{% endmacro -%}
+{% for currency in currencies %}{% call price(loop.index) %};{% endfor %}
+{%- let currency = "GBP" %}{% call price(3) %}"#,
+    ext = "txt"
+)]
+struct MacroUses<'a> {
+    currencies: Vec<&'a str>,
+}
+
+#[test]
+fn test_macro_uses() {
+    let t = MacroUses {
+        currencies: vec!["EUR", "USD"],
+    };
+    assert_eq!(t.render().unwrap(), "1 EUR;2 USD;3 GBP");
+}
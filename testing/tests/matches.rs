@@ -224,3 +224,52 @@ fn test_match_enum_or() {
     };
     assert_eq!(template.render().unwrap(), "The card is red\n");
 }
+
+enum Shape<'a> {
+    Circle(u32),
+    Rect { width: u32, height: u32 },
+    Labeled(&'a str, Option<(u32, u32)>),
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for shape in shapes %}
+{%- match shape %}
+    {%- when Shape::Circle(r) if r|deref > 10 %}big circle {{ r }}
+    {%- when Shape::Circle(r) %}circle {{ r }}
+    {%- when Shape::Rect { width, height } if width == height %}square {{ width }}
+    {%- when Shape::Rect with { width, height } %}rect {{ width }}x{{ height }}
+    {%- when Shape::Labeled(label, Some((x, y))) if label.len() > 2 && x != y %}{{ label }} at {{ x }},{{ y }}
+    {%- when Shape::Labeled(label, _) %}{{ label }}
+{%- endmatch %};
+{%- endfor %}"#,
+    ext = "html"
+)]
+struct MatchGuardTemplate<'a> {
+    shapes: Vec<Shape<'a>>,
+}
+
+#[test]
+fn test_match_guard() {
+    let t = MatchGuardTemplate {
+        shapes: vec![
+            Shape::Circle(20),
+            Shape::Circle(5),
+            Shape::Rect {
+                width: 2,
+                height: 2,
+            },
+            Shape::Rect {
+                width: 2,
+                height: 3,
+            },
+            Shape::Labeled("<a>", Some((1, 2))),
+            Shape::Labeled("<a>", Some((1, 1))),
+            Shape::Labeled("b", None),
+        ],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "big circle 20;circle 5;square 2;rect 2x3;&lt;a&gt; at 1,2;&lt;a&gt;;b;"
+    );
+}
@@ -53,3 +53,132 @@ fn test_nested() {
     };
     assert_eq!(t.render().unwrap(), "bar foo");
 }
+
+struct Item {
+    name: &'static str,
+    active: bool,
+    price: u32,
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ items.iter().filter(|item| item.active).count() }} \
+        {{ items.iter().map(|item| item.price * factor).max().unwrap_or(0) }} \
+        {% for name in items.iter().filter(|x| x.price > min).map(|x| x.name) %}{{ name }},{% endfor %}",
+    ext = "txt"
+)]
+struct ClosureTemplate {
+    items: Vec<Item>,
+    factor: u32,
+    min: u32,
+}
+
+#[test]
+fn test_closures() {
+    let t = ClosureTemplate {
+        items: vec![
+            Item {
+                name: "a",
+                active: true,
+                price: 1,
+            },
+            Item {
+                name: "b",
+                active: false,
+                price: 2,
+            },
+            Item {
+                name: "c",
+                active: true,
+                price: 3,
+            },
+        ],
+        factor: 10,
+        min: 1,
+    };
+    assert_eq!(t.render().unwrap(), "2 30 b,c,");
+}
+
+macro_rules! dedup_templates {
+    ($dedup:ident, $plain:ident, $source:literal) => {
+        #[derive(Template)]
+        #[template(source = $source, ext = "txt", config = "test_dedup.toml")]
+        struct $dedup {
+            calls: std::cell::Cell<usize>,
+        }
+
+        #[derive(Template)]
+        #[template(source = $source, ext = "txt")]
+        struct $plain {
+            calls: std::cell::Cell<usize>,
+        }
+
+        impl $dedup {
+            fn name(&self, suffix: &str) -> String {
+                self.calls.set(self.calls.get() + 1);
+                format!("name{suffix}")
+            }
+        }
+
+        impl $plain {
+            fn name(&self, suffix: &str) -> String {
+                self.calls.set(self.calls.get() + 1);
+                format!("name{suffix}")
+            }
+        }
+    };
+}
+
+dedup_templates!(
+    DedupCalls,
+    RepeatedCalls,
+    r#"{{ self.name("") }} {{ self.name("") }} {{ self.name("!") }} {{ self.name("") }}
+    {%- if true %} {{ self.name("") }}{% endif %}"#
+);
+
+#[test]
+fn test_dedup_exprs() {
+    let t = RepeatedCalls {
+        calls: Default::default(),
+    };
+    assert_eq!(t.render().unwrap(), "name name name! name name");
+    assert_eq!(t.calls.get(), 5);
+
+    // The call written in the `if` block uses the value evaluated for the enclosing scope.
+    let t = DedupCalls {
+        calls: Default::default(),
+    };
+    assert_eq!(t.render().unwrap(), "name name name! name name");
+    assert_eq!(t.calls.get(), 2);
+}
+
+dedup_templates!(
+    DedupScopedCalls,
+    RepeatedScopedCalls,
+    r#"{% for i in 0..3 %}{{ self.name("") }}{{ self.name("") }} {% endfor %}
+    {%- if self.calls.get() > 10 %}{{ self.name("!") }}{% else %}{{ self.name("?") }}{% endif %}
+    {%- block content %} {{ self.name("") }} {{ self.name("") }}{% endblock %}"#
+);
+
+#[test]
+fn test_dedup_exprs_scopes() {
+    let t = RepeatedScopedCalls {
+        calls: Default::default(),
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "namename namename namename name? name name"
+    );
+    assert_eq!(t.calls.get(), 9);
+
+    // The calls are evaluated once per iteration of the loop, and the calls which are only
+    // written in nested blocks, like `self.name("!")`, are not evaluated before their block.
+    let t = DedupScopedCalls {
+        calls: Default::default(),
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "namename namename namename name? name name"
+    );
+    assert_eq!(t.calls.get(), 5);
+}
@@ -61,3 +61,78 @@ fn test_short_circuit() {
     let t = ShortCircuitTemplate {};
     assert_eq!(t.render().unwrap(), "truetrue");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ "Hello, " ~ name ~ "!" }} {{ n ~ n + 1 }} {% if name ~ n == "<b>2" %}eq{% endif %}
+{%- let id = "user-" ~ n %} {{ id }}"#,
+    ext = "html"
+)]
+struct ConcatTemplate<'a> {
+    name: &'a str,
+    n: u32,
+}
+
+#[test]
+fn test_concat() {
+    let t = ConcatTemplate { name: "<b>", n: 2 };
+    assert_eq!(t.render().unwrap(), "Hello, &lt;b&gt;! 23 eq user-2");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ tag|safe ~ name ~ "</b>"|safe }} {{ name|e ~ "&" }} {{ (tag ~ name)|safe }}
+{%- let both = tag|safe ~ name %} {{ both }}"#,
+    ext = "html"
+)]
+struct ConcatEscapeTemplate<'a> {
+    tag: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_concat_escape() {
+    let t = ConcatEscapeTemplate {
+        tag: "<b>",
+        name: "a&b",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<b>a&amp;b</b> a&amp;b&amp; <b>a&b &lt;b&gt;a&amp;b"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"<li class="{{ "active" if active else "inactive" }}">{{ name if active else "<none>" }}</li>
+{{- " " ~ n if n > 1 else " -" ~ "" }} {{ "one" if n == 1 else "two" if n == 2 else "many" }}
+{%- let id = n + 1 if active else 0 %} {{ id + 1 }} {{ (name if active else "<i>")|safe }}"#,
+    ext = "html"
+)]
+struct IfElseTemplate<'a> {
+    name: &'a str,
+    active: bool,
+    n: u32,
+}
+
+#[test]
+fn test_if_else_expr() {
+    let t = IfElseTemplate {
+        name: "<b>",
+        active: true,
+        n: 2,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<li class="active">&lt;b&gt;</li> 2 two 4 <b>"#
+    );
+    let t = IfElseTemplate {
+        name: "<b>",
+        active: false,
+        n: 1,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<li class="inactive">&lt;none&gt;</li> - one 1 <i>"#
+    );
+}
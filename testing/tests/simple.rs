@@ -42,6 +42,37 @@ fn test_escape() {
     assert_eq!(s.render().unwrap(), "Hello, &lt;&gt;&amp;&quot;&#x27;!");
 }
 
+struct SafeHtml(&'static str);
+
+impl std::fmt::Display for SafeHtml {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl askama::HtmlSafe for SafeHtml {}
+
+#[derive(Template)]
+#[template(
+    source = "{{ safe }} {{ unsafe_ }} {% for s in list %}{{ s }}{% endfor %}",
+    ext = "html"
+)]
+struct HtmlSafeTemplate<'a> {
+    safe: SafeHtml,
+    unsafe_: &'a str,
+    list: Vec<SafeHtml>,
+}
+
+#[test]
+fn test_html_safe() {
+    let s = HtmlSafeTemplate {
+        safe: SafeHtml("<b>bold</b>"),
+        unsafe_: "<i>",
+        list: vec![SafeHtml("<br>")],
+    };
+    assert_eq!(s.render().unwrap(), "<b>bold</b> &lt;i&gt; <br>");
+}
+
 #[derive(Template)]
 #[template(path = "simple-no-escape.txt")]
 struct VariablesTemplateNoEscape<'a> {
@@ -367,6 +398,20 @@ fn test_comment() {
     assert_eq!(t.render().unwrap(), "  ");
 }
 
+#[derive(Template)]
+#[template(source = "<p>{# debug: hello.html -#} </p>{# note #}", ext = "html")]
+struct DebugCommentTemplate;
+
+#[test]
+fn test_debug_comment() {
+    let expected = if cfg!(feature = "debug-comments") {
+        "<p><!-- hello.html --></p>"
+    } else {
+        "<p></p>"
+    };
+    assert_eq!(DebugCommentTemplate.render().unwrap(), expected);
+}
+
 #[derive(Template)]
 #[template(source = "{% if !foo %}Hello{% endif %}", ext = "txt")]
 struct NegationTemplate {
@@ -447,6 +492,24 @@ fn test_raw_ws() {
     assert_eq!(template.render().unwrap(), "<{{hello}}>\n<{{bye}}>");
 }
 
+#[derive(Template)]
+#[template(
+    source = "{% raw %}<b>{{ vue }}</b> {% raw %}{{ nested }}{%- endraw %} &{% endraw %} {{ s }}",
+    ext = "html"
+)]
+struct RawNestedTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_raw_nested() {
+    let template = RawNestedTemplate { s: "<&>" };
+    assert_eq!(
+        template.render().unwrap(),
+        "<b>{{ vue }}</b> {% raw %}{{ nested }}{%- endraw %} & &lt;&amp;&gt;"
+    );
+}
+
 mod without_import_on_derive {
     #[derive(askama::Template)]
     #[template(source = "foo", ext = "txt")]
@@ -531,3 +594,212 @@ fn test_referenced() {
     assert_eq!(template_to_string(&template), "Hello, person!");
     assert_eq!(template_to_string(template), "Hello, person!");
 }
+
+struct CrlfEscaper;
+
+impl askama::Escaper for CrlfEscaper {
+    const FINALIZES: bool = true;
+
+    fn write_escaped<W: std::fmt::Write>(&self, mut fmt: W, string: &str) -> std::fmt::Result {
+        fmt.write_str(string)
+    }
+
+    fn finalize(&self, output: &mut String) {
+        *output = output.replace('\n', "\r\n");
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ a }}\n{{ b }}",
+    ext = "crlf",
+    config = "test_escaper.toml"
+)]
+struct FinalizeTemplate<'a> {
+    a: &'a str,
+    b: &'a str,
+}
+
+#[test]
+fn test_escaper_finalize() {
+    let t = FinalizeTemplate { a: "1", b: "2\n3" };
+    assert_eq!(t.render().unwrap(), "1\r\n2\r\n3");
+    assert_eq!(t.to_string(), t.render().unwrap());
+
+    let mut buf = String::new();
+    t.render_into(&mut buf).unwrap();
+    assert_eq!(buf, "1\r\n2\r\n3");
+    let mut bytes = Vec::new();
+    t.write_into(&mut bytes).unwrap();
+    assert_eq!(bytes, b"1\r\n2\r\n3");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ a }} {{{ a }}} {{{- b|upper -}}} {{ b }}",
+    ext = "html",
+    config = "test_raw_expr.toml",
+    syntax = "mustache"
+)]
+struct RawExprTemplate<'a> {
+    a: &'a str,
+    b: &'a str,
+}
+
+#[test]
+fn test_raw_expr() {
+    let t = RawExprTemplate { a: "<b>", b: "&" };
+    assert_eq!(t.render().unwrap(), "&lt;b&gt; <b>&&amp;");
+}
+
+#[derive(Template)]
+#[template(source = "{% for item in items %}{{ item }},{% endfor %}", ext = "txt")]
+struct LimitedTemplate {
+    items: Vec<u32>,
+}
+
+#[test]
+fn test_render_into_limited() {
+    let t = LimitedTemplate {
+        items: vec![1, 2, 3],
+    };
+    let mut buf = String::new();
+    t.render_into_limited(&mut buf, 6).unwrap();
+    assert_eq!(buf, "1,2,3,");
+
+    let mut buf = String::new();
+    let err = t.render_into_limited(&mut buf, 5).unwrap_err();
+    let askama::Error::Custom(custom) = &err else {
+        panic!("expected a custom error, got {err:?}");
+    };
+    assert_eq!(
+        custom.downcast_ref::<askama::LimitExceeded>(),
+        Some(&askama::LimitExceeded(5))
+    );
+    assert_eq!(err.to_string(), "output exceeds the limit of 5 bytes");
+    assert_eq!(buf, "1,2,3");
+}
+
+struct BoundedWriter {
+    buf: String,
+    max_writes: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct TooManyWrites(usize);
+
+impl askama::FallibleWrite for BoundedWriter {
+    type Error = TooManyWrites;
+
+    fn write_str(&mut self, s: &str) -> Result<(), TooManyWrites> {
+        if self.max_writes == 0 {
+            return Err(TooManyWrites(self.buf.len()));
+        }
+        self.max_writes -= 1;
+        self.buf.push_str(s);
+        Ok(())
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for item? in self.items() %}{{ item }},{% endfor %}",
+    ext = "txt"
+)]
+struct FallibleTemplate {
+    items: Vec<Result<u32, &'static str>>,
+}
+
+impl FallibleTemplate {
+    fn items(&self) -> impl Iterator<Item = Result<u32, &'static str>> + '_ {
+        self.items.iter().copied()
+    }
+}
+
+#[test]
+fn test_render_into_fallible() {
+    let t = FallibleTemplate {
+        items: vec![Ok(1), Ok(2)],
+    };
+    let mut writer = BoundedWriter {
+        buf: String::new(),
+        max_writes: 10,
+    };
+    t.render_into_fallible(&mut writer).unwrap();
+    assert_eq!(writer.buf, "1,2,");
+
+    let mut writer = BoundedWriter {
+        buf: String::new(),
+        max_writes: 1,
+    };
+    let err = t.render_into_fallible(&mut writer).unwrap_err();
+    assert!(matches!(err, askama::RenderError::Write(TooManyWrites(1))));
+
+    let t = FallibleTemplate {
+        items: vec![Ok(1), Err("oops")],
+    };
+    let mut writer = BoundedWriter {
+        buf: String::new(),
+        max_writes: 10,
+    };
+    let err = t.render_into_fallible(&mut writer).unwrap_err();
+    assert!(matches!(
+        err,
+        askama::RenderError::Render(askama::Error::Custom(_))
+    ));
+}
+
+#[test]
+fn test_template_path() {
+    assert_eq!(VariablesTemplate::TEMPLATE_PATH, Some("simple.html"));
+    assert_eq!(ConstTemplate::TEMPLATE_PATH, None);
+
+    let t: &dyn askama::DynTemplate = &VariablesTemplate {
+        strvar: "foo",
+        num: 42,
+        i18n: "Iñtërnâtiônàlizætiøn".to_string(),
+    };
+    assert_eq!(t.template_path(), Some("simple.html"));
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ s }}|{{ s|safe }}|{{ s|escape }}|{{ s|escape(\"html\") }}|{{ n }}",
+    ext = "html"
+)]
+struct RuntimeEscaperTemplate<'a> {
+    s: &'a str,
+    n: u32,
+}
+
+struct Shouting;
+
+impl askama::Escaper for Shouting {
+    const FINALIZES: bool = true;
+
+    fn write_escaped<W: std::fmt::Write>(&self, mut fmt: W, string: &str) -> std::fmt::Result {
+        fmt.write_str(&string.to_uppercase())
+    }
+
+    fn finalize(&self, output: &mut String) {
+        output.push('!');
+    }
+}
+
+#[test]
+fn test_render_with_escaper() {
+    let t = RuntimeEscaperTemplate { s: "<b>", n: 1 };
+    assert_eq!(t.render().unwrap(), "&lt;b&gt;|<b>|&lt;b&gt;|&lt;b&gt;|1");
+    assert_eq!(
+        t.render_with_escaper(askama::Text).unwrap(),
+        "<b>|<b>|<b>|&lt;b&gt;|1"
+    );
+    assert_eq!(
+        t.render_with_escaper(Shouting).unwrap(),
+        "<B>|<b>|<B>|&lt;b&gt;|1!"
+    );
+
+    let mut buf = String::new();
+    t.render_into_with_escaper(&mut buf, askama::Text).unwrap();
+    assert_eq!(buf, "<b>|<b>|<b>|&lt;b&gt;|1");
+}
@@ -67,3 +67,148 @@ fn fail_str() {
     };
     assert_eq!(template.render().unwrap(), "hello world");
 }
+
+struct Config(std::collections::HashMap<&'static str, &'static str>);
+
+impl Config {
+    fn get(&self, key: &str) -> Result<&'static str, String> {
+        self.0
+            .get(key)
+            .copied()
+            .ok_or_else(|| format!("missing key {key:?}"))
+    }
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ config.get("key")? }}"#, ext = "txt")]
+struct MethodTemplate {
+    config: Config,
+}
+
+#[test]
+fn fail_method() {
+    let template = MethodTemplate {
+        config: Config([("other", "value")].into_iter().collect()),
+    };
+    assert!(matches!(template.render(), Err(askama::Error::Custom(_))));
+    assert_eq!(
+        format!("{}", &template.render().unwrap_err()),
+        r#"missing key "key""#
+    );
+
+    let template = MethodTemplate {
+        config: Config([("key", "value")].into_iter().collect()),
+    };
+    assert_eq!(template.render().unwrap(), "value");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for n? in self.numbers() %}{{ loop.index }}:{{ n }} {% endfor %}",
+    ext = "txt"
+)]
+struct ForTryTemplate<'a> {
+    numbers: &'a [&'a str],
+}
+
+impl ForTryTemplate<'_> {
+    fn numbers(&self) -> impl Iterator<Item = Result<i32, std::num::ParseIntError>> + '_ {
+        self.numbers.iter().map(|s| s.parse())
+    }
+}
+
+#[test]
+fn test_for_try() {
+    let template = ForTryTemplate {
+        numbers: &["1", "2", "3"],
+    };
+    assert_eq!(template.render().unwrap(), "1:1 2:2 3:3 ");
+
+    let template = ForTryTemplate {
+        numbers: &["1", "x", "3"],
+    };
+    assert_eq!(
+        format!("{}", &template.render().unwrap_err()),
+        "invalid digit found in string"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for n? in self.numbers() if n % 2 == 1 %}{{ n }} {% endfor %}",
+    ext = "txt"
+)]
+struct ForTryIfTemplate<'a> {
+    numbers: &'a [&'a str],
+}
+
+impl ForTryIfTemplate<'_> {
+    fn numbers(&self) -> impl Iterator<Item = Result<i32, std::num::ParseIntError>> + '_ {
+        self.numbers.iter().map(|s| s.parse())
+    }
+}
+
+#[test]
+fn test_for_try_if() {
+    let template = ForTryIfTemplate {
+        numbers: &["1", "2", "3"],
+    };
+    assert_eq!(template.render().unwrap(), "1 3 ");
+
+    let template = ForTryIfTemplate {
+        numbers: &["1", "2", "x"],
+    };
+    assert!(matches!(template.render(), Err(askama::Error::Custom(_))));
+}
+
+struct Address {
+    city: String,
+    zip: Option<u32>,
+}
+
+impl Address {
+    fn street(&self) -> Option<&str> {
+        None
+    }
+}
+
+struct User {
+    address: Option<Address>,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ user.address?.city|default("-") }} {{ user.address?.zip?.to_string()|default("-") }}
+{%- if let Some(city) = user.address?.city %} {{ city }}{% endif %}
+{%- for u in users %} {{ u.address?.street()|default("?") }}{% endfor %}"#,
+    ext = "txt"
+)]
+struct OptionalChainTemplate {
+    user: User,
+    users: Vec<User>,
+}
+
+#[test]
+fn test_optional_chain() {
+    let t = OptionalChainTemplate {
+        user: User { address: None },
+        users: vec![User { address: None }],
+    };
+    assert_eq!(t.render().unwrap(), "- - ?");
+
+    let t = OptionalChainTemplate {
+        user: User {
+            address: Some(Address {
+                city: "Paris".into(),
+                zip: Some(75001),
+            }),
+        },
+        users: vec![User {
+            address: Some(Address {
+                city: "Rome".into(),
+                zip: None,
+            }),
+        }],
+    };
+    assert_eq!(t.render().unwrap(), "Paris 75001 Paris ?");
+}
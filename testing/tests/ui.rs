@@ -20,16 +20,20 @@ fn ui() {
             panic!("failed to create folder `{}`: {err:?}", target.display());
         }
     }
-    let target = target.canonicalize().unwrap().join("templates");
-    if target.exists() {
-        return;
-    }
-    let original = Path::new(&manifest_dir).join("templates");
-    if symlink(&original, &target).is_err() {
-        panic!(
-            "failed to create to create link on `{}` as `{}`",
-            original.display(),
-            target.display()
-        );
+    // The configuration of the tests of strict mode is linked as well.
+    let target = target.canonicalize().unwrap();
+    for name in ["templates", "test_strict.toml"] {
+        let target = target.join(name);
+        if target.exists() {
+            continue;
+        }
+        let original = Path::new(&manifest_dir).join(name);
+        if symlink(&original, &target).is_err() {
+            panic!(
+                "failed to create to create link on `{}` as `{}`",
+                original.display(),
+                target.display()
+            );
+        }
     }
 }
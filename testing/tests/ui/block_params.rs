@@ -0,0 +1,51 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "block-params-base.html" %}{% block card(heading) %}{% endblock %}"#,
+    ext = "html"
+)]
+struct Renamed;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "block-params-base.html" %}{% block card %}{% endblock %}"#,
+    ext = "html"
+)]
+struct NoParams;
+
+#[derive(Template)]
+#[template(
+    source = "{% block card(title) %}{{ title }}{% endblock %}{% call card() %}",
+    ext = "html"
+)]
+struct MissingArgument;
+
+#[derive(Template)]
+#[template(
+    source = "{% block card(title) %}{{ title }}{% endblock %}{% call card(1, 2) %}",
+    ext = "html"
+)]
+struct TooManyArguments;
+
+#[derive(Template)]
+#[template(source = "{% block card %}{% endblock %}{% call card() %}", ext = "html")]
+struct CallWithoutParams;
+
+#[derive(Template)]
+#[template(
+    source = "{% block card(title) %}{% call card(title) %}{% endblock %}{% call card(1) %}",
+    ext = "html"
+)]
+struct Recursive;
+
+#[derive(Template)]
+#[template(path = "block-params-base.html", block = "card")]
+struct Fragment;
+
+#[derive(Template)]
+#[template(source = "{% block card(title) scoped %}{% endblock %}", ext = "html")]
+struct Scoped;
+
+fn main() {
+}
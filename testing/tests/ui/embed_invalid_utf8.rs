@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{% embed "invalid-utf8.txt" %}"#, ext = "txt")]
+struct EmbedInvalidUtf8;
+
+fn main() {
+}
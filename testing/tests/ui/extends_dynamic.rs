@@ -0,0 +1,14 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{% extends "dynamic-extends.html" %}"#, ext = "html")]
+struct ExtendsDynamic;
+
+#[derive(Template)]
+#[template(source = r#"{% extends base in [] %}"#, ext = "html")]
+struct NoCandidates<'a> {
+    base: &'a str,
+}
+
+fn main() {
+}
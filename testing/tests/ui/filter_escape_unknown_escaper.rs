@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ "a"|e("js") }}"#, ext = "html")]
+struct UnknownEscaper;
+
+fn main() {
+}
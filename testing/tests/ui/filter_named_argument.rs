@@ -0,0 +1,12 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ "a"|center(width=4) }}"#, ext = "txt")]
+struct NamedArgumentNotSupported;
+
+#[derive(Template)]
+#[template(source = r#"{{ 1.5|format_float(2, round=true) }}"#, ext = "txt")]
+struct UnknownNamedArgument;
+
+fn main() {
+}
@@ -0,0 +1,12 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% for row in rows %}{% block row scoped %}{{ row }}{% endblock %}{% endfor %}",
+    ext = "txt",
+    block = "row"
+)]
+struct ScopedFragment;
+
+fn main() {
+}
@@ -0,0 +1,10 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{% include "hello.html" with user = name %}"#, ext = "html")]
+struct MissingVariable<'a> {
+    name: &'a str,
+}
+
+fn main() {
+}
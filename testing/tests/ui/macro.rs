@@ -23,5 +23,20 @@ struct InvalidNumberOfArgs2;
 {%- call thrice(1, 2) -%}", ext = "html")]
 struct InvalidNumberOfArgs3;
 
+#[derive(Template)]
+#[template(source = "{%- macro thrice(param, param2 = 2) -%}
+{{ param }} {{ param2 }}
+{%- endmacro -%}
+
+{%- call thrice(param2 = 3) -%}", ext = "html")]
+struct MissingArgument;
+
+#[derive(Template)]
+#[template(source = "{%- macro thrice(param, param2 = 2) -%}
+{%- endmacro -%}
+
+{%- call thrice(1, 2, 3) -%}", ext = "html")]
+struct TooManyArguments;
+
 fn main() {
 }
@@ -0,0 +1,19 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{%- macro countdown(n) -%}
+{{ n }}{% if n > 0 %}{% call countdown(n - 1) %}{% endif %}
+{%- endmacro -%}
+
+{%- call countdown(3) -%}", ext = "html")]
+struct RecursiveMacro;
+
+#[derive(Template)]
+#[template(source = "{%- macro ping() %}{% call pong() %}{% endmacro -%}
+{%- macro pong() %}{% call ping() %}{% endmacro -%}
+
+{%- call ping() -%}", ext = "html")]
+struct MutuallyRecursiveMacros;
+
+fn main() {
+}
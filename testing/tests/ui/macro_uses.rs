@@ -0,0 +1,22 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro price(amount) uses currency %}{{ amount }} {{ currency }} {{ rate }}{% endmacro -%}
+{% for currency in currencies %}{% let rate = 2 %}{% call price(1) %}{% endfor %}"#,
+    ext = "txt"
+)]
+struct Undeclared<'a> {
+    currencies: Vec<&'a str>,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro price(amount) uses currency %}{{ amount }} {{ currency }}{% endmacro -%}
+{% call price(1) %}"#,
+    ext = "txt"
+)]
+struct NotAVariable;
+
+fn main() {
+}
@@ -0,0 +1,16 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ words|map(filter="shout")|join(" ") }}"#, ext = "txt")]
+struct UnknownFilter<'a> {
+    words: &'a [&'a str],
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ words|map(filter="truncate", length=2)|join(" ") }}"#, ext = "txt")]
+struct UnknownArgument<'a> {
+    words: &'a [&'a str],
+}
+
+fn main() {
+}
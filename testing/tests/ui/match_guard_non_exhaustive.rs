@@ -0,0 +1,13 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% match n %}{% when Some(n) if n|deref > 0 %}{{ n }}{% when None %}{% endmatch %}",
+    ext = "txt"
+)]
+struct NonExhaustive {
+    n: Option<u32>,
+}
+
+fn main() {
+}
@@ -0,0 +1,10 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{{ name?.len() }}", ext = "txt")]
+struct NotAnOption {
+    name: String,
+}
+
+fn main() {
+}
@@ -0,0 +1,16 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ items|sort_by("1st")|join(",") }}"#, ext = "txt")]
+struct LeadingDigit<'a> {
+    items: &'a [(u32, u32)],
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ items|sort_by("address.straße")|join(",") }}"#, ext = "txt")]
+struct NonAscii<'a> {
+    items: &'a [(u32, u32)],
+}
+
+fn main() {
+}
@@ -0,0 +1,14 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% extends \"base.html\" %}\n{% block contnet %}Hi{% endblock %}",
+    ext = "html",
+    config = "test_strict.toml"
+)]
+struct UnknownBlock {
+    title: &'static str,
+}
+
+fn main() {
+}
@@ -0,0 +1,28 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro greet(name) %}Hi {{ name }}{% endmacro %}\n{% call gret(\"you\") %}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct UnknownMacro;
+
+#[derive(Template)]
+#[template(
+    source = "{% import \"macro.html\" as scope %}\n{% call scope::missing() %}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct UnknownScopedMacro;
+
+#[derive(Template)]
+#[template(
+    source = "{% call other::thrice(2) %}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct UnknownScope;
+
+fn main() {
+}
@@ -0,0 +1,28 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    source = "{{ s|uper }}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct UnknownFilter<'a> {
+    s: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% filter lowre %}\n  Hi\n{% endfilter %}",
+    ext = "txt",
+    config = "test_strict.toml"
+)]
+struct UnknownFilterBlock;
+
+#[derive(Template)]
+#[template(path = "strict_unknown_filter.html", config = "test_strict.toml")]
+struct UnknownFilterInFile<'a> {
+    s: &'a str,
+}
+
+fn main() {
+}
@@ -0,0 +1,8 @@
+use askama::Template;
+
+#[derive(Template)]
+#[template(source = "{% block body %}{{ super() }}{% endblock %}", ext = "html")]
+struct SuperWithoutParentBlock;
+
+fn main() {
+}
@@ -131,3 +131,123 @@ fn test_decl_assign_range() {
     let t = DeclAssignRange;
     assert_eq!(t.render().unwrap(), "1");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{% let greeting = "hi" %}{% for (i, item) in items.iter().enumerate() %}{% if i == 1 %}{% debug %}{% endif %}{% endfor %}
+{%- macro dump(arg) %}{% debug %}{% endmacro %}{% call dump(1 - 2) %}"#,
+    ext = "html"
+)]
+struct DebugTemplate<'a> {
+    items: &'a [&'a str],
+}
+
+#[test]
+fn test_debug() {
+    let t = DebugTemplate {
+        items: &["a", "b--c"],
+    };
+    if cfg!(feature = "debug") {
+        assert_eq!(
+            t.render().unwrap(),
+            "<!-- debug:\ngreeting = \"hi\"\ni = 1\nitem = \"b- -c\"\n-->\
+            <!-- debug:\narg = -1\ngreeting = \"hi\"\n-->"
+        );
+    } else {
+        assert_eq!(t.render().unwrap(), "");
+    }
+}
+
+#[derive(Template)]
+#[template(source = "{% debug %}", ext = "txt")]
+struct EmptyDebugTemplate;
+
+#[test]
+fn test_debug_empty() {
+    if cfg!(feature = "debug") {
+        assert_eq!(
+            EmptyDebugTemplate.render().unwrap(),
+            "<!-- debug: no variables in scope\n-->"
+        );
+    } else {
+        assert_eq!(EmptyDebugTemplate.render().unwrap(), "");
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% set status %}{% if x %}on{% elif y %}maybe{% else %}off{% endif %}{% endset %}\
+              [{{ status }}] {% if status == \"off\" %}({{ status.len() }}){% endif %}",
+    ext = "txt"
+)]
+struct SetBlockTemplate {
+    x: bool,
+    y: bool,
+}
+
+#[test]
+fn test_set_block() {
+    let t = SetBlockTemplate { x: true, y: true };
+    assert_eq!(t.render().unwrap(), "[on] ");
+    let t = SetBlockTemplate { x: false, y: true };
+    assert_eq!(t.render().unwrap(), "[maybe] ");
+    let t = SetBlockTemplate { x: false, y: false };
+    assert_eq!(t.render().unwrap(), "[off] (3)");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% set list -%}
+        {% for item in items %}{% let n = loop.index %}{{ n }}. {{ item }} {% endfor %}
+    {%- endset %}{% let n = list.len() %}<{{ list }}>{{ n }}",
+    ext = "html"
+)]
+struct LetBlockTemplate<'a> {
+    items: &'a [&'a str],
+}
+
+#[test]
+fn test_let_block() {
+    let t = LetBlockTemplate {
+        items: &["a", "<b>"],
+    };
+    assert_eq!(t.render().unwrap(), "<1. a 2. &lt;b&gt; >18");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% let s %}{% if cond %}{% let s = \"yes\" %}{% else %}{% let s = \"no\" %}{% endif %}{{ s }}",
+    ext = "txt"
+)]
+struct LetDeclNotBlockTemplate {
+    cond: bool,
+}
+
+#[test]
+fn test_let_decl_not_block() {
+    assert_eq!(
+        LetDeclNotBlockTemplate { cond: false }.render().unwrap(),
+        "no"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% set x = 1 %}{% set x = x + 1 %}{{ x }}\
+              {% if cond %}{% set x = 10 %}{% set name = \"inner\" %}{{ x }} {{ name }}{% endif %} \
+              {{ x }} {{ name }}",
+    ext = "txt"
+)]
+struct SetScope<'a> {
+    cond: bool,
+    name: &'a str,
+}
+
+#[test]
+fn test_set_scope() {
+    let t = SetScope {
+        cond: true,
+        name: "field",
+    };
+    assert_eq!(t.render().unwrap(), "210 inner 2 field");
+}
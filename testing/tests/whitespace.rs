@@ -140,3 +140,29 @@ fn test_template_whitespace_config() {
     );
     test_template_ws_config!("test_minimize.toml", "suppress", "\n1{# #}\n\n\n2", "\n12");
 }
+
+#[test]
+fn test_inline_whitespace_overrides_config() {
+    // Inline markers win over the `whitespace` argument, which wins over the configuration.
+    test_template_ws_config!(
+        "test_trim.toml",
+        "suppress",
+        " 1 {%+ if true +%} 2 {% endif %} 3",
+        " 1  23"
+    );
+    test_template_ws_config!(
+        "test_minimize.toml",
+        "preserve",
+        " 1 {%- if true ~%}\n\n2\n{% endif %} 3",
+        " 1\n2\n 3"
+    );
+    test_template_ws_config!(
+        "test_trim.toml",
+        "minimize",
+        " 1  {%+ if true -%} 2 {%~ endif %}  3",
+        " 1  2  3"
+    );
+    // When two markers apply to the same whitespace, `-` wins over `~`, which wins over `+`.
+    test_template!(" 1 {{ 1 -}} {{+ 2 }} 3", " 1 12 3");
+    test_template!(" 1 {{ 1 +}} \n {{~ 2 }} 3", " 1 1\n2 3");
+}